@@ -0,0 +1,246 @@
+//! The char-vector tokenizer that `tokenize_bytes` replaced, kept as it was
+//! with default options so the benchmark compares the old code with the new.
+//! It copies every lexeme into a `String`.
+use json_formatter::tokenizer::{Position, Span, TokenizeErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    LeftBracket,
+    RightBracket,
+    LeftSquareBracket,
+    RightSquareBracket,
+    Commma,
+    Colorn,
+    Str(String),
+    Num(String),
+    Bool(String),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+struct Cursor {
+    count: usize,
+    position: Position,
+}
+
+impl Cursor {
+    fn advance(&mut self, str_vec: &[char], count: usize) -> Position {
+        while self.count < count && self.count < str_vec.len() {
+            let ch = str_vec[self.count];
+            self.position.offset += ch.len_utf8();
+            if ch == '\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+            self.count += 1;
+        }
+        return self.position;
+    }
+}
+
+pub fn tokenize(str_vec: Vec<char>) -> Result<Vec<Token>, TokenizeErrorKind> {
+    let mut vec: Vec<Token> = Vec::new();
+    let mut count = 0;
+    let mut cursor = Cursor {
+        count: 0,
+        position: Position::default(),
+    };
+    if str_vec.first() == Some(&'\u{feff}') {
+        count += 1;
+    }
+    while count < str_vec.len() {
+        let start = cursor.advance(&str_vec, count);
+        let kind = match str_vec[count] {
+            '{' => {
+                count += 1;
+                TokenKind::LeftBracket
+            }
+            '}' => {
+                count += 1;
+                TokenKind::RightBracket
+            }
+            '[' => {
+                count += 1;
+                TokenKind::LeftSquareBracket
+            }
+            ']' => {
+                count += 1;
+                TokenKind::RightSquareBracket
+            }
+            ',' => {
+                count += 1;
+                TokenKind::Commma
+            }
+            ':' => {
+                count += 1;
+                TokenKind::Colorn
+            }
+            '"' => distinguish_string(&str_vec, &mut count)?,
+            'f' => distinguish_word(&str_vec, &mut count, "false", TokenizeErrorKind::NotFalse)?,
+            't' => distinguish_word(&str_vec, &mut count, "true", TokenizeErrorKind::NotTrue)?,
+            'n' => distinguish_word(&str_vec, &mut count, "null", TokenizeErrorKind::NotNull)?,
+            '-' | '0'..='9' => distinguish_number(&str_vec, &mut count)?,
+            ch if ch.is_whitespace() => {
+                while count < str_vec.len() && str_vec[count].is_whitespace() {
+                    count += 1;
+                }
+                continue;
+            }
+            _ => return Err(TokenizeErrorKind::InvalidCharactar),
+        };
+        let end = cursor.advance(&str_vec, count);
+        vec.push(Token {
+            kind,
+            span: Span { start, end },
+        });
+    }
+    return Ok(vec);
+}
+
+fn distinguish_string(str_vec: &[char], count: &mut usize) -> Result<TokenKind, TokenizeErrorKind> {
+    let mut buf = String::new();
+    *count += 1;
+    while *count < str_vec.len() {
+        if str_vec[*count] == '"' {
+            *count += 1;
+            return Ok(TokenKind::Str(buf));
+        } else if str_vec[*count] == '\\' {
+            distinguish_escape_string(str_vec, count, &mut buf)?;
+        } else {
+            buf.push(str_vec[*count]);
+            *count += 1;
+        }
+    }
+    return Err(TokenizeErrorKind::UnterminatedString);
+}
+
+fn distinguish_escape_string(
+    str_vec: &[char],
+    count: &mut usize,
+    buf: &mut String,
+) -> Result<(), TokenizeErrorKind> {
+    *count += 1;
+    let ch = match str_vec.get(*count) {
+        Some('"') => '"',
+        Some('\\') => '\\',
+        Some('/') => '/',
+        Some('b') => '\u{8}',
+        Some('f') => '\u{c}',
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('u') => {
+            let high = distinguish_unicode_escape(str_vec, count)?;
+            if (0xD800..=0xDBFF).contains(&high)
+                && str_vec.get(*count) == Some(&'\\')
+                && str_vec.get(*count + 1) == Some(&'u')
+            {
+                let high_end = *count;
+                *count += 1;
+                let low = distinguish_unicode_escape(str_vec, count)?;
+                if (0xDC00..=0xDFFF).contains(&low) {
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                    buf.push(char::from_u32(code).unwrap());
+                    return Ok(());
+                }
+                *count = high_end;
+            }
+            buf.push(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER));
+            return Ok(());
+        }
+        Some(_) => return Err(TokenizeErrorKind::InvalidString),
+        None => return Err(TokenizeErrorKind::UnterminatedString),
+    };
+    buf.push(ch);
+    *count += 1;
+    return Ok(());
+}
+
+fn distinguish_unicode_escape(
+    str_vec: &[char],
+    count: &mut usize,
+) -> Result<u32, TokenizeErrorKind> {
+    match str_vec.get(*count + 1..*count + 5) {
+        Some(hex) if hex.iter().all(|ch| ch.is_ascii_hexdigit()) => {
+            *count += 5;
+            return Ok(hex
+                .iter()
+                .fold(0, |acc, ch| acc * 16 + ch.to_digit(16).unwrap()));
+        }
+        _ => return Err(TokenizeErrorKind::InvalidString),
+    }
+}
+
+fn distinguish_word(
+    str_vec: &[char],
+    count: &mut usize,
+    word: &str,
+    kind: TokenizeErrorKind,
+) -> Result<TokenKind, TokenizeErrorKind> {
+    let len = word.chars().count();
+    if let Some(chars) = str_vec.get(*count..*count + len) {
+        let buf = chars.iter().collect::<String>();
+        if buf == word {
+            *count += len;
+            if word == "null" {
+                return Ok(TokenKind::Null);
+            }
+            return Ok(TokenKind::Bool(buf));
+        }
+    }
+    return Err(kind);
+}
+
+/// States of the number grammar, as the old `NumberTokenizer` walked them
+/// one char at a time.
+#[derive(PartialEq)]
+enum NState {
+    None,
+    Minus,
+    Zero,
+    DigitOneToNine,
+    Dot,
+    Fraction,
+    Exponential,
+    Sign,
+    ExponentDigit,
+}
+
+fn distinguish_number(str_vec: &[char], count: &mut usize) -> Result<TokenKind, TokenizeErrorKind> {
+    let mut buf = String::new();
+    let mut state = NState::None;
+    while let Some(&ch) = str_vec.get(*count) {
+        let next = match (ch, &state) {
+            ('-', NState::None) => NState::Minus,
+            ('-' | '+', NState::Exponential) => NState::Sign,
+            ('0', NState::None | NState::Minus) => NState::Zero,
+            ('1'..='9', NState::None | NState::Minus) => NState::DigitOneToNine,
+            ('0'..='9', NState::DigitOneToNine) => NState::DigitOneToNine,
+            ('0'..='9', NState::Dot | NState::Fraction) => NState::Fraction,
+            ('0'..='9', NState::Exponential | NState::Sign | NState::ExponentDigit) => {
+                NState::ExponentDigit
+            }
+            ('.', NState::DigitOneToNine | NState::Zero) => NState::Dot,
+            ('e' | 'E', NState::DigitOneToNine | NState::Zero | NState::Fraction) => {
+                NState::Exponential
+            }
+            _ => break,
+        };
+        buf.push(ch);
+        *count += 1;
+        state = next;
+    }
+    match state {
+        NState::Zero | NState::DigitOneToNine | NState::Fraction | NState::ExponentDigit => {
+            return Ok(TokenKind::Num(buf));
+        }
+        _ => return Err(TokenizeErrorKind::InvalidNumber),
+    }
+}
@@ -0,0 +1,72 @@
+//! Compares the byte tokenizer with the char-vector tokenizer it replaced,
+//! which is kept in `baseline`.
+//!
+//! Run with `cargo bench`. Each case tokenizes the same generated document
+//! repeatedly and reports throughput in MB/s of UTF-8 input.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use json_formatter::tokenizer;
+
+mod baseline;
+
+const ROUNDS: usize = 20;
+
+/// Builds a document of roughly `records` objects mixing plain strings,
+/// escaped strings, non-ASCII text and numbers.
+fn document(records: usize) -> String {
+    let mut buf = String::from("[");
+    for i in 0..records {
+        if i > 0 {
+            buf.push(',');
+        }
+        buf.push_str(&format!(
+            r#"{{"id": {}, "name": "user{}", "score": {}.{}e-3, "active": {}, "tags": ["a", "b\n", "日本語"], "note": null}}"#,
+            i,
+            i,
+            i * 7,
+            i % 100,
+            i % 2 == 0
+        ));
+    }
+    buf.push(']');
+    return buf;
+}
+
+fn measure<F: FnMut()>(name: &str, bytes: usize, mut f: F) {
+    // 1回目は計測しない
+    f();
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed());
+    }
+    let mb_per_sec = bytes as f64 / best.as_secs_f64() / 1_000_000.0;
+    println!("{:<32} {:>10.2?} {:>10.1} MB/s", name, best, mb_per_sec);
+}
+
+fn main() {
+    for records in [1_000, 50_000] {
+        let source = document(records);
+        println!("document: {} records, {} bytes", records, source.len());
+
+        // 比べる前に、二つが同じトークンを読むことを確かめる
+        let old = baseline::tokenize(source.chars().collect()).unwrap();
+        let new = tokenizer::tokenize_bytes(source.as_bytes()).unwrap();
+        assert_eq!(old.len(), new.len());
+        assert!(old
+            .iter()
+            .zip(new.iter())
+            .all(|(old, new)| old.span == new.span));
+
+        measure("baseline (Vec<char>)", source.len(), || {
+            let chars = source.chars().collect::<Vec<char>>();
+            black_box(baseline::tokenize(chars).unwrap());
+        });
+        measure("tokenize_bytes (&[u8])", source.len(), || {
+            black_box(tokenizer::tokenize_bytes(source.as_bytes()).unwrap());
+        });
+        println!();
+    }
+}
@@ -0,0 +1,135 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A file that replaces `target` only when `commit` is called.
+///
+/// Output goes to a temporary file next to the target, which is renamed over
+/// it at the end, so readers never see a half-written file. The temporary
+/// file is removed if the `AtomicFile` is dropped without committing.
+pub struct AtomicFile {
+    file: Option<File>,
+    temp: PathBuf,
+    target: PathBuf,
+}
+
+impl AtomicFile {
+    pub fn create(target: &Path) -> io::Result<AtomicFile> {
+        // シンボリックリンクはリンク先を置き換える
+        let target = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+        let name = target
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?;
+        let temp =
+            target.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        let atomic = AtomicFile {
+            file: Some(file),
+            temp,
+            target,
+        };
+        // 元のファイルがあれば権限を引き継ぐ
+        if let Ok(metadata) = fs::metadata(&atomic.target) {
+            atomic.file()?.set_permissions(metadata.permissions())?;
+        }
+        return Ok(atomic);
+    }
+
+    /// Flushes the contents to disk and moves them over the target.
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(file) = self.file.take() {
+            file.sync_all()?;
+        }
+        return fs::rename(&self.temp, &self.target);
+    }
+
+    fn file(&self) -> io::Result<&File> {
+        return self
+            .file
+            .as_ref()
+            .ok_or_else(|| io::Error::other("already committed"));
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        return self.file()?.write(buf);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.file()?.flush();
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("json-formatter-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    #[test]
+    fn test_atomic_file_commit() {
+        let dir = scratch("commit");
+        let target = dir.join("a.json");
+        fs::write(&target, "old").unwrap();
+
+        let mut file = AtomicFile::create(&target).unwrap();
+        file.write_all(b"new").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        file.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_file_drop() {
+        let dir = scratch("drop");
+        let target = dir.join("a.json");
+        fs::write(&target, "old").unwrap();
+
+        let mut file = AtomicFile::create(&target).unwrap();
+        file.write_all(b"new").unwrap();
+        drop(file);
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_file_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch("permissions");
+        let target = dir.join("a.json");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let file = AtomicFile::create(&target).unwrap();
+        file.commit().unwrap();
+
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
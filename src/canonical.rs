@@ -0,0 +1,270 @@
+//! RFC 8785 JSON Canonicalization Scheme (JCS).
+
+use std::fmt;
+
+use super::escape;
+use super::options::KeyOrder;
+use super::sort;
+use super::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanonicalError {
+    /// An object has the key more than once, which I-JSON forbids.
+    DuplicateKey(String),
+    /// The number does not fit in an IEEE 754 double.
+    NumberOutOfRange(String),
+}
+
+impl fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanonicalError::DuplicateKey(key) => {
+                write!(
+                    f,
+                    "duplicate key {} cannot be canonicalized",
+                    escape::quote(key)
+                )
+            }
+            CanonicalError::NumberOutOfRange(lexeme) => {
+                write!(f, "number {} is out of range for a double", lexeme)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CanonicalError {}
+
+/// Serializes `value` in the canonical form of RFC 8785: no whitespace,
+/// object keys sorted by UTF-16 code units, numbers in the shortest form
+/// that reads back as the same double, and only the string escapes JSON
+/// requires.
+pub fn to_canonical(value: &Value) -> Result<String, CanonicalError> {
+    let mut buf = String::new();
+    // 入れ子を再帰せずに書くため、残りの仕事をスタックに積む
+    let mut stack = vec![Step::Value(value)];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Value(value) => write_value(value, &mut buf, &mut stack)?,
+            Step::Key(key) => {
+                buf.push_str(&escape::quote(key));
+                buf.push(':');
+            }
+            Step::Punct(punct) => buf.push(punct),
+        }
+    }
+    return Ok(buf);
+}
+
+/// What `to_canonical` has left to write, last first.
+enum Step<'a> {
+    Value(&'a Value),
+    Key(&'a str),
+    Punct(char),
+}
+
+/// Writes a scalar, or the opening bracket of a container with its members
+/// pushed onto `stack`.
+fn write_value<'a>(
+    value: &'a Value,
+    buf: &mut String,
+    stack: &mut Vec<Step<'a>>,
+) -> Result<(), CanonicalError> {
+    match value {
+        Value::Object(members) => {
+            let mut members: Vec<&(String, Value)> = members.iter().collect();
+            members.sort_by(|a, b| sort::compare_keys(&a.0, &b.0, KeyOrder::Utf16));
+            if let Some(pair) = members.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(CanonicalError::DuplicateKey(pair[0].0.clone()));
+            }
+            buf.push('{');
+            stack.push(Step::Punct('}'));
+            for (i, (key, value)) in members.into_iter().enumerate().rev() {
+                stack.push(Step::Value(value));
+                stack.push(Step::Key(key));
+                if i > 0 {
+                    stack.push(Step::Punct(','));
+                }
+            }
+        }
+        Value::Array(elements) => {
+            buf.push('[');
+            stack.push(Step::Punct(']'));
+            for (i, value) in elements.iter().enumerate().rev() {
+                stack.push(Step::Value(value));
+                if i > 0 {
+                    stack.push(Step::Punct(','));
+                }
+            }
+        }
+        Value::Str(string) => buf.push_str(&escape::quote(string)),
+        Value::Num(number) => {
+            // JSON5 の 0x1F や .5 もそのまま読める
+            let number = number
+                .as_f64()
+                .filter(|number| number.is_finite())
+                .ok_or_else(|| CanonicalError::NumberOutOfRange(number.to_string()))?;
+            buf.push_str(&format_number(number));
+        }
+        Value::Bool(b) => buf.push_str(if *b { "true" } else { "false" }),
+        Value::Null => buf.push_str("null"),
+    }
+    return Ok(());
+}
+
+/// Formats a finite double the way ECMAScript's `Number.prototype.toString`
+/// does, which is what RFC 8785 prescribes.
+pub fn format_number(number: f64) -> String {
+    if number == 0.0 {
+        // -0 も 0 と書く
+        return "0".to_string();
+    }
+    if number < 0.0 {
+        return format!("-{}", format_number(-number));
+    }
+    // `{:e}` は元の値に戻る最短の桁を出す。例: 3.333e8
+    let exp_form = format!("{:e}", number);
+    let (mantissa, exponent) = exp_form.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // 値は 0.digits × 10^n
+    let n = exponent.parse::<i32>().unwrap() + 1;
+
+    if k <= n && n <= 21 {
+        return format!("{}{}", digits, "0".repeat((n - k) as usize));
+    }
+    if 0 < n && n <= 21 {
+        return format!("{}.{}", &digits[..n as usize], &digits[n as usize..]);
+    }
+    if -6 < n && n <= 0 {
+        return format!("0.{}{}", "0".repeat((-n) as usize), digits);
+    }
+    let sign = if n - 1 < 0 { '-' } else { '+' };
+    if k == 1 {
+        return format!("{}e{}{}", digits, sign, (n - 1).abs());
+    }
+    return format!(
+        "{}.{}e{}{}",
+        &digits[..1],
+        &digits[1..],
+        sign,
+        (n - 1).abs()
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParseOptions;
+    use crate::parser;
+
+    fn canonical(source: &str) -> Result<String, CanonicalError> {
+        let value = parser::parse_str(source).unwrap();
+        let output = to_canonical(&value);
+        value.dispose();
+        return output;
+    }
+
+    #[test]
+    fn test_to_canonical() {
+        // RFC 8785 3.2.2 の例
+        let source = r#"{
+          "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+          "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+          "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            canonical(source).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn test_to_canonical_key_order() {
+        // RFC 8785 3.2.3 の例
+        let source = r#"{
+          "€": "Euro Sign",
+          "\r": "Carriage Return",
+          "דּ": "Hebrew Letter Dalet With Dagesh",
+          "1": "One",
+          "😀": "Emoji: Grinning Face",
+          "\u0080": "Control",
+          "ö": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let value = parser::parse_str(&canonical(source).unwrap()).unwrap();
+        assert_eq!(
+            value.keys().collect::<Vec<&str>>(),
+            vec!["\r", "1", "\u{80}", "ö", "€", "😀", "\u{fb33}"]
+        );
+    }
+
+    #[test]
+    fn test_to_canonical_errors() {
+        assert_eq!(
+            canonical(r#"{"a": 1, "b": {"c": 1, "c": 2}}"#),
+            Err(CanonicalError::DuplicateKey("c".to_string()))
+        );
+        assert_eq!(
+            canonical("[1e400]"),
+            Err(CanonicalError::NumberOutOfRange("1e400".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_to_canonical_json5() {
+        let value =
+            parser::parse_str_with("[0x1F, +1, .5, 5., -0x10]", &ParseOptions::json5()).unwrap();
+        assert_eq!(to_canonical(&value).unwrap(), "[31,1,0.5,5,-16]");
+
+        let value = parser::parse_str_with("[Infinity]", &ParseOptions::json5()).unwrap();
+        assert_eq!(
+            to_canonical(&value),
+            Err(CanonicalError::NumberOutOfRange("Infinity".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_to_canonical_deep_nesting() {
+        let depth = 100_000;
+        let source = format!("{}1{}", r#"{"b":0,"a":["#.repeat(depth), "]}".repeat(depth));
+        let expected = format!(
+            "{}1{}",
+            r#"{"a":["#.repeat(depth),
+            r#"],"b":0}"#.repeat(depth)
+        );
+        assert_eq!(canonical(&source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_number() {
+        // RFC 8785 付録 B の値
+        let cases: [(u64, &str); 14] = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x41b3de4355555555, "333333333.3333333"),
+        ];
+        for (bits, expected) in cases.iter() {
+            assert_eq!(
+                format_number(f64::from_bits(*bits)),
+                *expected,
+                "{:x}",
+                bits
+            );
+        }
+        assert_eq!(format_number(1e21), "1e+21");
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(1e-7), "1e-7");
+        assert_eq!(format_number(-1.5), "-1.5");
+        assert_eq!(format_number(123.0), "123");
+    }
+}
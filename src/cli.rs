@@ -0,0 +1,696 @@
+use json_formatter::color::Theme;
+use json_formatter::options::{
+    DuplicateKeys, ExponentCase, FormatOptions, KeyOrder, Newline, ParseOptions, SortKeys,
+};
+
+pub const USAGE: &str = "\
+usage: json-formatter [options] [file ...]
+
+Formats each file, or stdin when no file or '-' is given.
+
+options:
+  -o, --output <path>       write the result to <path> instead of stdout
+  -w, --write               format the files in place
+      --check               print a diff for each file that is not formatted
+                            and exit with 1 if any is, without writing
+      --indent <n>          indent with n spaces (default 2)
+      --tab                 indent with tabs
+      --crlf                end lines with CRLF
+      --space-before-colon  write `\"key\" : value`
+      --no-space-after-colon
+                            write `\"key\":value`
+      --no-trailing-newline do not end the output with a newline
+      --trailing-commas     put a comma after the last member or element of
+                            containers written over several lines
+      --normalize-numbers   write numbers in a normal form: `1.0`, `1e0` and
+                            `10E-1` all become `1`
+      --trim-number-zeros   drop zeros at the end of fractions: `1.50` -> `1.5`
+      --exponent <case>     write exponents with `e` (lower) or `E` (upper)
+      --no-exponent-plus    drop `+` from exponents: `1e+5` -> `1e5`
+      --plain-numbers <n>   write numbers with an exponent without it when that
+                            takes at most n characters: `1.5e3` -> `1500`
+      --warn-lossy-numbers  warn about numbers a double cannot hold exactly
+      --inline-width <n>    keep containers of at most n columns on one line
+      --max-width <n>       keep containers on one line while the line fits
+                            in n columns
+      --sort-keys[=<order>] write object members sorted by key. <order> is
+                            codepoint (default), natural to compare digit
+                            runs as numbers, or utf16
+      --sort-top-level      sort only the keys of the top-level object
+      --jsonc               accept `//` and `/* */` comments and keep them in
+                            the output (dropped by --minify, --canonical
+                            and --stream)
+      --allow-trailing-commas
+                            accept a comma after the last member or element
+      --lossy-strings       accept raw control characters in strings and
+                            replace lone surrogate escapes with U+FFFD
+      --duplicate-keys <policy>
+                            what to do with a key used twice in one object:
+                            allow (default), error, warn with both locations,
+                            first or last to keep only one member
+      --max-depth <n>       reject input nested more than n levels deep
+      --max-bytes <n>       reject documents longer than n bytes
+      --max-string-length <n>
+                            reject strings longer than n bytes
+      --max-number-length <n>
+                            reject numbers longer than n characters
+      --max-members <n>     reject objects or arrays with more than n members
+      --json5               accept JSON5 input and write it as JSON
+      --json5-style         accept JSON5 input and keep it JSON5: numbers as
+                            written and identifier keys unquoted
+      --repair              fix almost-JSON, such as LLM output, and report
+                            each fix as a warning
+      --canonical           write the RFC 8785 canonical form, with no
+                            trailing newline; other layout options are ignored
+      --minify, --compact   write without any insignificant whitespace
+      --stats               report the size of each input and its output
+      --color <when>        color the output: auto (default), always or never.
+                            auto colors only a terminal and honours NO_COLOR
+      --theme <spec>        colors as SGR codes, e.g. 'key=1;34,string=32'.
+                            names: key, string, number, bool, null, punct,
+                            comment.
+                            defaults to $JSON_FORMATTER_THEME
+      --stream              format while reading, for very large inputs
+      --lines               read newline-delimited JSON and write each line
+                            as one compact line, reporting lines that do not
+                            parse and going on with the rest
+      --lines-to-array      read newline-delimited JSON and write a single
+                            array of its documents
+      --array-to-lines      write the elements of a top-level array as
+                            newline-delimited JSON
+      --seq                 read and write an RFC 7464 JSON text sequence,
+                            formatting each document on its own
+      --concat              read documents written one after another, as in
+                            '{..}{..}', and format each on its own
+  -h, --help                print this help
+  -V, --version             print the version
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How many documents an input holds and how they are delimited.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Documents {
+    /// Each input is a single document.
+    #[default]
+    Single,
+    /// One document per line in, one per line out.
+    Lines,
+    /// One document per line in, an array of them out.
+    LinesToArray,
+    /// An array in, one element per line out.
+    ArrayToLines,
+    /// An RFC 7464 JSON text sequence in and out.
+    Sequence,
+    /// Documents one after another, with or without whitespace between.
+    Concatenated,
+}
+
+impl Documents {
+    /// The option that selects this mode.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Documents::Single => "",
+            Documents::Lines => "--lines",
+            Documents::LinesToArray => "--lines-to-array",
+            Documents::ArrayToLines => "--array-to-lines",
+            Documents::Sequence => "--seq",
+            Documents::Concatenated => "--concat",
+        }
+    }
+}
+
+/// Settings taken from the command line.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Args {
+    pub files: Vec<String>,
+    pub output: Option<String>,
+    pub write: bool,
+    pub check: bool,
+    pub stats: bool,
+    pub canonical: bool,
+    pub repair: bool,
+    pub warn_lossy_numbers: bool,
+    pub color: ColorChoice,
+    pub theme: Option<String>,
+    pub stream: bool,
+    pub documents: Documents,
+    pub help: bool,
+    pub version: bool,
+    pub parse: ParseOptions,
+    pub format: FormatOptions,
+}
+
+impl Args {
+    /// Whether the output goes to stdout.
+    pub fn to_stdout(&self) -> bool {
+        return !self.write && self.output.is_none();
+    }
+
+    /// Decides on the theme to color with, if any. `no_color` and
+    /// `theme_env` are the values of `NO_COLOR` and `JSON_FORMATTER_THEME`.
+    pub fn theme(
+        &self,
+        is_terminal: bool,
+        no_color: Option<String>,
+        theme_env: Option<String>,
+    ) -> Result<Option<Theme>, String> {
+        let enabled = match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            // NO_COLOR は空でなければ有効
+            ColorChoice::Auto => {
+                is_terminal && self.to_stdout() && no_color.is_none_or(|value| value.is_empty())
+            }
+        };
+        if !enabled {
+            return Ok(None);
+        }
+        let spec = self.theme.clone().or(theme_env).unwrap_or_default();
+        return Theme::parse(&spec).map(Some).map_err(|err| err.to_string());
+    }
+
+    /// The inputs to format, `-` meaning stdin.
+    pub fn inputs(&self) -> Vec<String> {
+        if self.files.is_empty() {
+            return vec!["-".to_string()];
+        }
+        return self.files.clone();
+    }
+}
+
+/// Parses the arguments after the program name.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut result = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            result.files.extend(args.by_ref());
+            break;
+        }
+        if arg == "-" || !arg.starts_with('-') {
+            result.files.push(arg);
+            continue;
+        }
+        // --indent=4 の形も受け付ける
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline_value.clone().or_else(|| args.next());
+        match flag {
+            "-o" | "--output" => {
+                result.output = Some(value().ok_or_else(|| missing_value(flag))?);
+            }
+            "--indent" => result.format.indent = " ".repeat(number_value(flag, value())?),
+            "--inline-width" => result.format.inline_width = Some(number_value(flag, value())?),
+            "--max-width" => result.format.max_width = Some(number_value(flag, value())?),
+            "--max-depth" => result.parse.limits.max_depth = Some(number_value(flag, value())?),
+            "--max-bytes" => result.parse.limits.max_bytes = Some(number_value(flag, value())?),
+            "--max-string-length" => {
+                result.parse.limits.max_string_length = Some(number_value(flag, value())?);
+            }
+            "--max-number-length" => {
+                result.parse.limits.max_number_length = Some(number_value(flag, value())?);
+            }
+            "--max-members" => {
+                result.parse.limits.max_members = Some(number_value(flag, value())?);
+            }
+            "--plain-numbers" => {
+                result.format.numbers.plain_width = Some(number_value(flag, value())?);
+            }
+            "--exponent" => {
+                result.format.numbers.exponent_case = match value().as_deref() {
+                    Some("lower") => Some(ExponentCase::Lower),
+                    Some("upper") => Some(ExponentCase::Upper),
+                    Some(other) => {
+                        return Err(format!(
+                            "invalid value '{}' for '--exponent', expected lower or upper",
+                            other
+                        ));
+                    }
+                    None => return Err(missing_value(flag)),
+                };
+            }
+            "--duplicate-keys" => {
+                result.parse.duplicate_keys = match value().as_deref() {
+                    Some("allow") => DuplicateKeys::Allow,
+                    Some("error") => DuplicateKeys::Error,
+                    Some("warn") => DuplicateKeys::Warn,
+                    Some("first") => DuplicateKeys::First,
+                    Some("last") => DuplicateKeys::Last,
+                    Some(other) => {
+                        return Err(format!(
+                            "invalid value '{}' for '--duplicate-keys', expected allow, error, warn, first or last",
+                            other
+                        ));
+                    }
+                    None => return Err(missing_value(flag)),
+                };
+            }
+            "--color" => {
+                result.color = match value().as_deref() {
+                    Some("auto") => ColorChoice::Auto,
+                    Some("always") => ColorChoice::Always,
+                    Some("never") => ColorChoice::Never,
+                    Some(other) => {
+                        return Err(format!(
+                            "invalid value '{}' for '--color', expected auto, always or never",
+                            other
+                        ));
+                    }
+                    None => return Err(missing_value(flag)),
+                };
+            }
+            "--theme" => result.theme = Some(value().ok_or_else(|| missing_value(flag))?),
+            "--sort-keys" => {
+                let order = match inline_value.as_deref() {
+                    None | Some("codepoint") => KeyOrder::CodePoint,
+                    Some("natural") => KeyOrder::Natural,
+                    Some("utf16") => KeyOrder::Utf16,
+                    Some(other) => {
+                        return Err(format!(
+                            "invalid value '{}' for '--sort-keys', expected codepoint, natural or utf16",
+                            other
+                        ));
+                    }
+                };
+                let recursive = result.format.sort_keys.is_none_or(|sort| sort.recursive);
+                result.format.sort_keys = Some(SortKeys { order, recursive });
+            }
+            _ if inline_value.is_some() => {
+                return Err(format!("option '{}' does not take a value", flag));
+            }
+            "-w" | "--write" => result.write = true,
+            "--check" => result.check = true,
+            "--canonical" => result.canonical = true,
+            "--repair" => result.repair = true,
+            "--jsonc" => result.parse.comments = true,
+            "--allow-trailing-commas" => result.parse.trailing_commas = true,
+            "--lossy-strings" => result.parse.lossy_strings = true,
+            "--trailing-commas" => result.format.trailing_commas = true,
+            "--normalize-numbers" => result.format.numbers.normalize = true,
+            "--trim-number-zeros" => result.format.numbers.trim_fraction_zeros = true,
+            "--no-exponent-plus" => result.format.numbers.strip_exponent_plus = true,
+            "--warn-lossy-numbers" => result.warn_lossy_numbers = true,
+            "--json5" => result.parse = json5_options(&result.parse),
+            "--json5-style" => {
+                result.parse = json5_options(&result.parse);
+                result.format.json5 = true;
+            }
+            "--sort-top-level" => {
+                let order = result.format.sort_keys.map(|sort| sort.order);
+                result.format.sort_keys = Some(SortKeys {
+                    order: order.unwrap_or_default(),
+                    recursive: false,
+                });
+            }
+            "--minify" | "--compact" => result.format.compact = true,
+            "--stats" => result.stats = true,
+            "--stream" => result.stream = true,
+            "--lines" => result.documents = Documents::Lines,
+            "--lines-to-array" => result.documents = Documents::LinesToArray,
+            "--array-to-lines" => result.documents = Documents::ArrayToLines,
+            "--seq" => result.documents = Documents::Sequence,
+            "--concat" => result.documents = Documents::Concatenated,
+            "-h" | "--help" => result.help = true,
+            "-V" | "--version" => result.version = true,
+            "--tab" => result.format.indent = "\t".to_string(),
+            "--crlf" => result.format.newline = Newline::CrLf,
+            "--space-before-colon" => result.format.space_before_colon = true,
+            "--no-space-after-colon" => result.format.space_after_colon = false,
+            "--no-trailing-newline" => result.format.trailing_newline = false,
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+    check(&result)?;
+    return Ok(result);
+}
+
+/// JSON5 parsing, keeping the string handling, duplicate-key policy and
+/// limits of any earlier flag.
+fn json5_options(parse: &ParseOptions) -> ParseOptions {
+    return ParseOptions {
+        lossy_strings: parse.lossy_strings,
+        duplicate_keys: parse.duplicate_keys,
+        limits: parse.limits,
+        ..ParseOptions::json5()
+    };
+}
+
+/// Rejects combinations of options that cannot be honoured together.
+fn check(args: &Args) -> Result<(), String> {
+    if args.write && args.output.is_some() {
+        return Err("'--write' and '--output' cannot be used together".to_string());
+    }
+    if args.write && (args.files.is_empty() || args.files.iter().any(|file| file == "-")) {
+        return Err("'--write' needs file arguments and cannot format stdin".to_string());
+    }
+    if args.output.is_some() && args.inputs().len() > 1 {
+        return Err("'--output' takes a single input".to_string());
+    }
+    if args.color == ColorChoice::Always && !args.to_stdout() {
+        return Err("'--color=always' only applies to stdout".to_string());
+    }
+    if args.stream && args.format.sort_keys.is_some() {
+        return Err("'--sort-keys' cannot be used with '--stream'".to_string());
+    }
+    if args.stream && args.format.inline_width.is_some() {
+        return Err("'--inline-width' cannot be used with '--stream'".to_string());
+    }
+    if args.stream && args.format.max_width.is_some() {
+        return Err("'--max-width' cannot be used with '--stream'".to_string());
+    }
+    if args.stream && args.parse.json5 {
+        return Err("'--json5' cannot be used with '--stream'".to_string());
+    }
+    if args.repair && args.stream {
+        return Err("'--repair' cannot be used with '--stream'".to_string());
+    }
+    if args.repair && args.format.json5 {
+        return Err("'--repair' cannot be used with '--json5-style'".to_string());
+    }
+    if args.canonical && args.format.json5 {
+        return Err("'--json5-style' cannot be used with '--canonical'".to_string());
+    }
+    if args.stream && args.canonical {
+        return Err("'--canonical' cannot be used with '--stream'".to_string());
+    }
+    if args.documents != Documents::Single && (args.stream || args.check || args.repair) {
+        return Err(format!(
+            "'{}' cannot be used with '--stream', '--check' or '--repair'",
+            args.documents.flag()
+        ));
+    }
+    if args.warn_lossy_numbers
+        && (args.stream || args.repair || !matches!(args.documents, Documents::Single))
+    {
+        return Err(
+            "'--warn-lossy-numbers' only works on single documents, without '--stream' or '--repair'"
+                .to_string(),
+        );
+    }
+    if args.parse.duplicate_keys != DuplicateKeys::Allow && (args.stream || args.repair) {
+        return Err("'--duplicate-keys' cannot be used with '--stream' or '--repair'".to_string());
+    }
+    if args.parse.duplicate_keys == DuplicateKeys::Warn && args.documents != Documents::Single {
+        return Err(format!(
+            "'--duplicate-keys=warn' cannot be used with '{}'",
+            args.documents.flag()
+        ));
+    }
+    if args.check && (args.write || args.output.is_some() || args.stream) {
+        return Err(
+            "'--check' cannot be used with '--write', '--output' or '--stream'".to_string(),
+        );
+    }
+    return Ok(());
+}
+
+fn missing_value(flag: &str) -> String {
+    return format!("'{}' needs a value", flag);
+}
+
+fn number_value(flag: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| missing_value(flag))?;
+    return value
+        .parse::<usize>()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value, flag));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use json_formatter::options::{Limits, NumberFormat};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        return parse_args(args.iter().map(|arg| arg.to_string()));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+        assert_eq!(parse(&[]).unwrap().inputs(), vec!["-"]);
+        let args = parse(&[
+            "--indent",
+            "4",
+            "--crlf",
+            "--space-before-colon",
+            "--no-space-after-colon",
+            "--no-trailing-newline",
+            "--inline-width",
+            "40",
+            "--max-width=100",
+        ])
+        .unwrap();
+        assert!(parse(&["--stream"]).unwrap().stream);
+        assert_eq!(
+            args.format,
+            FormatOptions {
+                indent: "    ".to_string(),
+                newline: Newline::CrLf,
+                space_before_colon: true,
+                space_after_colon: false,
+                trailing_newline: false,
+                inline_width: Some(40),
+                max_width: Some(100),
+                compact: false,
+                color: None,
+                sort_keys: None,
+                json5: false,
+                trailing_commas: false,
+                numbers: NumberFormat::preserve(),
+            }
+        );
+        assert_eq!(parse(&["--tab"]).unwrap().format.indent, "\t");
+    }
+
+    #[test]
+    fn test_parse_args_files() {
+        let args = parse(&["a.json", "-w", "b.json", "--", "-c.json"]).unwrap();
+        assert!(args.write);
+        assert_eq!(args.inputs(), vec!["a.json", "b.json", "-c.json"]);
+
+        let args = parse(&["-", "-o", "out.json", "--minify"]).unwrap();
+        assert_eq!(args.inputs(), vec!["-"]);
+        assert_eq!(args.output, Some("out.json".to_string()));
+        assert!(args.format.compact);
+        assert!(parse(&["--compact"]).unwrap().format.compact);
+
+        assert!(parse(&["--check", "a.json"]).unwrap().check);
+        assert!(parse(&["--stats"]).unwrap().stats);
+        assert!(parse(&["--canonical"]).unwrap().canonical);
+        assert!(parse(&["--canonical", "--stream"]).is_err());
+        assert_eq!(parse(&["--jsonc"]).unwrap().parse, ParseOptions::jsonc());
+        let args = parse(&["--json5"]).unwrap();
+        assert_eq!(args.parse, ParseOptions::json5());
+        assert!(!args.format.json5);
+        let args = parse(&["--json5-style"]).unwrap();
+        assert_eq!(args.parse, ParseOptions::json5());
+        assert!(args.format.json5);
+        assert!(parse(&["--json5", "--stream"]).is_err());
+        assert_eq!(
+            parse(&["--stream", "--max-width=80"]),
+            Err("'--max-width' cannot be used with '--stream'".to_string())
+        );
+        assert!(parse(&["--inline-width", "40", "--stream"]).is_err());
+        let args = parse(&["--allow-trailing-commas", "--trailing-commas"]).unwrap();
+        assert!(args.parse.trailing_commas);
+        assert!(args.format.trailing_commas);
+        assert!(parse(&["--json5-style", "--canonical"]).is_err());
+        assert_eq!(
+            parse(&["--normalize-numbers"]).unwrap().format.numbers,
+            NumberFormat::normalized()
+        );
+        let numbers = parse(&[
+            "--trim-number-zeros",
+            "--exponent",
+            "upper",
+            "--no-exponent-plus",
+            "--plain-numbers=8",
+        ])
+        .unwrap()
+        .format
+        .numbers;
+        assert_eq!(
+            numbers,
+            NumberFormat {
+                normalize: false,
+                trim_fraction_zeros: true,
+                exponent_case: Some(ExponentCase::Upper),
+                strip_exponent_plus: true,
+                plain_width: Some(8),
+            }
+        );
+        assert!(parse(&["--exponent=title"]).is_err());
+        assert!(parse(&["--warn-lossy-numbers"]).unwrap().warn_lossy_numbers);
+        assert!(parse(&["--warn-lossy-numbers", "--lines"]).is_err());
+        let duplicate_keys = |args: &[&str]| parse(args).map(|args| args.parse.duplicate_keys);
+        assert_eq!(duplicate_keys(&[]), Ok(DuplicateKeys::Allow));
+        assert_eq!(
+            duplicate_keys(&["--duplicate-keys", "error"]),
+            Ok(DuplicateKeys::Error)
+        );
+        assert_eq!(
+            duplicate_keys(&["--duplicate-keys=last", "--json5"]),
+            Ok(DuplicateKeys::Last)
+        );
+        assert_eq!(
+            duplicate_keys(&["--duplicate-keys=first", "--lines"]),
+            Ok(DuplicateKeys::First)
+        );
+        assert!(parse(&["--duplicate-keys=warn", "--lines"]).is_err());
+        assert!(parse(&["--duplicate-keys=error", "--stream"]).is_err());
+        assert!(parse(&["--duplicate-keys=keep"]).is_err());
+        let limits = parse(&[
+            "--max-depth=3",
+            "--max-bytes",
+            "100",
+            "--max-string-length=10",
+            "--max-number-length=5",
+            "--max-members=4",
+            "--json5",
+        ])
+        .unwrap()
+        .parse
+        .limits;
+        assert_eq!(
+            limits,
+            Limits {
+                max_depth: Some(3),
+                max_bytes: Some(100),
+                max_string_length: Some(10),
+                max_number_length: Some(5),
+                max_members: Some(4),
+            }
+        );
+        assert!(parse(&["--max-depth=deep"]).is_err());
+        assert!(!parse(&[]).unwrap().parse.lossy_strings);
+        let args = parse(&["--lossy-strings", "--json5"]).unwrap();
+        assert!(args.parse.lossy_strings && args.parse.json5);
+        assert!(parse(&["--repair"]).unwrap().repair);
+        assert!(parse(&["--repair", "--stream"]).is_err());
+        assert!(parse(&["--repair", "--json5-style"]).is_err());
+        let documents = |args: &[&str]| parse(args).unwrap().documents;
+        assert_eq!(documents(&["--lines"]), Documents::Lines);
+        assert_eq!(documents(&["--lines-to-array"]), Documents::LinesToArray);
+        assert_eq!(documents(&["--array-to-lines"]), Documents::ArrayToLines);
+        assert_eq!(documents(&["--seq"]), Documents::Sequence);
+        assert_eq!(documents(&["--concat"]), Documents::Concatenated);
+        assert_eq!(
+            parse(&["--seq", "--stream"]),
+            Err("'--seq' cannot be used with '--stream', '--check' or '--repair'".to_string())
+        );
+        assert!(parse(&["--lines", "--stream"]).is_err());
+        assert!(parse(&["--array-to-lines", "--check"]).is_err());
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--version"]).unwrap().version);
+    }
+
+    #[test]
+    fn test_parse_args_sort_keys() {
+        let sort_keys = |args: &[&str]| parse(args).unwrap().format.sort_keys;
+        assert_eq!(sort_keys(&[]), None);
+        assert_eq!(
+            sort_keys(&["--sort-keys", "a.json"]),
+            Some(SortKeys {
+                order: KeyOrder::CodePoint,
+                recursive: true
+            })
+        );
+        let natural_top = Some(SortKeys {
+            order: KeyOrder::Natural,
+            recursive: false,
+        });
+        assert_eq!(
+            sort_keys(&["--sort-keys=natural", "--sort-top-level"]),
+            natural_top
+        );
+        assert_eq!(
+            sort_keys(&["--sort-top-level", "--sort-keys=natural"]),
+            natural_top
+        );
+        assert_eq!(
+            parse(&["--sort-keys", "a.json"]).unwrap().files,
+            vec!["a.json"]
+        );
+        assert_eq!(
+            sort_keys(&["--sort-keys=utf16"]).map(|sort| sort.order),
+            Some(KeyOrder::Utf16)
+        );
+        assert!(parse(&["--sort-keys=random"]).is_err());
+        assert!(parse(&["--sort-keys", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_color() {
+        let theme = |args: &[&str], tty: bool, no_color: Option<&str>, env: Option<&str>| {
+            return parse(args).unwrap().theme(
+                tty,
+                no_color.map(String::from),
+                env.map(String::from),
+            );
+        };
+        assert_eq!(theme(&[], true, None, None), Ok(Some(Theme::default())));
+        assert_eq!(theme(&[], false, None, None), Ok(None));
+        assert_eq!(theme(&[], true, Some("1"), None), Ok(None));
+        assert_eq!(theme(&[], true, Some(""), None), Ok(Some(Theme::default())));
+        assert_eq!(theme(&["-o", "x.json"], true, None, None), Ok(None));
+        assert_eq!(theme(&["--color=never"], true, None, None), Ok(None));
+        assert_eq!(
+            theme(&["--color", "always"], false, Some("1"), None),
+            Ok(Some(Theme::default()))
+        );
+
+        let red = Theme {
+            key: "31".to_string(),
+            ..Theme::default()
+        };
+        assert_eq!(
+            theme(&[], true, None, Some("key=31")),
+            Ok(Some(red.clone()))
+        );
+        assert_eq!(
+            theme(&["--theme", "key=31"], true, None, Some("key=32")),
+            Ok(Some(red))
+        );
+        assert!(theme(&["--theme", "key"], true, None, None).is_err());
+        // 色を付けないならテーマは読まない
+        assert_eq!(theme(&["--theme", "key"], false, None, None), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(
+            parse(&["--indent"]),
+            Err("'--indent' needs a value".to_string())
+        );
+        assert_eq!(parse(&["-o"]), Err("'-o' needs a value".to_string()));
+        assert_eq!(
+            parse(&["--inline-width", "x"]),
+            Err("invalid value 'x' for '--inline-width'".to_string())
+        );
+        assert_eq!(
+            parse(&["--bogus"]),
+            Err("unknown option '--bogus'".to_string())
+        );
+        assert_eq!(
+            parse(&["--tab=1"]),
+            Err("option '--tab' does not take a value".to_string())
+        );
+        assert!(parse(&["-w"]).is_err());
+        assert!(parse(&["-w", "-"]).is_err());
+        assert!(parse(&["-w", "a.json", "-o", "b.json"]).is_err());
+        assert!(parse(&["a.json", "b.json", "-o", "c.json"]).is_err());
+        assert!(parse(&["--check", "-w", "a.json"]).is_err());
+        assert!(parse(&["--check", "--stream"]).is_err());
+        assert!(parse(&["--color=sometimes"]).is_err());
+        assert!(parse(&["--color=always", "-w", "a.json"]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+/// What a piece of output is, for choosing its color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Key,
+    Str,
+    Num,
+    Bool,
+    Null,
+    /// Brackets, commas and colons.
+    Punct,
+    Comment,
+}
+
+/// ANSI SGR parameters for each `Style`, e.g. `"1;34"` for bold blue. An
+/// empty string leaves that style uncolored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub key: String,
+    pub string: String,
+    pub number: String,
+    pub boolean: String,
+    pub null: String,
+    pub punct: String,
+    pub comment: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeError {
+    pub entry: String,
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid theme entry '{}', expected e.g. 'key=1;34' with one of key, string, number, bool, null, punct, comment",
+            self.entry
+        )
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            key: "1;34".to_string(),
+            string: "32".to_string(),
+            number: "36".to_string(),
+            boolean: "33".to_string(),
+            null: "35".to_string(),
+            punct: "1".to_string(),
+            comment: "90".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    /// Reads a theme such as `key=1;34,string=32`, starting from the default
+    /// so that only the styles to change need to be given.
+    pub fn parse(spec: &str) -> Result<Theme, ThemeError> {
+        let mut theme = Theme::default();
+        for entry in spec.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let error = || ThemeError {
+                entry: entry.to_string(),
+            };
+            let (name, code) = entry.split_once('=').ok_or_else(error)?;
+            let code = code.trim();
+            // SGR のパラメータは数字と ; だけ
+            if !code.bytes().all(|b| b.is_ascii_digit() || b == b';') {
+                return Err(error());
+            }
+            let slot = match name.trim() {
+                "key" => &mut theme.key,
+                "string" => &mut theme.string,
+                "number" => &mut theme.number,
+                "bool" => &mut theme.boolean,
+                "null" => &mut theme.null,
+                "punct" => &mut theme.punct,
+                "comment" => &mut theme.comment,
+                _ => return Err(error()),
+            };
+            *slot = code.to_string();
+        }
+        return Ok(theme);
+    }
+
+    pub fn code(&self, style: Style) -> &str {
+        match style {
+            Style::Key => &self.key,
+            Style::Str => &self.string,
+            Style::Num => &self.number,
+            Style::Bool => &self.boolean,
+            Style::Null => &self.null,
+            Style::Punct => &self.punct,
+            Style::Comment => &self.comment,
+        }
+    }
+
+    /// Appends `text` to `buf`, wrapped in the escape codes for `style`.
+    pub fn paint(&self, style: Style, text: &str, buf: &mut String) {
+        let code = self.code(style);
+        if code.is_empty() {
+            buf.push_str(text);
+            return;
+        }
+        buf.push_str("\x1b[");
+        buf.push_str(code);
+        buf.push('m');
+        buf.push_str(text);
+        buf.push_str("\x1b[0m");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_theme_parse() {
+        assert_eq!(Theme::parse("").unwrap(), Theme::default());
+        let theme = Theme::parse("key=31, null=,punct=2;37").unwrap();
+        assert_eq!(theme.key, "31");
+        assert_eq!(theme.null, "");
+        assert_eq!(theme.punct, "2;37");
+        assert_eq!(theme.string, Theme::default().string);
+
+        assert!(Theme::parse("key").is_err());
+        assert!(Theme::parse("color=31").is_err());
+        assert!(Theme::parse("key=31m").is_err());
+    }
+
+    #[test]
+    fn test_theme_paint() {
+        let theme = Theme::parse("key=1;34,null=").unwrap();
+        let mut buf = String::new();
+        theme.paint(Style::Key, "\"a\"", &mut buf);
+        theme.paint(Style::Null, "null", &mut buf);
+        assert_eq!(buf, "\x1b[1;34m\"a\"\x1b[0mnull");
+    }
+}
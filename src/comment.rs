@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use super::tokenizer::{Span, Token, TokenKind};
+
+/// A comment from the source, delimiters included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+    /// Whether the input had a line break between this comment and the
+    /// token after it.
+    pub line_break: bool,
+}
+
+impl Comment {
+    /// Whether this is a `//` comment, which must be followed by a line
+    /// break.
+    pub fn is_line(&self) -> bool {
+        return self.text.starts_with("//");
+    }
+}
+
+/// Where each comment of a document belongs.
+///
+/// Comments are keyed by the path of the member or element they belong to:
+/// the position of each member or element from the root down, in input
+/// order, so `[]` is the root value and `[2, 0]` the first member of the
+/// third element.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Comments {
+    leading: HashMap<Vec<usize>, Vec<Comment>>,
+    trailing: HashMap<Vec<usize>, Comment>,
+    inner: HashMap<Vec<usize>, Vec<Comment>>,
+    end: Vec<Comment>,
+}
+
+impl Comments {
+    /// Comments on the lines before a member or element.
+    pub fn leading(&self, path: &[usize]) -> &[Comment] {
+        return self.leading.get(path).map_or(&[], |comments| comments);
+    }
+
+    /// A comment on the same line, after a member or element and its comma.
+    pub fn trailing(&self, path: &[usize]) -> Option<&Comment> {
+        return self.trailing.get(path);
+    }
+
+    /// Comments after the last member of an object or array, before its
+    /// closing bracket.
+    pub fn inner(&self, path: &[usize]) -> &[Comment] {
+        return self.inner.get(path).map_or(&[], |comments| comments);
+    }
+
+    /// Comments on the lines after the root value.
+    pub fn end(&self) -> &[Comment] {
+        return &self.end;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.leading.is_empty()
+            && self.trailing.is_empty()
+            && self.inner.is_empty()
+            && self.end.is_empty();
+    }
+}
+
+/// An object or array that has been opened but not closed yet.
+struct Frame {
+    path: Vec<usize>,
+    is_object: bool,
+    // 次の要素の番号と、いま読んでいる要素のパス
+    count: usize,
+    current: Vec<usize>,
+    expect_item: bool,
+}
+
+/// Attaches the comment tokens in `tokens` to the values around them. The
+/// other tokens must form a valid document.
+pub fn collect(tokens: &[Token]) -> Comments {
+    let mut comments = Comments::default();
+    let mut pending: Vec<Comment> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    // 直前に終わった要素のパスとその最後の行
+    let mut last: Option<(Vec<usize>, usize)> = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.kind {
+            TokenKind::Comment(text) => {
+                let comment = Comment {
+                    text: text.to_string(),
+                    span: token.span,
+                    line_break: tokens
+                        .get(i + 1)
+                        .is_none_or(|next| next.span.start.line > token.span.end.line),
+                };
+                match &last {
+                    Some((path, line))
+                        if token.span.start.line == *line
+                            && !comments.trailing.contains_key(path)
+                            && !precedes_item(&comment, &tokens[i + 1..]) =>
+                    {
+                        comments.trailing.insert(path.clone(), comment);
+                    }
+                    _ => pending.push(comment),
+                }
+                continue;
+            }
+            TokenKind::Commma => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.expect_item = true;
+                }
+                continue;
+            }
+            TokenKind::Colorn => continue,
+            TokenKind::RightBracket | TokenKind::RightSquareBracket => {
+                let frame = stack.pop().unwrap();
+                if !pending.is_empty() {
+                    comments
+                        .inner
+                        .insert(frame.path.clone(), std::mem::take(&mut pending));
+                }
+                last = Some((frame.path, token.span.end.line));
+                continue;
+            }
+            _ => {}
+        }
+
+        // 要素の始まりなら新しいパスを割り当てる
+        let starts_item = stack.last().is_none_or(|frame| frame.expect_item);
+        let path = match stack.last_mut() {
+            None => Vec::new(),
+            Some(frame) if starts_item => {
+                let mut path = frame.path.clone();
+                path.push(frame.count);
+                frame.count += 1;
+                frame.current = path.clone();
+                frame.expect_item = false;
+                path
+            }
+            Some(frame) => frame.current.clone(),
+        };
+        if !pending.is_empty() {
+            // キーと値の間のコメントもメンバーの前に出す
+            comments
+                .leading
+                .entry(path.clone())
+                .or_default()
+                .append(&mut pending);
+        }
+        if starts_item {
+            last = None;
+            if stack.last().is_some_and(|frame| frame.is_object) {
+                // オブジェクトのキー。値はコロンの後に来る
+                continue;
+            }
+        }
+        match token.kind {
+            TokenKind::LeftBracket | TokenKind::LeftSquareBracket => stack.push(Frame {
+                path,
+                is_object: token.kind == TokenKind::LeftBracket,
+                count: 0,
+                current: Vec::new(),
+                expect_item: true,
+            }),
+            _ => last = Some((path, token.span.end.line)),
+        }
+    }
+    comments.end = pending;
+    return comments;
+}
+
+/// Whether a block comment is followed on its own line by the next member or
+/// element, as in `[1, /* two */ 2]`, so that it belongs before that item.
+fn precedes_item(comment: &Comment, rest: &[Token]) -> bool {
+    if comment.is_line() {
+        return false;
+    }
+    let next = rest
+        .iter()
+        .find(|token| !matches!(token.kind, TokenKind::Comment(_)));
+    match next {
+        Some(token) if token.span.start.line == comment.span.end.line => {
+            return !matches!(
+                token.kind,
+                TokenKind::Commma | TokenKind::RightBracket | TokenKind::RightSquareBracket
+            );
+        }
+        _ => return false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParseOptions;
+    use crate::tokenizer;
+
+    fn comments(source: &str) -> Comments {
+        let tokens = tokenizer::tokenize_bytes_with(source.as_bytes(), &ParseOptions::jsonc());
+        return collect(&tokens.unwrap());
+    }
+
+    fn texts(comments: &[Comment]) -> Vec<&str> {
+        return comments
+            .iter()
+            .map(|comment| comment.text.as_str())
+            .collect();
+    }
+
+    #[test]
+    fn test_collect() {
+        let source = r#"// file
+{
+  // about a
+  "a": 1, // one
+  "b": [
+    2, /* two */
+    // before three
+    3
+    // after three
+  ],
+  "c": /* odd */ {} // c
+  // last
+} // root
+// end"#;
+        let comments = comments(source);
+        assert_eq!(texts(comments.leading(&[])), vec!["// file"]);
+        assert_eq!(texts(comments.leading(&[0])), vec!["// about a"]);
+        assert_eq!(comments.trailing(&[0]).unwrap().text, "// one");
+        assert_eq!(comments.trailing(&[1, 0]).unwrap().text, "/* two */");
+        assert_eq!(texts(comments.leading(&[1, 1])), vec!["// before three"]);
+        assert_eq!(texts(comments.inner(&[1])), vec!["// after three"]);
+        assert_eq!(texts(comments.leading(&[2])), vec!["/* odd */"]);
+        assert_eq!(comments.trailing(&[2]).unwrap().text, "// c");
+        assert_eq!(texts(comments.inner(&[])), vec!["// last"]);
+        assert_eq!(comments.trailing(&[]).unwrap().text, "// root");
+        assert_eq!(texts(comments.end()), vec!["// end"]);
+        assert!(comments.trailing(&[1]).is_none());
+
+        // 次の要素と同じ行のブロックコメントはその要素の前
+        let inline = self::comments("[1, /* two */ 2]");
+        assert_eq!(texts(inline.leading(&[1])), vec!["/* two */"]);
+        assert!(inline.trailing(&[0]).is_none());
+    }
+
+    #[test]
+    fn test_collect_empty() {
+        assert!(comments(r#"{"a": [1, {"b": null}]}"#).is_empty());
+        let comments = comments("[ /* nothing */ ]");
+        assert_eq!(texts(comments.inner(&[])), vec!["/* nothing */"]);
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt;
+
+use super::tokenizer::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// How many characters of the offending line `render` shows at most, and
+/// how many of them come before the caret.
+const SNIPPET_WIDTH: usize = 80;
+const CONTEXT_BEFORE: usize = 40;
+
+/// A message attached to a location in the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            span,
+            message,
+        }
+    }
+
+    pub fn warning(span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message,
+        }
+    }
+
+    /// Renders just the message and its location, for when the source text
+    /// is not kept around.
+    pub fn render_short(&self, name: &str) -> String {
+        return format!(
+            "{}: {}\n --> {}:{}:{}\n",
+            self.severity, self.message, name, self.span.start.line, self.span.start.column
+        );
+    }
+
+    /// Renders the message followed by the offending line of `source` with a
+    /// caret under the span. A long line is cut down to the part around the
+    /// caret, with `...` where text was left out:
+    ///
+    /// ```text
+    /// error: expected ':', found number 1
+    ///  --> <stdin>:1:6
+    ///   |
+    /// 1 | {"a" 1}
+    ///   |      ^
+    /// ```
+    pub fn render(&self, source: &str, name: &str) -> String {
+        let start = self.span.start;
+        let offset = start.offset.min(source.len());
+        // 長い行でも印の周りだけを読む
+        let mut before = source[..offset]
+            .chars()
+            .rev()
+            .take_while(|ch| *ch != '\n')
+            .take(CONTEXT_BEFORE + 1)
+            .collect::<Vec<char>>();
+        let clipped_before = before.len() > CONTEXT_BEFORE;
+        before.truncate(CONTEXT_BEFORE);
+        before.reverse();
+        let room = SNIPPET_WIDTH - before.len();
+        let mut after = source[offset..]
+            .chars()
+            .take_while(|ch| *ch != '\n')
+            .take(room + 1)
+            .collect::<Vec<char>>();
+        let clipped_after = after.len() > room;
+        after.truncate(room);
+        if !clipped_after {
+            while after.last() == Some(&'\r') {
+                after.pop();
+            }
+        }
+
+        let mut snippet = String::new();
+        let mut padding = String::new();
+        if clipped_before {
+            snippet.push_str("...");
+            padding.push_str("   ");
+        }
+        snippet.extend(before.iter().chain(after.iter()));
+        if clipped_after {
+            snippet.push_str("...");
+        }
+        // タブの幅が端末によって違うので、タブはそのまま残して位置を合わせる
+        padding.extend(before.iter().map(|ch| if *ch == '\t' { '\t' } else { ' ' }));
+        let width = if self.span.end.line == start.line && self.span.end.column > start.column {
+            self.span.end.column - start.column
+        } else {
+            1
+        };
+        let width = width.min(after.len()).max(1);
+
+        let line_number = start.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        return format!(
+            "{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self.severity,
+            self.message,
+            gutter,
+            name,
+            start.line,
+            start.column,
+            gutter,
+            line_number,
+            snippet,
+            gutter,
+            padding,
+            "^".repeat(width)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tokenizer::Position;
+
+    #[test]
+    fn test_render() {
+        let source = "{\n\t\"a\" 1\n}";
+        let diagnostic = Diagnostic::error(
+            Span {
+                start: Position {
+                    offset: 7,
+                    line: 2,
+                    column: 6,
+                },
+                end: Position {
+                    offset: 8,
+                    line: 2,
+                    column: 7,
+                },
+            },
+            "expected ':', found number 1".to_string(),
+        );
+        assert_eq!(
+            diagnostic.render(source, "test.json"),
+            "error: expected ':', found number 1\n --> test.json:2:6\n  |\n2 | \t\"a\" 1\n  | \t    ^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_short() {
+        let diagnostic = Diagnostic::error(
+            Span {
+                start: Position {
+                    offset: 120,
+                    line: 7,
+                    column: 12,
+                },
+                end: Position {
+                    offset: 121,
+                    line: 7,
+                    column: 13,
+                },
+            },
+            "expected ':', found '1'".to_string(),
+        );
+        assert_eq!(
+            diagnostic.render_short("<stdin>"),
+            "error: expected ':', found '1'\n --> <stdin>:7:12\n"
+        );
+    }
+
+    #[test]
+    fn test_render_end_of_input() {
+        let source = "[1,";
+        let diagnostic = Diagnostic::error(
+            Span {
+                start: Position {
+                    offset: 3,
+                    line: 1,
+                    column: 4,
+                },
+                end: Position {
+                    offset: 3,
+                    line: 1,
+                    column: 4,
+                },
+            },
+            "expected a value, found end of input".to_string(),
+        );
+        assert_eq!(
+            diagnostic.render(source, "<stdin>"),
+            "error: expected a value, found end of input\n --> <stdin>:1:4\n  |\n1 | [1,\n  |    ^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_long_line() {
+        let source = format!("[{}1 2{}]", "0,".repeat(100), ",3".repeat(100));
+        let offset = source.find(" 2").unwrap() + 1;
+        let diagnostic = Diagnostic::error(
+            Span {
+                start: Position {
+                    offset,
+                    line: 1,
+                    column: offset + 1,
+                },
+                end: Position {
+                    offset: offset + 1,
+                    line: 1,
+                    column: offset + 2,
+                },
+            },
+            "expected ',' or ']', found number 2".to_string(),
+        );
+        let rendered = diagnostic.render(&source, "<stdin>");
+        let lines = rendered.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[1], " --> <stdin>:1:204");
+        let snippet = format!("...{}1 2{},...", "0,".repeat(19), ",3".repeat(19));
+        assert_eq!(lines[3], format!("1 | {}", snippet));
+        assert_eq!(lines[4], format!("  | {}^", " ".repeat(3 + 40)));
+
+        // 短い行はそのまま出す
+        let diagnostic = Diagnostic::error(diagnostic.span, String::new());
+        assert!(diagnostic.render("[1 2]", "-").contains("1 | [1 2]\n"));
+        // 行末の CR は出さない
+        let diagnostic = Diagnostic::error(
+            Span {
+                start: Position {
+                    offset: 3,
+                    line: 1,
+                    column: 4,
+                },
+                end: Position {
+                    offset: 3,
+                    line: 1,
+                    column: 4,
+                },
+            },
+            String::new(),
+        );
+        assert!(diagnostic
+            .render("[1,\r\n", "-")
+            .ends_with("1 | [1,\n  |    ^\n"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Line-based unified diffs, as printed by `diff -u`.
+
+use std::collections::HashSet;
+
+/// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+/// Fixed part of the work allowed for one middle snake search.
+const BUDGET: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One line of the edit script, with the number of old and new lines that
+/// come before it.
+struct Edit<'a> {
+    op: Op,
+    text: &'a str,
+    old: usize,
+    new: usize,
+}
+
+/// Returns a unified diff turning `old` into `new`, or an empty string when
+/// they are equal.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let pairs = matching_lines(&a, &b);
+    let edits = edit_script(&a, &b, &pairs);
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut i = 0;
+    while let Some(first) = (i..edits.len()).find(|&j| edits[j].op != Op::Equal) {
+        let start = first.saturating_sub(CONTEXT).max(i);
+        // 間の一致行が少なければ次の変更と同じハンクにまとめる
+        let mut end = first;
+        loop {
+            while end < edits.len() && edits[end].op != Op::Equal {
+                end += 1;
+            }
+            match (end..edits.len()).find(|&j| edits[j].op != Op::Equal) {
+                Some(next) if next - end <= 2 * CONTEXT => end = next,
+                _ => break,
+            }
+        }
+        let stop = (end + CONTEXT).min(edits.len());
+        write_hunk(&mut out, &edits[start..stop]);
+        i = stop;
+    }
+    return out;
+}
+
+fn write_hunk(out: &mut String, edits: &[Edit]) {
+    let old_count = edits.iter().filter(|edit| edit.op != Op::Insert).count();
+    let new_count = edits.iter().filter(|edit| edit.op != Op::Delete).count();
+    out.push_str(&format!(
+        "@@ -{} +{} @@\n",
+        range(edits[0].old, old_count),
+        range(edits[0].new, new_count)
+    ));
+    for edit in edits {
+        out.push(match edit.op {
+            Op::Equal => ' ',
+            Op::Delete => '-',
+            Op::Insert => '+',
+        });
+        out.push_str(edit.text);
+        if !edit.text.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// `diff -u` style range: the first line and the count, with the count left
+/// out when it is 1 and the line before the hunk used when it is 0.
+fn range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, count),
+    }
+}
+
+/// Turns the matched line pairs into an edit script.
+fn edit_script<'a>(a: &[&'a str], b: &[&'a str], pairs: &[(usize, usize)]) -> Vec<Edit<'a>> {
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let sentinel = [(a.len(), b.len())];
+    for &(i, j) in pairs.iter().chain(sentinel.iter()) {
+        while x < i {
+            edits.push(Edit {
+                op: Op::Delete,
+                text: a[x],
+                old: x,
+                new: y,
+            });
+            x += 1;
+        }
+        while y < j {
+            edits.push(Edit {
+                op: Op::Insert,
+                text: b[y],
+                old: x,
+                new: y,
+            });
+            y += 1;
+        }
+        if i < a.len() {
+            edits.push(Edit {
+                op: Op::Equal,
+                text: a[x],
+                old: x,
+                new: y,
+            });
+            x += 1;
+            y += 1;
+        }
+    }
+    return edits;
+}
+
+/// Returns the index pairs of a longest common subsequence of `a` and `b`.
+fn matching_lines(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    // 片方にしかない行は対応しようがないので先に除く。一行に詰めた JSON と
+    // 整形後の比較のように、ほとんどの行が変わる場合に速くなる
+    let in_a: HashSet<&str> = a.iter().copied().collect();
+    let in_b: HashSet<&str> = b.iter().copied().collect();
+    let a_index: Vec<usize> = (0..a.len()).filter(|&i| in_b.contains(a[i])).collect();
+    let b_index: Vec<usize> = (0..b.len()).filter(|&j| in_a.contains(b[j])).collect();
+    let a_kept: Vec<&str> = a_index.iter().map(|&i| a[i]).collect();
+    let b_kept: Vec<&str> = b_index.iter().map(|&j| b[j]).collect();
+
+    let mut pairs = Vec::new();
+    common_lines(&a_kept, &b_kept, 0, 0, &mut pairs);
+    return pairs
+        .into_iter()
+        .map(|(i, j)| (a_index[i], b_index[j]))
+        .collect();
+}
+
+/// Appends the index pairs of a longest common subsequence of `a` and `b`,
+/// offset by `a0` and `b0`, in order. Uses Myers' linear space refinement so
+/// large inputs with many changes stay cheap in memory. When a region is too
+/// costly to compare it is treated as replaced outright, so the diff stays
+/// correct but may not be minimal.
+fn common_lines(a: &[&str], b: &[&str], a0: usize, b0: usize, out: &mut Vec<(usize, usize)>) {
+    let mut prefix = 0;
+    while prefix < a.len() && prefix < b.len() && a[prefix] == b[prefix] {
+        out.push((a0 + prefix, b0 + prefix));
+        prefix += 1;
+    }
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let (a0, b0) = (a0 + prefix, b0 + prefix);
+    let mut suffix = 0;
+    while suffix < a.len() && suffix < b.len() && a[a.len() - 1 - suffix] == b[b.len() - 1 - suffix]
+    {
+        suffix += 1;
+    }
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    // 前後の一致を除いた後、両方に行が残っていれば中央のスネークで分割する
+    if !a.is_empty() && !b.is_empty() {
+        if let Some((x, y, u, v)) = middle_snake(a, b) {
+            common_lines(&a[..x], &b[..y], a0, b0, out);
+            for i in 0..u - x {
+                out.push((a0 + x + i, b0 + y + i));
+            }
+            common_lines(&a[u..], &b[v..], a0 + u, b0 + v, out);
+        }
+    }
+    for i in 0..suffix {
+        out.push((a0 + a.len() + i, b0 + b.len() + i));
+    }
+}
+
+/// Finds the middle snake of a shortest edit script from `a` to `b`, as
+/// `(x, y, u, v)` where the snake runs from `(x, y)` to `(u, v)`. Gives up
+/// with `None` once the search has done more than `BUDGET` plus a few steps
+/// per line.
+fn middle_snake(a: &[&str], b: &[&str]) -> Option<(usize, usize, usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // 前方向は x、後ろ方向は末尾から数えた x を対角線ごとに持つ
+    let mut forward = vec![0isize; (2 * offset + 1) as usize];
+    let mut backward = vec![0isize; (2 * offset + 1) as usize];
+    let at = |k: isize| (k + offset) as usize;
+    let limit = BUDGET + 4 * (a.len() + b.len());
+    let mut work = 0;
+
+    for d in 0..=max {
+        if work > limit {
+            return None;
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            work += (x - x0) as usize + 1;
+            forward[at(k)] = x;
+            let rk = delta - k;
+            if delta % 2 != 0 && -d < rk && rk < d && x + backward[at(rk)] >= n {
+                return Some((x0 as usize, y0 as usize, x as usize, y as usize));
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            work += (x - x0) as usize + 1;
+            backward[at(k)] = x;
+            let fk = delta - k;
+            if delta % 2 == 0 && -d <= fk && fk <= d && x + forward[at(fk)] >= n {
+                return Some((
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - x0) as usize,
+                    (m - y0) as usize,
+                ));
+            }
+        }
+    }
+    unreachable!("a shortest edit script always has a middle snake");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "x", "y"), "");
+        assert_eq!(
+            unified_diff("a\nb\nc\n", "a\nB\nc\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+        assert_eq!(
+            unified_diff("{\"a\":1}", "{\n  \"a\": 1\n}\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1 +1,3 @@\n-{\"a\":1}\n\\ No newline at end of file\n+{\n+  \"a\": 1\n+}\n"
+        );
+        assert_eq!(
+            unified_diff("", "x\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1 @@\n+x\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let lines = |changes: &[(usize, &str)]| -> String {
+            return (1..=20)
+                .map(|i| match changes.iter().find(|(line, _)| *line == i) {
+                    Some((_, text)) => format!("{}\n", text),
+                    None => format!("{}\n", i),
+                })
+                .collect();
+        };
+        let old = lines(&[]);
+        let new = lines(&[(2, "two"), (18, "eighteen")]);
+        assert_eq!(
+            unified_diff(&old, &new, "old", "new"),
+            "--- old\n+++ new\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+eighteen\n 19\n 20\n"
+        );
+        // 変更の間が 6 行以下なら一つのハンクになる
+        let new = lines(&[(2, "two"), (9, "nine")]);
+        assert!(unified_diff(&old, &new, "old", "new").contains("@@ -1,12 +1,12 @@\n"));
+    }
+
+    #[test]
+    fn test_common_lines_is_minimal() {
+        let cases = [
+            ("abcabba", "cbabac", 4),
+            ("xaxbxc", "abc", 3),
+            ("abc", "xyz", 0),
+            ("aaaa", "aa", 2),
+        ];
+        for (a, b, len) in cases.iter() {
+            let a: Vec<&str> = a.split("").filter(|s| !s.is_empty()).collect();
+            let b: Vec<&str> = b.split("").filter(|s| !s.is_empty()).collect();
+            let mut pairs = Vec::new();
+            common_lines(&a, &b, 0, 0, &mut pairs);
+            assert_eq!(pairs.len(), *len);
+            for window in pairs.windows(2) {
+                assert!(window[0].0 < window[1].0 && window[0].1 < window[1].1);
+            }
+            for &(i, j) in pairs.iter() {
+                assert_eq!(a[i], b[j]);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use super::diagnostic::Diagnostic;
+use super::parser::ParseError;
+use super::tokenizer::{Position, Span, TokenizeError};
+
+/// Any error produced while turning source text into a `Value`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Tokenize(TokenizeError),
+    Parse(ParseError),
+}
+
+impl Error {
+    pub fn span(&self) -> Span {
+        match self {
+            Error::Tokenize(err) => err.span,
+            Error::Parse(err) => err.span,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        return Diagnostic::error(self.span(), self.to_string());
+    }
+
+    /// Moves the positions of an error found in a piece of the input to
+    /// where they are in the whole input, the piece beginning at `start`.
+    pub(crate) fn offset_by(self, start: Position) -> Error {
+        let position = |position: Position| Position {
+            offset: position.offset + start.offset,
+            line: position.line + start.line - 1,
+            // 一行目だけは列もずれる
+            column: if position.line == 1 {
+                position.column + start.column - 1
+            } else {
+                position.column
+            },
+        };
+        let span = |span: Span| Span {
+            start: position(span.start),
+            end: position(span.end),
+        };
+        match self {
+            Error::Tokenize(mut err) => {
+                err.span = span(err.span);
+                return Error::Tokenize(err);
+            }
+            Error::Parse(mut err) => {
+                err.span = span(err.span);
+                return Error::Parse(err);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Tokenize(err) => err.fmt(f),
+            Error::Parse(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<TokenizeError> for Error {
+    fn from(err: TokenizeError) -> Error {
+        Error::Tokenize(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
@@ -0,0 +1,38 @@
+/// Encodes `s` as a JSON string literal, quotes included.
+///
+/// Only what RFC 8259 requires is escaped: `"`, `\` and control characters.
+/// Everything else, including non-ASCII text, is written as is, so decoding
+/// and re-encoding a string gives back the same text.
+pub fn quote(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\u{8}' => buf.push_str("\\b"),
+            '\u{c}' => buf.push_str("\\f"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            ch if ch < '\u{20}' => buf.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => buf.push(ch),
+        }
+    }
+    buf.push('"');
+    return buf;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("hoge"), "\"hoge\"");
+        assert_eq!(quote("a\"b\\c/d"), "\"a\\\"b\\\\c/d\"");
+        assert_eq!(quote("\u{8}\u{c}\n\r\t"), "\"\\b\\f\\n\\r\\t\"");
+        assert_eq!(quote("\u{0}\u{1f}"), "\"\\u0000\\u001f\"");
+        assert_eq!(quote("café 😀"), "\"café 😀\"");
+    }
+}
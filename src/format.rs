@@ -0,0 +1,824 @@
+use std::mem;
+
+use super::color::Style;
+use super::comment::{Comment, Comments};
+use super::escape;
+use super::json5;
+use super::number::Number;
+use super::options::{FormatOptions, NumberFormat};
+use super::parser::Document;
+use super::sort;
+use super::value::Value;
+
+/// Formats `value` according to `options`. Arrays and objects are broken
+/// onto one line per member unless `max_width` or `inline_width` let them
+/// stay on a single line.
+pub fn format_value(value: &Value, options: &FormatOptions) -> String {
+    let mut printer = Printer::new(options);
+    printer.value(value, 0);
+    if options.trailing_newline {
+        printer.buf.push_str(options.newline.as_str());
+    }
+    return printer.buf;
+}
+
+/// Formats a parsed document, writing its comments back next to the members
+/// and elements they were attached to. Compact output drops them.
+pub fn format_document(document: &Document, options: &FormatOptions) -> String {
+    let mut printer = Printer::new(options);
+    printer.document(document, 0);
+    if options.trailing_newline {
+        printer.buf.push_str(options.newline.as_str());
+    }
+    return printer.buf;
+}
+
+/// Writes a number the way `options.numbers` asks.
+pub(crate) fn number_text(number: &Number, options: &FormatOptions) -> String {
+    if options.numbers == NumberFormat::preserve() {
+        return number.as_str().to_string();
+    }
+    return number.format(&options.numbers);
+}
+
+/// Layout of a value before line breaks are chosen, in the style of
+/// Wadler's "prettier printer". A group is written on one line when it fits
+/// and broken at each of its `Line`s otherwise.
+#[derive(Debug)]
+pub(crate) enum Doc {
+    Text(Style, String),
+    /// A `//` comment or a block comment spanning lines cannot share a line
+    /// with what follows, so it keeps every group around it broken.
+    Comment(String),
+    /// 常に改行する
+    HardLine,
+    /// Punctuation written only when the enclosing group is broken.
+    IfBreak(String),
+    /// 一行に収めるときは空白、折り返すときは改行とインデント
+    Line,
+    /// 一行に収めるときは何も出さない
+    SoftLine,
+    Nest(Box<Doc>),
+    Concat(Vec<Doc>),
+    /// `width` は一行に収めたときの幅
+    Group {
+        doc: Box<Doc>,
+        width: usize,
+    },
+}
+
+impl Doc {
+    fn group(doc: Doc) -> Doc {
+        let width = doc.flat_width();
+        return Doc::Group {
+            doc: Box::new(doc),
+            width,
+        };
+    }
+
+    fn flat_width(&self) -> usize {
+        match self {
+            Doc::Text(_, text) => text.chars().count(),
+            Doc::Comment(text) if text.starts_with("//") || text.contains('\n') => usize::MAX,
+            Doc::Comment(text) => text.chars().count(),
+            Doc::HardLine => usize::MAX,
+            Doc::IfBreak(_) => 0,
+            Doc::Line => 1,
+            Doc::SoftLine => 0,
+            Doc::Nest(doc) => doc.flat_width(),
+            Doc::Concat(docs) => docs
+                .iter()
+                .fold(0, |width, doc| width.saturating_add(doc.flat_width())),
+            Doc::Group { width, .. } => *width,
+        }
+    }
+}
+
+/// Takes nested docs apart one level at a time, so dropping the doc of a
+/// deeply nested value does not recurse.
+impl Drop for Doc {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        take_nested(self, &mut stack);
+        while let Some(mut doc) = stack.pop() {
+            take_nested(&mut doc, &mut stack);
+        }
+    }
+}
+
+/// Moves the docs inside `doc` onto `stack`.
+fn take_nested(doc: &mut Doc, stack: &mut Vec<Doc>) {
+    match doc {
+        Doc::Nest(doc) | Doc::Group { doc, .. } => {
+            stack.push(mem::replace(doc.as_mut(), Doc::SoftLine))
+        }
+        Doc::Concat(docs) => stack.append(docs),
+        _ => {}
+    }
+}
+
+/// A container whose members `Printer::to_doc` is turning into docs.
+struct Frame<'v> {
+    object: bool,
+    /// 残りの要素の位置、キー、値
+    rest: std::vec::IntoIter<(usize, Option<&'v str>, &'v Value)>,
+    items: Vec<(usize, Vec<Doc>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub(crate) struct Printer<'a> {
+    options: &'a FormatOptions,
+    comments: Option<&'a Comments>,
+    pub(crate) buf: String,
+    column: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub(crate) fn new(options: &'a FormatOptions) -> Printer<'a> {
+        Printer {
+            options,
+            comments: None,
+            buf: String::new(),
+            column: 0,
+        }
+    }
+
+    /// Writes `value` as if it started on a line indented `depth` levels.
+    pub(crate) fn value(&mut self, value: &Value, depth: usize) {
+        let doc = self.to_doc(value, &mut Vec::new());
+        self.column = depth * self.options.indent.chars().count();
+        self.render(&doc, depth);
+    }
+
+    /// Like `value`, but writes the comments of `document` as well.
+    pub(crate) fn document(&mut self, document: &'a Document, depth: usize) {
+        self.comments = Some(&document.comments);
+        let value = &document.value;
+        let mut docs = Vec::new();
+        for comment in self.leading(&[]) {
+            docs.push(Doc::Comment(comment.text.clone()));
+            docs.push(comment_break(comment));
+        }
+        docs.push(self.to_doc(value, &mut Vec::new()));
+        docs.extend(self.trailing(&[]));
+        for comment in self.end() {
+            docs.push(Doc::HardLine);
+            docs.push(Doc::Comment(comment.text.clone()));
+        }
+        self.column = depth * self.options.indent.chars().count();
+        self.render(&Doc::Concat(docs), depth);
+    }
+
+    fn key(&self, key: &str) -> String {
+        if self.options.json5 {
+            return json5::key(key);
+        }
+        return escape::quote(key);
+    }
+
+    fn leading(&self, path: &[usize]) -> &'a [Comment] {
+        match self.comments {
+            Some(comments) if !self.options.compact => return comments.leading(path),
+            _ => return &[],
+        }
+    }
+
+    /// The comment after a member or element, with the space before it.
+    fn trailing(&self, path: &[usize]) -> Option<Doc> {
+        let comment = match self.comments {
+            Some(comments) if !self.options.compact => comments.trailing(path)?,
+            _ => return None,
+        };
+        return Some(Doc::Concat(vec![
+            Doc::Text(Style::Punct, " ".to_string()),
+            Doc::Comment(comment.text.clone()),
+        ]));
+    }
+
+    fn inner(&self, path: &[usize]) -> &'a [Comment] {
+        match self.comments {
+            Some(comments) if !self.options.compact => return comments.inner(path),
+            _ => return &[],
+        }
+    }
+
+    fn end(&self) -> &'a [Comment] {
+        match self.comments {
+            Some(comments) if !self.options.compact => return comments.end(),
+            _ => return &[],
+        }
+    }
+
+    /// Appends the members or elements of a container to `body`. `items`
+    /// gives each one's position in the input and its docs.
+    fn items(&self, body: &mut Vec<Doc>, path: &mut Vec<usize>, items: Vec<(usize, Vec<Doc>)>) {
+        let count = items.len();
+        for (i, (index, docs)) in items.into_iter().enumerate() {
+            path.push(index);
+            for comment in self.leading(path) {
+                body.push(Doc::Comment(comment.text.clone()));
+                body.push(comment_break(comment));
+            }
+            body.extend(docs);
+            if i + 1 < count {
+                body.push(punct(","));
+            } else if self.options.trailing_commas {
+                body.push(Doc::IfBreak(",".to_string()));
+            }
+            body.extend(self.trailing(path));
+            path.pop();
+            if i + 1 < count {
+                body.push(self.separator());
+            }
+        }
+        // 閉じ括弧の前のコメントはそれぞれ一行にする
+        for (i, comment) in self.inner(path).iter().enumerate() {
+            if count > 0 || i > 0 {
+                body.push(Doc::HardLine);
+            }
+            body.push(Doc::Comment(comment.text.clone()));
+        }
+    }
+
+    /// The separator between members: a space or a line break, or nothing
+    /// at all when compact.
+    fn separator(&self) -> Doc {
+        if self.options.compact {
+            return Doc::SoftLine;
+        }
+        return Doc::Line;
+    }
+
+    /// `path` locates `value` in the document, as the comments are keyed.
+    /// Containers are built on a stack of frames rather than by recursion.
+    fn to_doc(&self, value: &Value, path: &mut Vec<usize>) -> Doc {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut done = self.begin(value, path, &mut stack);
+        loop {
+            // できあがった値を親の最後の要素に加える
+            if let Some(doc) = done.take() {
+                match stack.last_mut() {
+                    Some(frame) => frame.items.last_mut().unwrap().1.push(doc),
+                    None => return doc,
+                }
+                path.pop();
+            }
+            let frame = stack.last_mut().unwrap();
+            match frame.rest.next() {
+                Some((index, key, value)) => {
+                    let mut docs = Vec::new();
+                    if let Some(key) = key {
+                        docs.push(Doc::Text(Style::Key, self.key(key)));
+                        docs.push(Doc::Text(Style::Punct, self.colon()));
+                    }
+                    frame.items.push((index, docs));
+                    path.push(index);
+                    done = self.begin(value, path, &mut stack);
+                }
+                None => {
+                    let frame = stack.pop().unwrap();
+                    let mut body = Vec::new();
+                    self.items(&mut body, path, frame.items);
+                    done = Some(if frame.object {
+                        container("{", body, "}")
+                    } else {
+                        container("[", body, "]")
+                    });
+                }
+            }
+        }
+    }
+
+    /// The doc of a scalar or an empty container. For any other container
+    /// pushes a frame for its members and returns `None`.
+    fn begin<'v>(
+        &self,
+        value: &'v Value,
+        path: &[usize],
+        stack: &mut Vec<Frame<'v>>,
+    ) -> Option<Doc> {
+        let empty = value.is_empty() && self.inner(path).is_empty();
+        let rest = match value {
+            Value::Object(members) if !empty => {
+                let mut members: Vec<(usize, &(String, Value))> =
+                    members.iter().enumerate().collect();
+                if let Some(sort_keys) = self.options.sort_keys {
+                    if path.is_empty() || sort_keys.recursive {
+                        // 同じキーは入力順のまま (sort_by は安定ソート)
+                        members
+                            .sort_by(|a, b| sort::compare_keys(&a.1 .0, &b.1 .0, sort_keys.order));
+                    }
+                }
+                members
+                    .into_iter()
+                    .map(|(index, (key, value))| (index, Some(key.as_str()), value))
+                    .collect::<Vec<_>>()
+            }
+            Value::Array(elements) if !empty => elements
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (index, None, value))
+                .collect::<Vec<_>>(),
+            Value::Object(_) => return Some(punct("{}")),
+            Value::Array(_) => return Some(punct("[]")),
+            Value::Str(string) => return Some(Doc::Text(Style::Str, escape::quote(string))),
+            Value::Num(number) => {
+                return Some(Doc::Text(Style::Num, number_text(number, self.options)))
+            }
+            Value::Bool(b) => return Some(Doc::Text(Style::Bool, b.to_string())),
+            Value::Null => return Some(Doc::Text(Style::Null, "null".to_string())),
+        };
+        stack.push(Frame {
+            object: value.is_object(),
+            rest: rest.into_iter(),
+            items: Vec::new(),
+        });
+        return None;
+    }
+
+    /// The colon after a key with the spacing around it.
+    fn colon(&self) -> String {
+        if self.options.compact {
+            return ":".to_string();
+        }
+        let mut text = String::new();
+        if self.options.space_before_colon {
+            text.push(' ');
+        }
+        text.push(':');
+        if self.options.space_after_colon {
+            text.push(' ');
+        }
+        return text;
+    }
+
+    fn render(&mut self, doc: &Doc, depth: usize) {
+        let mode = if self.options.compact {
+            Mode::Flat
+        } else {
+            Mode::Break
+        };
+        let mut stack: Vec<(usize, Mode, &Doc)> = vec![(depth, mode, doc)];
+        while let Some((depth, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(style, text) => self.text(*style, text),
+                Doc::Comment(text) => {
+                    match &self.options.color {
+                        Some(theme) => theme.paint(Style::Comment, text, &mut self.buf),
+                        None => self.buf.push_str(text),
+                    }
+                    let last_line = text.rsplit('\n').next().unwrap_or_default();
+                    self.column += last_line.chars().count();
+                }
+                Doc::HardLine => self.newline(depth),
+                Doc::IfBreak(text) => {
+                    if mode == Mode::Break {
+                        self.text(Style::Punct, text);
+                    }
+                }
+                Doc::Line | Doc::SoftLine => {
+                    if mode == Mode::Break {
+                        self.newline(depth);
+                    } else if let Doc::Line = doc {
+                        self.buf.push(' ');
+                        self.column += 1;
+                    }
+                }
+                Doc::Nest(doc) => stack.push((depth + 1, mode, doc)),
+                Doc::Concat(docs) => {
+                    for doc in docs.iter().rev() {
+                        stack.push((depth, mode, doc));
+                    }
+                }
+                Doc::Group { doc, width } => {
+                    let mode = if mode == Mode::Flat || self.fits(*width, &stack) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push((depth, mode, doc));
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, style: Style, text: &str) {
+        match &self.options.color {
+            Some(theme) => theme.paint(style, text, &mut self.buf),
+            None => self.buf.push_str(text),
+        }
+        self.column += text.chars().count();
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.buf.push_str(self.options.newline.as_str());
+        for _ in 0..depth {
+            self.buf.push_str(&self.options.indent);
+        }
+        self.column = depth * self.options.indent.chars().count();
+    }
+
+    /// Whether a group `width` columns wide can be written on one line
+    /// here. With `max_width`, whatever follows the group up to the next
+    /// line break must fit as well, so a closing bracket or comma is not
+    /// pushed over the limit.
+    fn fits(&self, width: usize, rest: &[(usize, Mode, &Doc)]) -> bool {
+        if let Some(inline_width) = self.options.inline_width {
+            if width > inline_width {
+                return false;
+            }
+        }
+        let max_width = match self.options.max_width {
+            Some(max_width) => max_width,
+            None => return self.options.inline_width.is_some(),
+        };
+        let used = self.column.saturating_add(width);
+        if used > max_width {
+            return false;
+        }
+        let mut remaining = (max_width - used) as isize;
+        let mut local: Vec<(Mode, &Doc)> = Vec::new();
+        let mut index = rest.len();
+        while remaining >= 0 {
+            let (mode, doc) = match local.pop() {
+                Some(next) => next,
+                None if index > 0 => {
+                    index -= 1;
+                    (rest[index].1, rest[index].2)
+                }
+                None => return true,
+            };
+            match doc {
+                Doc::Text(_, text) => remaining -= text.chars().count() as isize,
+                Doc::Comment(text) => {
+                    let first_line = text.split('\n').next().unwrap_or_default();
+                    remaining -= first_line.chars().count() as isize;
+                    // 行コメントの後ろは必ず改行になる
+                    if text.starts_with("//") || text.contains('\n') {
+                        return remaining >= 0;
+                    }
+                }
+                Doc::HardLine => return true,
+                Doc::IfBreak(text) => {
+                    if mode == Mode::Break {
+                        remaining -= text.chars().count() as isize;
+                    }
+                }
+                Doc::Line | Doc::SoftLine => {
+                    if mode == Mode::Break {
+                        return true;
+                    }
+                    if let Doc::Line = doc {
+                        remaining -= 1;
+                    }
+                }
+                Doc::Nest(doc) => local.push((mode, doc)),
+                Doc::Concat(docs) => {
+                    for doc in docs.iter().rev() {
+                        local.push((mode, doc));
+                    }
+                }
+                // 後続のグループはまだ折り返すかどうか決まっていないので、
+                // 最初の改行位置までで判断する
+                Doc::Group { doc, .. } => local.push((mode, doc)),
+            }
+        }
+        return false;
+    }
+}
+
+/// What follows a comment written before a value: a line break, unless it
+/// was a block comment on the same line as the value.
+fn comment_break(comment: &Comment) -> Doc {
+    if comment.is_line() || comment.line_break {
+        return Doc::HardLine;
+    }
+    return Doc::Text(Style::Punct, " ".to_string());
+}
+
+fn punct(text: &str) -> Doc {
+    return Doc::Text(Style::Punct, text.to_string());
+}
+
+/// `open`, the members and `close`, with the members indented one level
+/// when broken.
+fn container(open: &str, body: Vec<Doc>, close: &str) -> Doc {
+    let mut inner = vec![Doc::SoftLine];
+    inner.extend(body);
+    return Doc::group(Doc::Concat(vec![
+        punct(open),
+        Doc::Nest(Box::new(Doc::Concat(inner))),
+        Doc::SoftLine,
+        punct(close),
+    ]));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Theme;
+    use crate::options::{KeyOrder, Newline, ParseOptions, SortKeys};
+    use crate::parser;
+
+    fn format(source: &str, options: &FormatOptions) -> String {
+        let value = parser::parse_str(source).unwrap();
+        let output = format_value(&value, options);
+        value.dispose();
+        return output;
+    }
+
+    #[test]
+    fn test_format_value_default() {
+        assert_eq!(
+            format(r#"{"a":[1,{"b":null}],"c":{},"d":[]}"#, &FormatOptions::default()),
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": []\n}\n"
+        );
+        assert_eq!(format("1", &FormatOptions::default()), "1\n");
+    }
+
+    #[test]
+    fn test_format_value_options() {
+        let options = FormatOptions {
+            indent: "\t".to_string(),
+            newline: Newline::CrLf,
+            space_before_colon: true,
+            space_after_colon: false,
+            trailing_newline: false,
+            inline_width: None,
+            max_width: None,
+            compact: false,
+            color: None,
+            sort_keys: None,
+            json5: false,
+            trailing_commas: false,
+            numbers: NumberFormat::normalized(),
+        };
+        assert_eq!(
+            format(r#"{"a":{"b":true,"c":1.50E+1}}"#, &options),
+            "{\r\n\t\"a\" :{\r\n\t\t\"b\" :true,\r\n\t\t\"c\" :15\r\n\t}\r\n}"
+        );
+        assert_eq!(
+            format("[1e99999999999999999999,1e-99999999999999999999]", &options),
+            "[\r\n\t1e99999999999999999999,\r\n\t1e-99999999999999999999\r\n]"
+        );
+    }
+
+    #[test]
+    fn test_format_value_inline_width() {
+        let options = FormatOptions {
+            inline_width: Some(12),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(
+                r#"{"short":[1,2,3],"long":[100000,200000,300000],"o":{"k":"v"}}"#,
+                &options
+            ),
+            "{\n  \"short\": [1, 2, 3],\n  \"long\": [\n    100000,\n    200000,\n    300000\n  ],\n  \"o\": {\"k\": \"v\"}\n}\n"
+        );
+        // ちょうど幅に収まるものは一行にする
+        let options = FormatOptions {
+            inline_width: Some(9),
+            ..FormatOptions::default()
+        };
+        assert_eq!(format("[1, 2, 3]", &options), "[1, 2, 3]\n");
+        assert_eq!(format("[1, 2, 30]", &options), "[\n  1,\n  2,\n  30\n]\n");
+    }
+
+    #[test]
+    fn test_format_value_compact() {
+        let options = FormatOptions {
+            compact: true,
+            indent: "\t".to_string(),
+            max_width: Some(80),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format("{ \"a\" : [ 1, { } ], \"b\" : \"x y\" }", &options),
+            "{\"a\":[1,{}],\"b\":\"x y\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value_color() {
+        let options = FormatOptions {
+            color: Some(
+                Theme::parse("key=34,string=32,number=36,bool=33,null=35,punct=1").unwrap(),
+            ),
+            max_width: Some(80),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(r#"{"a":["x",1,true,null,{}]}"#, &options),
+            "\x1b[1m{\x1b[0m\x1b[34m\"a\"\x1b[0m\x1b[1m: \x1b[0m\x1b[1m[\x1b[0m\x1b[32m\"x\"\x1b[0m\
+             \x1b[1m,\x1b[0m \x1b[36m1\x1b[0m\x1b[1m,\x1b[0m \x1b[33mtrue\x1b[0m\x1b[1m,\x1b[0m \
+             \x1b[35mnull\x1b[0m\x1b[1m,\x1b[0m \x1b[1m{}\x1b[0m\x1b[1m]\x1b[0m\x1b[1m}\x1b[0m\n"
+        );
+        // 色を付けても折り返し位置は変わらない
+        let plain = FormatOptions {
+            max_width: Some(10),
+            ..FormatOptions::default()
+        };
+        let colored = FormatOptions {
+            color: Some(Theme::default()),
+            ..plain.clone()
+        };
+        let source = r#"{"a":[1,2],"b":[100,200,300]}"#;
+        let strip = |s: String| -> String {
+            let mut out = String::new();
+            let mut chars = s.chars();
+            while let Some(c) = chars.next() {
+                if c == '\x1b' {
+                    chars.by_ref().find(|&c| c == 'm');
+                } else {
+                    out.push(c);
+                }
+            }
+            return out;
+        };
+        assert_eq!(strip(format(source, &colored)), format(source, &plain));
+    }
+
+    #[test]
+    fn test_format_value_sort_keys() {
+        let source = r#"{"b":1,"a10":{"y":1,"x":2},"a2":[{"d":1,"c":2}],"b":0}"#;
+        let sorted = |order: KeyOrder, recursive: bool| -> String {
+            let options = FormatOptions {
+                sort_keys: Some(SortKeys { order, recursive }),
+                compact: true,
+                ..FormatOptions::default()
+            };
+            return format(source, &options);
+        };
+        assert_eq!(
+            sorted(KeyOrder::CodePoint, true),
+            "{\"a10\":{\"x\":2,\"y\":1},\"a2\":[{\"c\":2,\"d\":1}],\"b\":1,\"b\":0}\n"
+        );
+        assert_eq!(
+            sorted(KeyOrder::Natural, true),
+            "{\"a2\":[{\"c\":2,\"d\":1}],\"a10\":{\"x\":2,\"y\":1},\"b\":1,\"b\":0}\n"
+        );
+        assert_eq!(
+            sorted(KeyOrder::CodePoint, false),
+            "{\"a10\":{\"y\":1,\"x\":2},\"a2\":[{\"d\":1,\"c\":2}],\"b\":1,\"b\":0}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value_max_width() {
+        let options = FormatOptions {
+            max_width: Some(20),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(r#"{"a":[1,2,3],"b":{"c":null},"long":[10000,20000,30000]}"#, &options),
+            "{\n  \"a\": [1, 2, 3],\n  \"b\": {\"c\": null},\n  \"long\": [\n    10000,\n    20000,\n    30000\n  ]\n}\n"
+        );
+        // 全体が収まるなら一行で書く
+        assert_eq!(
+            format("[[1, 2], {\"a\": []}]", &options),
+            "[[1, 2], {\"a\": []}]\n"
+        );
+        // 閉じ括弧とカンマまで含めて幅に収める
+        let options = FormatOptions {
+            max_width: Some(13),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(r#"[[1, 2, 3], [1, 2, 3, 4]]"#, &options),
+            "[\n  [1, 2, 3],\n  [\n    1,\n    2,\n    3,\n    4\n  ]\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value_max_and_inline_width() {
+        let options = FormatOptions {
+            max_width: Some(80),
+            inline_width: Some(6),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(r#"{"a":[1,2],"b":[1,2,3]}"#, &options),
+            "{\n  \"a\": [1, 2],\n  \"b\": [\n    1,\n    2,\n    3\n  ]\n}\n"
+        );
+    }
+
+    fn format_jsonc(source: &str, options: &FormatOptions) -> String {
+        let document =
+            parser::parse_document_with(source.as_bytes(), &ParseOptions::jsonc()).unwrap();
+        return format_document(&document, options);
+    }
+
+    #[test]
+    fn test_format_document_comments() {
+        let source = "// head\n{\n  /* a */ \"a\": 1, // after a\n  \"b\": [\n    2 // two\n    // end of b\n  ],\n  \"c\": {} // c\n  // last\n}\n// tail\n";
+        assert_eq!(
+            format_jsonc(source, &FormatOptions::default()),
+            "// head\n{\n  /* a */ \"a\": 1, // after a\n  \"b\": [\n    2 // two\n    // end of b\n  ],\n  \"c\": {} // c\n  // last\n}\n// tail\n"
+        );
+        assert_eq!(
+            format_jsonc("{\"a\":[/* none */]}", &FormatOptions::default()),
+            "{\n  \"a\": [\n    /* none */\n  ]\n}\n"
+        );
+        let compact = FormatOptions {
+            compact: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_jsonc(source, &compact),
+            "{\"a\":1,\"b\":[2],\"c\":{}}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_document_comments_layout() {
+        // 並べ替えてもコメントはメンバーについていく
+        let sorted = FormatOptions {
+            sort_keys: Some(SortKeys::default()),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_jsonc("{\"b\": 1, // b\n\"a\": 2 // a\n}", &sorted),
+            "{\n  \"a\": 2, // a\n  \"b\": 1 // b\n}\n"
+        );
+        // 行コメントがあると一行にはまとめない
+        let wide = FormatOptions {
+            max_width: Some(80),
+            ..FormatOptions::default()
+        };
+        assert_eq!(format_jsonc("[1, /* x */ 2]", &wide), "[1, /* x */ 2]\n");
+        assert_eq!(
+            format_jsonc("[1, // x\n 2]", &wide),
+            "[\n  1, // x\n  2\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value_json5() {
+        let options = FormatOptions {
+            json5: true,
+            ..FormatOptions::default()
+        };
+        let value =
+            parser::parse_str_with("{a: .5, 'b-c': 0x1F, $d: [+1]}", &ParseOptions::json5())
+                .unwrap();
+        assert_eq!(
+            format_value(&value, &options),
+            "{\n  a: .5,\n  \"b-c\": 0x1F,\n  $d: [\n    +1\n  ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value_trailing_commas() {
+        let options = FormatOptions {
+            trailing_commas: true,
+            max_width: Some(20),
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format(r#"{"a":[1,2],"b":{"c":"a long string"}}"#, &options),
+            "{\n  \"a\": [1, 2],\n  \"b\": {\n    \"c\": \"a long string\",\n  },\n}\n"
+        );
+        let compact = FormatOptions {
+            trailing_commas: true,
+            compact: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(format("[1,[2]]", &compact), "[1,[2]]\n");
+        // コメントはカンマの後ろに来る
+        let options = FormatOptions {
+            trailing_commas: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_jsonc("[1 // one\n]", &options),
+            "[\n  1, // one\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_format_value_deep_nesting() {
+        // 再帰しないので、スレッドのスタックより深く入れ子にできる
+        let depth = 100_000;
+        let source = format!("{}1{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+        let compact = FormatOptions {
+            compact: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(format(&source, &compact), format!("{}\n", source));
+        // 字下げは深さに比例して長くなるので浅めにする
+        let depth = 5_000;
+        let source = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let options = FormatOptions {
+            indent: String::new(),
+            ..FormatOptions::default()
+        };
+        assert_eq!(format("[[[]]]", &options), "[\n[\n[]\n]\n]\n");
+        assert_eq!(format(&source, &options).lines().count(), depth * 2 - 1);
+    }
+}
@@ -0,0 +1,184 @@
+//! Conversion between JSON5 spellings and plain JSON.
+
+use std::fmt;
+
+use super::escape;
+use super::number::Number;
+use super::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json5Error {
+    /// `Infinity`, `-Infinity` or `NaN`, which JSON cannot represent.
+    NotFinite(String),
+}
+
+impl fmt::Display for Json5Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json5Error::NotFinite(lexeme) => write!(f, "{} cannot be written as JSON", lexeme),
+        }
+    }
+}
+
+impl std::error::Error for Json5Error {}
+
+/// Rewrites the numbers of a value parsed as JSON5 into JSON's grammar, so
+/// that it prints as plain JSON. Strings and keys need no change once
+/// parsed. Nested values are visited with a stack rather than by recursion.
+pub fn to_json(mut value: Value) -> Result<Value, Json5Error> {
+    match rewrite_numbers(&mut value) {
+        Ok(()) => return Ok(value),
+        Err(err) => {
+            value.dispose();
+            return Err(err);
+        }
+    }
+}
+
+fn rewrite_numbers(value: &mut Value) -> Result<(), Json5Error> {
+    // 入力順に見て、最初の誤りを返す
+    let mut stack: Vec<&mut Value> = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            Value::Object(members) => stack.extend(members.iter_mut().rev().map(|(_, v)| v)),
+            Value::Array(elements) => stack.extend(elements.iter_mut().rev()),
+            Value::Num(number) => match number_to_json(number.as_str()) {
+                Some(lexeme) => *number = Number::from_lexeme(lexeme),
+                None => return Err(Json5Error::NotFinite(number.to_string())),
+            },
+            _ => {}
+        }
+    }
+    return Ok(());
+}
+
+/// Spells a JSON5 number lexeme as a JSON number with the same value:
+/// `+1` becomes `1`, `.5` `0.5`, `5.` `5` and `0x1F` `31`. Returns `None`
+/// for `Infinity` and `NaN`.
+pub fn number_to_json(lexeme: &str) -> Option<String> {
+    let (sign, digits) = match lexeme.as_bytes().first() {
+        Some(b'-') => ("-", &lexeme[1..]),
+        Some(b'+') => ("", &lexeme[1..]),
+        _ => ("", lexeme),
+    };
+    if digits == "Infinity" || digits == "NaN" {
+        return None;
+    }
+    if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        return Some(format!("{}{}", sign, hex_to_decimal(hex)));
+    }
+    let (mantissa, exponent) = digits.split_at(digits.find(['e', 'E']).unwrap_or(digits.len()));
+    let mantissa = match mantissa.split_once('.') {
+        Some(("", fraction)) => format!("0.{}", fraction),
+        Some((int, "")) => int.to_string(),
+        _ => mantissa.to_string(),
+    };
+    return Some(format!("{}{}{}", sign, mantissa, exponent));
+}
+
+/// Converts hexadecimal digits of any length to decimal.
+fn hex_to_decimal(hex: &str) -> String {
+    // 10 進の各桁を下の桁から順に持つ
+    let mut digits: Vec<u32> = vec![0];
+    for ch in hex.chars() {
+        let mut carry = ch.to_digit(16).unwrap();
+        for digit in digits.iter_mut() {
+            let n = *digit * 16 + carry;
+            *digit = n % 10;
+            carry = n / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
+    }
+    return digits
+        .iter()
+        .rev()
+        .map(|digit| char::from_digit(*digit, 10).unwrap())
+        .collect();
+}
+
+/// Writes an object key the way JSON5 allows: bare when it is an
+/// identifier, quoted otherwise.
+pub fn key(key: &str) -> String {
+    if is_identifier(key) {
+        return key.to_string();
+    }
+    return escape::quote(key);
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(ch) if ch.is_alphabetic() || ch == '$' || ch == '_' => {}
+        _ => return false,
+    }
+    return chars.all(|ch| {
+        ch.is_alphanumeric() || ch == '$' || ch == '_' || ch == '\u{200c}' || ch == '\u{200d}'
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::ParseOptions;
+    use crate::parser::{self, Node};
+
+    #[test]
+    fn test_number_to_json() {
+        assert_eq!(number_to_json("1").unwrap(), "1");
+        assert_eq!(number_to_json("+1.5e3").unwrap(), "1.5e3");
+        assert_eq!(number_to_json("-.5").unwrap(), "-0.5");
+        assert_eq!(number_to_json("5.").unwrap(), "5");
+        assert_eq!(number_to_json("5.e-3").unwrap(), "5e-3");
+        assert_eq!(number_to_json("0x1F").unwrap(), "31");
+        assert_eq!(number_to_json("-0x0").unwrap(), "-0");
+        assert_eq!(
+            number_to_json("0xFFFFFFFFFFFFFFFFFFFF").unwrap(),
+            "1208925819614629174706175"
+        );
+        assert_eq!(number_to_json("Infinity"), None);
+        assert_eq!(number_to_json("-Infinity"), None);
+        assert_eq!(number_to_json("NaN"), None);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let source = "{a: [+1, .5, 0x10], 'b': {c: 5.}}";
+        let value = parser::parse_str_with(source, &ParseOptions::json5()).unwrap();
+        assert_eq!(
+            to_json(value).unwrap(),
+            parser::parse_str(r#"{"a": [1, 0.5, 16], "b": {"c": 5}}"#).unwrap()
+        );
+        let value = parser::parse_str_with("[1, -Infinity]", &ParseOptions::json5()).unwrap();
+        assert_eq!(
+            to_json(value).unwrap_err().to_string(),
+            "-Infinity cannot be written as JSON"
+        );
+    }
+
+    #[test]
+    fn test_to_json_deep_nesting() {
+        let depth = 100_000;
+        let source = format!("{}0x1F{}", "[".repeat(depth), "]".repeat(depth));
+        let value = parser::parse_str_with(&source, &ParseOptions::json5()).unwrap();
+        let expected = format!("{}31{}", "[".repeat(depth), "]".repeat(depth));
+        let value = to_json(value).unwrap();
+        assert_eq!(value.print_node(), expected);
+        value.dispose();
+        let source = format!("{}1{}, NaN]", "[".repeat(depth), "]".repeat(depth - 1));
+        let value = parser::parse_str_with(&source, &ParseOptions::json5()).unwrap();
+        assert!(to_json(value).is_err());
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key("name"), "name");
+        assert_eq!(key("$_x1"), "$_x1");
+        assert_eq!(key("café"), "café");
+        assert_eq!(key("1a"), "\"1a\"");
+        assert_eq!(key("a-b"), "\"a-b\"");
+        assert_eq!(key(""), "\"\"");
+    }
+}
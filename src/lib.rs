@@ -1,3 +1,20 @@
+pub mod canonical;
+pub mod color;
+pub mod comment;
+pub mod diagnostic;
+pub mod diff;
+pub mod error;
+pub mod escape;
+pub mod format;
+pub mod json5;
+pub mod lines;
 pub mod number;
+pub mod options;
 pub mod parser;
+pub mod recover;
+pub mod repair;
+pub mod sequence;
+pub mod sort;
+pub mod stream;
 pub mod tokenizer;
+pub mod value;
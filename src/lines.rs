@@ -0,0 +1,188 @@
+//! Newline-delimited JSON (NDJSON, JSON Lines): one document per line.
+
+use std::io::{self, BufRead, Write};
+
+use super::format;
+use super::options::{FormatOptions, ParseOptions};
+use super::parser;
+use super::stream::StreamError;
+use super::tokenizer::Position;
+use super::value::Value;
+
+/// Reads the documents of newline-delimited input one line at a time.
+pub struct LineReader<R> {
+    reader: R,
+    options: ParseOptions,
+    buf: Vec<u8>,
+    /// 直前に読んだ行の番号
+    line: usize,
+    /// 次の行の先頭のバイト位置
+    offset: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> LineReader<R> {
+        return LineReader::with_options(reader, ParseOptions::default());
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> LineReader<R> {
+        LineReader {
+            reader,
+            options,
+            buf: Vec::new(),
+            line: 0,
+            offset: 0,
+        }
+    }
+
+    /// The number of the line last read, counting from 1.
+    pub fn line(&self) -> usize {
+        return self.line;
+    }
+
+    /// Reads the document on the next line that is not blank, or returns
+    /// `None` at the end of the input. A line that does not parse gives a
+    /// `StreamError::Syntax` whose positions count from the start of the
+    /// input; reading can go on with the next line.
+    pub fn next_value(&mut self) -> Result<Option<Value>, StreamError> {
+        loop {
+            self.buf.clear();
+            let n = self.reader.read_until(b'\n', &mut self.buf)?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let start = self.offset;
+            self.offset += n;
+            let text = trim_line_end(&self.buf);
+            if text.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return match parser::parse_bytes_with(text, &self.options) {
+                Ok(value) => Ok(Some(value)),
+                Err(err) => {
+                    let start = Position {
+                        offset: start,
+                        line: self.line,
+                        column: 1,
+                    };
+                    return Err(StreamError::Syntax(err.offset_by(start)));
+                }
+            };
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = Result<Value, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.next_value().transpose();
+    }
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    return line.strip_suffix(b"\r").unwrap_or(line);
+}
+
+/// Formats `value` on a line of its own. Layout options do not apply, but
+/// colors, sorted keys and JSON5 style do.
+pub fn format_line(value: &Value, options: &FormatOptions) -> String {
+    let options = FormatOptions {
+        compact: true,
+        trailing_newline: true,
+        ..options.clone()
+    };
+    return format::format_value(value, &options);
+}
+
+/// Writes `value` as one line of newline-delimited output.
+pub fn write_line<W: Write>(
+    writer: &mut W,
+    value: &Value,
+    options: &FormatOptions,
+) -> io::Result<()> {
+    return writer.write_all(format_line(value, options).as_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::number::Number;
+    use crate::options::{KeyOrder, Newline, SortKeys};
+
+    fn read(source: &str) -> Vec<Result<Value, String>> {
+        return LineReader::new(source.as_bytes())
+            .map(|result| {
+                return result.map_err(|err| match err {
+                    StreamError::Syntax(err) => {
+                        let start = err.span().start;
+                        return format!("{}:{}:{} {}", start.offset, start.line, start.column, err);
+                    }
+                    StreamError::Io(err) => err.to_string(),
+                });
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_line_reader() {
+        let values = read("{\"a\": 1}\n\n  [1, 2]\r\n\"x\"");
+        assert_eq!(
+            values,
+            vec![
+                Ok(parser::parse_str(r#"{"a": 1}"#).unwrap()),
+                Ok(parser::parse_str("[1, 2]").unwrap()),
+                Ok(Value::Str("x".to_string())),
+            ]
+        );
+        assert!(read("").is_empty());
+        assert!(read("\n \n").is_empty());
+    }
+
+    #[test]
+    fn test_line_reader_errors() {
+        let values = read("1\n{\"a\" 1}\n[\n2\n");
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[0], Ok(Value::Num(Number::from(1))));
+        assert_eq!(
+            values[1],
+            Err("7:2:6 expected ':', found number 1".to_string())
+        );
+        assert!(values[2].as_ref().unwrap_err().starts_with("11:3:2 "));
+        assert_eq!(values[3], Ok(Value::Num(Number::from(2))));
+
+        let mut lines = LineReader::new("1\n\nx\n".as_bytes());
+        lines.next_value().unwrap();
+        assert_eq!(lines.line(), 1);
+        assert!(lines.next_value().is_err());
+        assert_eq!(lines.line(), 3);
+        assert!(lines.next_value().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_format_line() {
+        let value = parser::parse_str(r#"{"b": [1, 2], "a": {"c": null}}"#).unwrap();
+        assert_eq!(
+            format_line(&value, &FormatOptions::default()),
+            "{\"b\":[1,2],\"a\":{\"c\":null}}\n"
+        );
+        let options = FormatOptions {
+            newline: Newline::CrLf,
+            sort_keys: Some(SortKeys {
+                order: KeyOrder::CodePoint,
+                recursive: true,
+            }),
+            trailing_newline: false,
+            ..FormatOptions::default()
+        };
+        let mut out = Vec::new();
+        write_line(&mut out, &value, &options).unwrap();
+        write_line(&mut out, &Value::Null, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"a\":{\"c\":null},\"b\":[1,2]}\r\nnull\r\n"
+        );
+    }
+}
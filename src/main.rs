@@ -1,22 +1,563 @@
-use json_formatter::{parser, tokenizer};
+mod atomic;
+mod cli;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process;
+
+use atomic::AtomicFile;
+use cli::{Args, Documents};
+use json_formatter::canonical::{self, CanonicalError};
+use json_formatter::comment::Comments;
+use json_formatter::diff;
+use json_formatter::error::Error;
+use json_formatter::format;
+use json_formatter::json5::{self, Json5Error};
+use json_formatter::lines::{self, LineReader};
+use json_formatter::number;
+use json_formatter::options::{DuplicateKeys, FormatOptions};
+use json_formatter::parser::{self, Document, Values};
+use json_formatter::recover;
+use json_formatter::repair;
+use json_formatter::sequence::{self, SequenceReader};
+use json_formatter::stream::{self, StreamError};
+use json_formatter::tokenizer;
+use json_formatter::value::Value;
 
 fn main() {
-    let mut buf = String::new();
-    loop {
-        match std::io::stdin().read_line(&mut buf) {
-            Ok(0) => {
-                break;
+    let mut args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            eprintln!("try 'json-formatter --help' for more information");
+            process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", cli::USAGE);
+        return;
+    }
+    if args.version {
+        println!("json-formatter {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    if args.check {
+        check(&args);
+        return;
+    }
+
+    let theme = args.theme(
+        io::stdout().is_terminal(),
+        std::env::var("NO_COLOR").ok(),
+        std::env::var("JSON_FORMATTER_THEME").ok(),
+    );
+    args.format.color = match theme {
+        Ok(theme) => theme,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::exit(2);
+        }
+    };
+
+    // 失敗したファイルがあっても残りは処理する
+    let mut failed = false;
+    let mut total = Sizes::default();
+    let inputs = args.inputs();
+    for input in inputs.iter() {
+        match format_input(input, &args) {
+            Ok(sizes) => {
+                if args.stats {
+                    eprintln!("{}: {}", display_name(input), sizes);
+                }
+                total.input += sizes.input;
+                total.output += sizes.output;
+            }
+            Err(message) => {
+                eprint!("{}", message);
+                failed = true;
             }
-            Ok(_) => {
+        }
+    }
+    if args.stats && inputs.len() > 1 {
+        eprintln!("total: {}", total);
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Bytes read and written for one input.
+#[derive(Debug, Clone, Copy, Default)]
+struct Sizes {
+    input: u64,
+    output: u64,
+}
+
+impl fmt::Display for Sizes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {} bytes", self.input, self.output)?;
+        if self.input > 0 {
+            let change = (self.output as f64 - self.input as f64) / self.input as f64 * 100.0;
+            write!(f, " ({:+.1}%)", change)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Counts the bytes that pass through a reader or writer.
+struct Counted<T> {
+    inner: T,
+    bytes: u64,
+}
+
+impl<T> Counted<T> {
+    fn new(inner: T) -> Counted<T> {
+        Counted { inner, bytes: 0 }
+    }
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        return Ok(n);
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n as u64;
+        return Ok(n);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return self.inner.flush();
+    }
+}
+
+fn display_name(input: &str) -> &str {
+    if input == "-" {
+        return "<stdin>";
+    }
+    return input;
+}
+
+/// Formats one input to wherever the options send it. On failure returns
+/// the message to print.
+fn format_input(input: &str, args: &Args) -> Result<Sizes, String> {
+    let name = display_name(input);
+    let target = if args.write {
+        Some(input)
+    } else {
+        args.output.as_deref()
+    };
+    match target {
+        Some(path) => {
+            let file = AtomicFile::create(Path::new(path)).map_err(|err| io_message(path, err))?;
+            let mut writer = BufWriter::new(file);
+            let sizes = format_to(input, name, path, args, &mut writer)?;
+            let file = writer
+                .into_inner()
+                .map_err(|err| io_message(path, err.into_error()))?;
+            file.commit().map_err(|err| io_message(path, err))?;
+            return Ok(sizes);
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            let sizes = format_to(input, name, "<stdout>", args, &mut writer)?;
+            writer.flush().map_err(|err| io_message("<stdout>", err))?;
+            return Ok(sizes);
+        }
+    }
+}
+
+/// Reads `input` and writes it formatted to `writer`. `name` and `dest`
+/// label the input and the output in messages.
+fn format_to<W: Write>(
+    input: &str,
+    name: &str,
+    dest: &str,
+    args: &Args,
+    writer: &mut W,
+) -> Result<Sizes, String> {
+    let reader = open(input).map_err(|err| io_message(name, err))?;
+    match args.documents {
+        Documents::Lines | Documents::LinesToArray | Documents::Sequence => {
+            let mut reader = Counted::new(reader);
+            let mut writer = Counted::new(writer);
+            let buffered = BufReader::new(&mut reader);
+            if args.documents == Documents::Sequence {
+                let mut texts = SequenceReader::with_options(buffered, args.parse.clone());
+                format_documents(&mut texts, name, dest, args, &mut writer)?;
+            } else {
+                let mut lines = LineReader::with_options(buffered, args.parse.clone());
+                format_documents(&mut lines, name, dest, args, &mut writer)?;
+            }
+            return Ok(Sizes {
+                input: reader.bytes,
+                output: writer.bytes,
+            });
+        }
+        Documents::Concatenated => {
+            let buf = read_all(reader, args.parse.limits.max_bytes)
+                .map_err(|err| io_message(name, err))?;
+            let mut writer = Counted::new(writer);
+            let mut values = parser::parse_many_with(&buf, &args.parse);
+            format_documents(&mut values, name, dest, args, &mut writer)?;
+            return Ok(Sizes {
+                input: buf.len() as u64,
+                output: writer.bytes,
+            });
+        }
+        Documents::Single | Documents::ArrayToLines => {}
+    }
+    if args.stream {
+        let mut reader = Counted::new(reader);
+        let mut writer = Counted::new(writer);
+        let result = stream::format_stream(&mut reader, &mut writer, &args.format, &args.parse);
+        return match result {
+            Ok(()) => Ok(Sizes {
+                input: reader.bytes,
+                output: writer.bytes,
+            }),
+            Err(StreamError::Syntax(err)) => Err(err.to_diagnostic().render_short(name)),
+            Err(StreamError::Io(err)) => Err(io_message(dest, err)),
+        };
+    }
+
+    let buf = read_all(reader, args.parse.limits.max_bytes).map_err(|err| io_message(name, err))?;
+    let output = format_source(&buf, name, args)?;
+    writer
+        .write_all(output.as_bytes())
+        .map_err(|err| io_message(dest, err))?;
+    return Ok(Sizes {
+        input: buf.len() as u64,
+        output: output.len() as u64,
+    });
+}
+
+/// Parses `buf` and returns it formatted, or the diagnostics to print.
+fn format_source(buf: &[u8], name: &str, args: &Args) -> Result<String, String> {
+    if args.documents == Documents::ArrayToLines {
+        return array_to_lines(buf, name, args);
+    }
+    let document = parse_source(buf, name, args)?;
+    let output = print_document(&document, name, args);
+    // 深い入れ子でも再帰せずに捨てる
+    document.value.dispose();
+    return output;
+}
+
+/// Parses `buf` as the options ask, printing the fixes made by `--repair`.
+fn parse_source(buf: &[u8], name: &str, args: &Args) -> Result<Document, String> {
+    if args.repair {
+        let source = String::from_utf8_lossy(buf);
+        // 上限で読むのをやめた入力を直してしまわないようにする
+        tokenizer::check_size(buf, &args.parse)
+            .map_err(|err| Error::Tokenize(err).to_diagnostic().render(&source, name))?;
+        let repaired = repair::repair_with(buf, args.parse.limits.max_depth)
+            .map_err(|err| err.to_diagnostic().render(&source, name))?;
+        for fix in repaired.fixes.iter() {
+            eprint!("{}", fix.render(&source, name));
+        }
+        return Ok(Document {
+            value: repaired.value,
+            comments: Comments::default(),
+        });
+    }
+    let mut document = parser::parse_document_with(buf, &args.parse).map_err(|err| {
+        // 一度に直せるように、見つかった誤りをすべて報告する
+        let mut errors = recover::parse_bytes_recovering(buf, &args.parse).errors;
+        if errors.is_empty() {
+            errors.push(err);
+        }
+        let source = String::from_utf8_lossy(buf);
+        return errors
+            .iter()
+            .map(|err| err.to_diagnostic().render(&source, name))
+            .collect::<String>();
+    })?;
+    let warn_duplicate_keys = args.parse.duplicate_keys == DuplicateKeys::Warn;
+    if args.warn_lossy_numbers || warn_duplicate_keys {
+        // 構文は確かめたので字句の誤りはない
+        let token_list = tokenizer::tokenize_bytes_with(buf, &args.parse).unwrap_or_default();
+        let mut warnings = Vec::new();
+        if warn_duplicate_keys {
+            warnings.extend(parser::duplicate_keys(&token_list, &args.parse));
+        }
+        if args.warn_lossy_numbers {
+            warnings.extend(number::lossy_numbers(&token_list));
+        }
+        warnings.sort_by_key(|warning| warning.span.start.offset);
+        let source = String::from_utf8_lossy(buf);
+        for warning in warnings {
+            eprint!("{}", warning.render(&source, name));
+        }
+    }
+    document.value = to_output_value(document.value, args)
+        .map_err(|err| format!("error: {}: {}\n", name, err))?;
+    return Ok(document);
+}
+
+/// Rewrites JSON5 numbers as JSON unless the output keeps JSON5 style.
+fn to_output_value(value: Value, args: &Args) -> Result<Value, Json5Error> {
+    if args.parse.json5 && !args.format.json5 {
+        return json5::to_json(value);
+    }
+    return Ok(value);
+}
+
+/// Writes a parsed document in the form the options ask for.
+fn print_document(document: &Document, name: &str, args: &Args) -> Result<String, String> {
+    if args.canonical {
+        return canonical::to_canonical(&document.value)
+            .map_err(|err| format!("error: {}: {}\n", name, err));
+    }
+    return Ok(format::format_document(document, &args.format));
+}
+
+/// Writes one document of an input that holds several, or one element
+/// for `--array-to-lines`.
+fn print_each(value: &Value, args: &Args) -> Result<String, CanonicalError> {
+    if args.canonical {
+        let mut text = canonical::to_canonical(value)?;
+        text.push_str(args.format.newline.as_str());
+        if args.documents == Documents::Sequence {
+            text.insert(0, sequence::RS as char);
+        }
+        return Ok(text);
+    }
+    match args.documents {
+        Documents::Sequence => return Ok(sequence::format_text(value, &args.format)),
+        Documents::Concatenated => {
+            // 次の文書と区切るために必ず改行で終える
+            let options = FormatOptions {
+                trailing_newline: true,
+                ..args.format.clone()
+            };
+            return Ok(format::format_value(value, &options));
+        }
+        _ => return Ok(lines::format_line(value, &args.format)),
+    }
+}
+
+/// Writes each element of the top-level array in `buf` on its own line.
+fn array_to_lines(buf: &[u8], name: &str, args: &Args) -> Result<String, String> {
+    let document = parse_source(buf, name, args)?;
+    let elements = match &document.value {
+        Value::Array(elements) => elements,
+        _ => {
+            document.value.dispose();
+            return Err(format!(
+                "error: {}: '--array-to-lines' needs an array at the top level\n",
+                name
+            ));
+        }
+    };
+    let output = elements
+        .iter()
+        .map(|element| print_each(element, args))
+        .collect::<Result<String, CanonicalError>>()
+        .map_err(|err| format!("error: {}: {}\n", name, err));
+    document.value.dispose();
+    return output;
+}
+
+/// The documents of an input that holds several, read one at a time.
+trait DocumentReader {
+    fn next_value(&mut self) -> Result<Option<Value>, StreamError>;
+    /// The line the document last read began on.
+    fn line(&self) -> usize;
+}
+
+impl<R: BufRead> DocumentReader for LineReader<R> {
+    fn next_value(&mut self) -> Result<Option<Value>, StreamError> {
+        return LineReader::next_value(self);
+    }
+
+    fn line(&self) -> usize {
+        return LineReader::line(self);
+    }
+}
+
+impl<R: BufRead> DocumentReader for SequenceReader<R> {
+    fn next_value(&mut self) -> Result<Option<Value>, StreamError> {
+        return SequenceReader::next_value(self);
+    }
+
+    fn line(&self) -> usize {
+        return SequenceReader::line(self);
+    }
+}
+
+impl DocumentReader for Values<'_> {
+    fn next_value(&mut self) -> Result<Option<Value>, StreamError> {
+        return self.next().transpose().map_err(StreamError::Syntax);
+    }
+
+    fn line(&self) -> usize {
+        return Values::line(self);
+    }
+}
+
+/// Formats the documents of an input that holds several one at a time.
+/// Those that do not parse are reported as they are met and the rest are
+/// still written.
+fn format_documents<W: Write>(
+    documents: &mut dyn DocumentReader,
+    name: &str,
+    dest: &str,
+    args: &Args,
+    writer: &mut W,
+) -> Result<(), String> {
+    let mut elements = Vec::new();
+    let mut failed = 0;
+    loop {
+        let value = match documents.next_value() {
+            Ok(Some(value)) => value,
+            Ok(None) => break,
+            Err(StreamError::Syntax(err)) => {
+                eprint!("{}", err.to_diagnostic().render_short(name));
+                failed += 1;
                 continue;
             }
-            Err(_) => {
-                break;
+            Err(StreamError::Io(err)) => {
+                elements.into_iter().for_each(Value::dispose);
+                return Err(io_message(name, err));
+            }
+        };
+        let result = to_output_value(value, args)
+            .map_err(|err| err.to_string())
+            .and_then(|value| {
+                if args.documents == Documents::LinesToArray {
+                    elements.push(value);
+                    return Ok(None);
+                }
+                let text = print_each(&value, args);
+                value.dispose();
+                return text.map(Some).map_err(|err| err.to_string());
+            });
+        match result {
+            Ok(Some(text)) => {
+                // 後ろに続く処理がすぐ読めるように一つずつ書き出す
+                writer
+                    .write_all(text.as_bytes())
+                    .and_then(|()| writer.flush())
+                    .map_err(|err| io_message(dest, err))?;
+            }
+            Ok(None) => {}
+            Err(message) => {
+                eprintln!("error: {}:{}: {}", name, documents.line(), message);
+                failed += 1;
+            }
+        }
+    }
+    if args.documents == Documents::LinesToArray {
+        let document = Document {
+            value: Value::Array(elements),
+            comments: Comments::default(),
+        };
+        let output = print_document(&document, name, args);
+        document.value.dispose();
+        let output = output?;
+        writer
+            .write_all(output.as_bytes())
+            .map_err(|err| io_message(dest, err))?;
+    }
+    if failed > 0 {
+        let noun = match args.documents {
+            Documents::Lines | Documents::LinesToArray => "line",
+            _ => "document",
+        };
+        return Err(format!(
+            "error: {}: {} could not be formatted\n",
+            name,
+            plural(failed, noun)
+        ));
+    }
+    return Ok(());
+}
+
+/// Prints a diff for every input that formatting would change, then a
+/// summary, and exits with 1 if any input changes or cannot be read.
+fn check(args: &Args) {
+    let inputs = args.inputs();
+    let mut changed = 0;
+    let mut failed = 0;
+    for input in inputs.iter() {
+        let name = display_name(input);
+        let result = open(input)
+            .and_then(|reader| read_all(reader, args.parse.limits.max_bytes))
+            .map_err(|err| io_message(name, err))
+            .and_then(|buf| {
+                let output = format_source(&buf, name, args)?;
+                // 構文が正しければ UTF-8 として読めている
+                let source = String::from_utf8_lossy(&buf);
+                return Ok(diff::unified_diff(
+                    &source,
+                    &output,
+                    name,
+                    &format!("{} (formatted)", name),
+                ));
+            });
+        match result {
+            Ok(patch) if patch.is_empty() => {}
+            Ok(patch) => {
+                print!("{}", patch);
+                changed += 1;
+            }
+            Err(message) => {
+                eprint!("{}", message);
+                failed += 1;
             }
         }
     }
 
-    let vec_token = tokenizer::tokenize(buf.chars().collect()).unwrap();
-    let result = parser::parse(vec_token).unwrap();
-    println!("{}", result.format_node(&("  ".to_string()), &mut 0));
+    let mut summary = format!("checked {}", plural(inputs.len(), "file"));
+    if changed == 0 && failed == 0 {
+        summary.push_str(", all formatted");
+    }
+    if changed > 0 {
+        summary.push_str(&format!(", {} would be reformatted", changed));
+    }
+    if failed > 0 {
+        summary.push_str(&format!(", {} could not be checked", failed));
+    }
+    eprintln!("{}", summary);
+    if changed > 0 || failed > 0 {
+        process::exit(1);
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        return format!("{} {}", count, noun);
+    }
+    return format!("{} {}s", count, noun);
+}
+
+/// Reads all of `reader`, but stops one byte past `max_bytes` so that input
+/// over the limit is not held in memory. Parsing then reports it.
+fn read_all(reader: Box<dyn Read>, max_bytes: Option<usize>) -> io::Result<Vec<u8>> {
+    let limit = max_bytes.map_or(u64::MAX, |max| max as u64 + 1);
+    let mut buf = Vec::new();
+    reader.take(limit).read_to_end(&mut buf)?;
+    return Ok(buf);
+}
+
+/// Opens a file, or stdin for `-`.
+fn open(input: &str) -> io::Result<Box<dyn Read>> {
+    if input == "-" {
+        return Ok(Box::new(io::stdin()));
+    }
+    return Ok(Box::new(File::open(input)?));
+}
+
+fn io_message(name: &str, err: io::Error) -> String {
+    return format!("error: {}: {}\n", name, err);
 }
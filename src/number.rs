@@ -1,31 +1,69 @@
+use std::fmt;
+
+use super::diagnostic::Diagnostic;
+use super::json5;
+use super::options::{ExponentCase, NumberFormat};
+use super::tokenizer::{Token, TokenKind};
+
+/// States of the RFC 8259 number grammar:
+///
+/// ```text
+/// number = [ minus ] int [ frac ] [ exp ]
+/// ```
+///
+/// JSON5 adds a `+` sign, hexadecimal integers and a decimal point with no
+/// digits before or after it.
 #[derive(Debug, PartialEq)]
 pub enum NState {
     None,
     Minus,
-    Plus,
+    Zero,
     DigitOneToNine,
-    Digit,
     Dot,
-    Zero,
+    /// `.` with no integer part, which needs a digit after it.
+    LeadingDot,
+    Fraction,
     Exponential,
+    Sign,
+    ExponentDigit,
+    HexPrefix,
+    HexDigit,
 }
 
 pub struct NumberTokenizer {
-    buf: String,
     state: NState,
+    json5: bool,
+}
+
+impl Default for NumberTokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NumberTokenizer {
     pub fn new() -> NumberTokenizer {
         NumberTokenizer {
-            buf: String::new(),
             state: NState::None,
+            json5: false,
+        }
+    }
+
+    /// A tokenizer for JSON5 numbers. `Infinity` and `NaN` are words rather
+    /// than digits and are left to the caller.
+    pub fn json5() -> NumberTokenizer {
+        NumberTokenizer {
+            state: NState::None,
+            json5: true,
         }
     }
 
-    pub fn tokenize(&mut self, str_vec: &Vec<char>, count: &mut usize) -> String {
-        while *count < str_vec.len() {
-            let ch = str_vec[*count];
+    /// Consumes the longest number prefix starting at `count` and returns it
+    /// as a slice of the input.
+    pub fn tokenize<'a>(&mut self, bytes: &'a [u8], count: &mut usize) -> &'a str {
+        let start = *count;
+        while *count < bytes.len() {
+            let ch = bytes[*count];
             if self.is_minus(ch, count)
                 || self.is_zero(ch, count)
                 || self.is_digit_one_to_nine(ch, count)
@@ -33,163 +71,799 @@ impl NumberTokenizer {
                 || self.is_dot(ch, count)
                 || self.is_exponential(ch, count)
                 || self.is_plus(ch, count)
+                || self.is_hex(ch, count)
             {
                 continue;
             }
             break;
         }
-        return self.buf.clone();
+        // 数値に使う文字はすべて ASCII なので分割しても UTF-8 として正しい
+        return std::str::from_utf8(&bytes[start..*count]).unwrap();
     }
 
-    fn is_minus(&mut self, ch: char, count: &mut usize) -> bool {
-        if ch == '-' && (self.state == NState::None || self.state == NState::Exponential) {
-            self.buf.push(ch);
-            *count += 1;
-            self.state = NState::Minus;
-            return true;
+    /// Whether the characters consumed so far form a whole number. `-`, `1.`
+    /// and `1e+` stop in the middle of the grammar and are not complete.
+    /// JSON5 accepts `1.`.
+    pub fn is_complete(&self) -> bool {
+        match self.state {
+            NState::Zero
+            | NState::DigitOneToNine
+            | NState::Fraction
+            | NState::ExponentDigit
+            | NState::HexDigit => return true,
+            NState::Dot => return self.json5,
+            _ => return false,
         }
+    }
 
-        return false;
+    fn accept(&mut self, count: &mut usize, state: NState) -> bool {
+        *count += 1;
+        self.state = state;
+        return true;
+    }
+
+    fn is_minus(&mut self, ch: u8, count: &mut usize) -> bool {
+        match (ch, &self.state) {
+            (b'-', NState::None) => self.accept(count, NState::Minus),
+            (b'-', NState::Exponential) => self.accept(count, NState::Sign),
+            _ => false,
+        }
     }
 
-    fn is_plus(&mut self, ch: char, count: &mut usize) -> bool {
-        if ch == '+' || self.state == NState::Exponential {
-            self.buf.push(ch);
-            *count += 1;
-            self.state = NState::Minus;
-            return true;
+    fn is_plus(&mut self, ch: u8, count: &mut usize) -> bool {
+        match (ch, &self.state) {
+            (b'+', NState::None) if self.json5 => self.accept(count, NState::Minus),
+            (b'+', NState::Exponential) => self.accept(count, NState::Sign),
+            _ => false,
         }
+    }
+
+    fn is_hex(&mut self, ch: u8, count: &mut usize) -> bool {
+        match (ch, &self.state) {
+            (b'x' | b'X', NState::Zero) if self.json5 => self.accept(count, NState::HexPrefix),
+            (ch, NState::HexPrefix | NState::HexDigit) if ch.is_ascii_hexdigit() => {
+                self.accept(count, NState::HexDigit)
+            }
+            _ => false,
+        }
+    }
 
+    fn is_digit_one_to_nine(&mut self, ch: u8, count: &mut usize) -> bool {
+        if (b'1'..=b'9').contains(&ch)
+            && (self.state == NState::None || self.state == NState::Minus)
+        {
+            return self.accept(count, NState::DigitOneToNine);
+        }
         return false;
     }
 
-    fn is_digit_one_to_nine(&mut self, ch: char, count: &mut usize) -> bool {
-        match ch {
-            '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                if self.state == NState::None || self.state == NState::Minus {
-                    self.buf.push(ch);
-                    *count += 1;
-                    self.state = NState::DigitOneToNine;
-                    return true;
-                }
-                return false;
+    fn is_digit(&mut self, ch: u8, count: &mut usize) -> bool {
+        if !ch.is_ascii_digit() {
+            return false;
+        }
+        match self.state {
+            NState::DigitOneToNine => self.accept(count, NState::DigitOneToNine),
+            NState::Dot | NState::LeadingDot | NState::Fraction => {
+                self.accept(count, NState::Fraction)
             }
-            _ => {
-                return false;
+            NState::Exponential | NState::Sign | NState::ExponentDigit => {
+                self.accept(count, NState::ExponentDigit)
             }
+            _ => false,
         }
     }
 
-    fn is_digit(&mut self, ch: char, count: &mut usize) -> bool {
-        if ch.is_digit(10) {
-            match self.state {
-                NState::DigitOneToNine
-                | NState::Digit
-                | NState::Dot
-                | NState::Minus
-                | NState::Plus
-                | NState::Exponential => {
-                    self.buf.push(ch);
-                    *count += 1;
-                    self.state = NState::Digit;
-                    return true;
-                }
-                _ => {
-                    return false;
-                }
-            }
+    fn is_zero(&mut self, ch: u8, count: &mut usize) -> bool {
+        if ch == b'0' && (self.state == NState::Minus || self.state == NState::None) {
+            return self.accept(count, NState::Zero);
         }
         return false;
     }
 
-    fn is_zero(&mut self, ch: char, count: &mut usize) -> bool {
-        if ch == '0' && (self.state == NState::Minus || self.state == NState::None) {
-            self.buf.push(ch);
-            *count += 1;
-            self.state = NState::Zero;
-            return true;
+    fn is_dot(&mut self, ch: u8, count: &mut usize) -> bool {
+        match (ch, &self.state) {
+            (b'.', NState::DigitOneToNine | NState::Zero) => self.accept(count, NState::Dot),
+            (b'.', NState::None | NState::Minus) if self.json5 => {
+                self.accept(count, NState::LeadingDot)
+            }
+            _ => false,
         }
-        return false;
     }
 
-    fn is_dot(&mut self, ch: char, count: &mut usize) -> bool {
-        if ch == '.' {
+    fn is_exponential(&mut self, ch: u8, count: &mut usize) -> bool {
+        if ch == b'E' || ch == b'e' {
             match self.state {
-                NState::DigitOneToNine | NState::Digit | NState::Zero => {
-                    self.buf.push(ch);
-                    *count += 1;
-                    self.state = NState::Dot;
-                    return true;
-                }
-                _ => {
-                    return false;
+                NState::DigitOneToNine | NState::Zero | NState::Fraction => {
+                    return self.accept(count, NState::Exponential);
                 }
+                NState::Dot if self.json5 => return self.accept(count, NState::Exponential),
+                _ => return false,
             }
         }
         return false;
     }
+}
 
-    fn is_exponential(&mut self, ch: char, count: &mut usize) -> bool {
-        if ch == 'E' || ch == 'e' {
-            match self.state {
-                NState::DigitOneToNine | NState::Digit | NState::Zero => {
-                    self.buf.push(ch);
-                    *count += 1;
-                    self.state = NState::Exponential;
-                    return true;
+/// A JSON number. It keeps the text it was written as, so that it prints
+/// back unchanged, and converts to a Rust number only on request and only
+/// when the conversion is exact.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Number {
+    lexeme: String,
+}
+
+impl Number {
+    /// Wraps a lexeme the tokenizer has already checked, JSON or JSON5.
+    pub(crate) fn from_lexeme(lexeme: String) -> Number {
+        Number { lexeme }
+    }
+
+    /// Reads a JSON number, or returns `None` if `text` is not one.
+    pub fn parse(text: &str) -> Option<Number> {
+        let mut tokenizer = NumberTokenizer::new();
+        let mut count = 0;
+        tokenizer.tokenize(text.as_bytes(), &mut count);
+        if count < text.len() || !tokenizer.is_complete() {
+            return None;
+        }
+        return Some(Number::from_lexeme(text.to_string()));
+    }
+
+    /// The shortest number that reads back as `number`, or `None` for
+    /// infinities and NaN, which JSON cannot write.
+    pub fn from_f64(number: f64) -> Option<Number> {
+        if !number.is_finite() {
+            return None;
+        }
+        let decimal = Decimal::parse(&format!("{:e}", number))?;
+        return Some(Number::from_lexeme(decimal.to_string()));
+    }
+
+    /// The number as it was written.
+    pub fn as_str(&self) -> &str {
+        return &self.lexeme;
+    }
+
+    /// The exact value, or `None` for JSON5's `Infinity` and `NaN` and for
+    /// exponents that do not fit in an `i64`.
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        return Decimal::parse(&json5::number_to_json(&self.lexeme)?);
+    }
+
+    /// The number in the normal form of `Decimal`, e.g. `1.50e1` as `15`.
+    /// `Infinity`, `NaN` and numbers whose exponent does not fit in an `i64`
+    /// keep their value as written; only a JSON5 spelling is made JSON.
+    pub fn normalized(&self) -> String {
+        if let Some(decimal) = self.to_decimal() {
+            return decimal.to_string();
+        }
+        match json5::number_to_json(&self.lexeme) {
+            Some(lexeme) => return lexeme,
+            None => return self.lexeme.clone(),
+        }
+    }
+
+    /// Writes the number with the rewrites `format` asks for.
+    pub fn format(&self, format: &NumberFormat) -> String {
+        let case = |text: String| match format.exponent_case {
+            Some(ExponentCase::Lower) => text.replace('E', "e"),
+            Some(ExponentCase::Upper) => text.replace('e', "E"),
+            None => text,
+        };
+        if format.normalize {
+            return case(self.normalized());
+        }
+        let Some(parts) = Parts::split(&self.lexeme) else {
+            // JSON5 の 16 進数などはそのまま
+            return self.lexeme.clone();
+        };
+        if let (Some(width), Some(_)) = (format.plain_width, parts.exponent) {
+            if let Some(decimal) = self.to_decimal() {
+                if decimal.plain_len() <= width as u64 {
+                    return decimal.to_plain();
                 }
-                _ => {
-                    return false;
+            }
+        }
+        let mut text = format!("{}{}", parts.sign, parts.int);
+        if let Some(mut fraction) = parts.fraction {
+            if format.trim_fraction_zeros {
+                fraction = fraction.trim_end_matches('0');
+            }
+            if !fraction.is_empty() {
+                text.push('.');
+                text.push_str(fraction);
+            }
+        }
+        if let Some((marker, exponent)) = parts.exponent {
+            text.push(marker);
+            let exponent = match exponent.strip_prefix('+') {
+                Some(digits) if format.strip_exponent_plus => digits,
+                _ => exponent,
+            };
+            text.push_str(exponent);
+        }
+        return case(text);
+    }
+
+    /// The value as an `i128` when it is a whole number in range. `1e3` and
+    /// `10.0` count as whole numbers.
+    pub fn as_i128(&self) -> Option<i128> {
+        let decimal = self.to_decimal()?;
+        if decimal.is_zero() {
+            return Some(0);
+        }
+        // i128 は 39 桁まで
+        if decimal.exponent < 0 || decimal.digits.len() as i64 + decimal.exponent > 39 {
+            return None;
+        }
+        let sign = if decimal.negative { "-" } else { "" };
+        let zeros = "0".repeat(decimal.exponent as usize);
+        return format!("{}{}{}", sign, decimal.digits, zeros).parse().ok();
+    }
+
+    /// The value as an `i64` when it is a whole number in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        return self.as_i128()?.try_into().ok();
+    }
+
+    /// The value as a `u64` when it is a whole number in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        return self.as_i128()?.try_into().ok();
+    }
+
+    /// The nearest double, or `None` when the number is too large for one.
+    /// Use `fits_f64` to tell whether digits are lost.
+    pub fn as_f64(&self) -> Option<f64> {
+        let lexeme = match json5::number_to_json(&self.lexeme) {
+            Some(lexeme) => lexeme,
+            None if self.lexeme.ends_with("NaN") => return Some(f64::NAN),
+            None if self.lexeme.starts_with('-') => return Some(f64::NEG_INFINITY),
+            None => return Some(f64::INFINITY),
+        };
+        return lexeme
+            .parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite());
+    }
+
+    /// Whether a double holds this number without losing digits, that is,
+    /// whether it reads back with the same value once converted to `f64`.
+    pub fn fits_f64(&self) -> bool {
+        match self.as_f64() {
+            Some(number) if number.is_finite() => {
+                return Decimal::parse(&format!("{:e}", number)) == self.to_decimal();
+            }
+            Some(_) => return true,
+            None => return false,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.lexeme)
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($t:ty)*) => {
+        $(
+            impl From<$t> for Number {
+                fn from(number: $t) -> Number {
+                    Number::from_lexeme(number.to_string())
                 }
             }
+        )*
+    };
+}
+
+impl_from_integer!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+/// Warnings for the numbers among `token_list` that a double cannot hold
+/// exactly, for documents read by programs that parse every number as one,
+/// as JavaScript's `JSON.parse` does.
+pub fn lossy_numbers(token_list: &[Token]) -> Vec<Diagnostic> {
+    return token_list
+        .iter()
+        .filter_map(|token| {
+            let TokenKind::Num(lexeme) = &token.kind else {
+                return None;
+            };
+            let number = Number::from_lexeme(lexeme.to_string());
+            if number.fits_f64() {
+                return None;
+            }
+            let message = match number.as_f64().and_then(Number::from_f64) {
+                Some(double) => format!(
+                    "{} loses precision as a double and reads back as {}",
+                    lexeme, double
+                ),
+                None => format!("{} is out of range of a double", lexeme),
+            };
+            return Some(Diagnostic::warning(token.span, message));
+        })
+        .collect();
+}
+
+/// The pieces of a number in JSON's grammar, as written.
+struct Parts<'a> {
+    sign: &'a str,
+    int: &'a str,
+    fraction: Option<&'a str>,
+    /// `e` or `E`, and the exponent with its sign.
+    exponent: Option<(char, &'a str)>,
+}
+
+impl<'a> Parts<'a> {
+    /// Splits `lexeme`, or returns `None` if it is not in JSON's grammar.
+    fn split(lexeme: &'a str) -> Option<Parts<'a>> {
+        Number::parse(lexeme)?;
+        let (sign, rest) = match lexeme.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", lexeme),
+        };
+        let (mantissa, exponent) = match rest.find(['e', 'E']) {
+            Some(i) => (
+                &rest[..i],
+                Some((rest.as_bytes()[i] as char, &rest[i + 1..])),
+            ),
+            None => (rest, None),
+        };
+        let (int, fraction) = match mantissa.split_once('.') {
+            Some((int, fraction)) => (int, Some(fraction)),
+            None => (mantissa, None),
+        };
+        return Some(Parts {
+            sign,
+            int,
+            fraction,
+            exponent,
+        });
+    }
+}
+
+/// A number as an exact decimal: `digits` × 10^`exponent`, with no leading
+/// or trailing zeros in `digits`, so that equal values compare equal
+/// however they were written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Reads a number in JSON's grammar, or returns `None` when the exponent
+    /// of its normal form does not fit in an `i64`.
+    fn parse(lexeme: &str) -> Option<Decimal> {
+        let (negative, rest) = match lexeme.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, lexeme),
+        };
+        let (mantissa, exponent) = match rest.find(['e', 'E']) {
+            Some(i) => (&rest[..i], Some(split_exponent(&rest[i + 1..])?)),
+            None => (rest, None),
+        };
+        let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let is_digits = |text: &str| text.bytes().all(|byte| byte.is_ascii_digit());
+        if int.is_empty() || !is_digits(int) || !is_digits(fraction) {
+            return None;
         }
-        return false;
+        let digits = format!("{}{}", int, fraction);
+        let digits = digits.trim_start_matches('0');
+        let significant = digits.trim_end_matches('0');
+        if significant.is_empty() {
+            // 0 はどんな指数でも 0
+            return Some(Decimal {
+                negative,
+                digits: String::new(),
+                exponent: 0,
+            });
+        }
+        let exponent = match exponent {
+            Some((negative, digits)) => parse_exponent(negative, digits)?,
+            None => 0,
+        };
+        let exponent = exponent
+            .checked_sub(fraction.len() as i64)?
+            .checked_add((digits.len() - significant.len()) as i64)?;
+        // 0.digits × 10^n の n - 1 まで書けるか
+        exponent
+            .checked_add(significant.len() as i64)?
+            .checked_sub(1)?;
+        return Some(Decimal {
+            negative,
+            digits: significant.to_string(),
+            exponent,
+        });
+    }
+
+    /// Whether the number has a minus sign. `-0` does.
+    pub fn is_negative(&self) -> bool {
+        return self.negative;
+    }
+
+    pub fn is_zero(&self) -> bool {
+        return self.digits.is_empty();
+    }
+
+    /// Whether the number has no fractional part.
+    pub fn is_integer(&self) -> bool {
+        return self.exponent >= 0;
+    }
+
+    /// The significant digits, without leading or trailing zeros. Empty for
+    /// zero.
+    pub fn digits(&self) -> &str {
+        return &self.digits;
+    }
+
+    /// The power of ten the digits are multiplied by.
+    pub fn exponent(&self) -> i64 {
+        return self.exponent;
+    }
+
+    /// The number in decimal notation with no exponent, e.g. `1500` or
+    /// `0.0015`. Check `plain_len` first when the exponent may be large.
+    pub fn to_plain(&self) -> String {
+        let sign = if self.negative { "-" } else { "" };
+        if self.is_zero() {
+            return format!("{}0", sign);
+        }
+        let n = self.exponent + self.digits.len() as i64;
+        if self.exponent >= 0 {
+            return format!(
+                "{}{}{}",
+                sign,
+                self.digits,
+                "0".repeat(self.exponent as usize)
+            );
+        }
+        if n > 0 {
+            let (int, fraction) = self.digits.split_at(n as usize);
+            return format!("{}{}.{}", sign, int, fraction);
+        }
+        return format!("{}0.{}{}", sign, "0".repeat((-n) as usize), self.digits);
+    }
+
+    /// The length of `to_plain`, without writing it out.
+    pub fn plain_len(&self) -> u64 {
+        let sign = self.negative as u64;
+        let k = self.digits.len() as u64;
+        if self.is_zero() {
+            return sign + 1;
+        }
+        let n = self.exponent + k as i64;
+        if self.exponent >= 0 {
+            return sign + k + self.exponent as u64;
+        }
+        if n > 0 {
+            return sign + k + 1;
+        }
+        return sign + 2 + (-n) as u64 + k;
     }
 }
 
+/// The normal form: plain decimal notation unless that would need more than
+/// 21 digits before the point or 6 zeros after it, and otherwise one digit
+/// before the point and a lowercase `e` with no `+`. This is how ECMAScript
+/// writes numbers, but with every digit kept.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // 値は 0.digits × 10^n
+        let n = self.exponent + self.digits.len() as i64;
+        if self.is_zero() || (-6 < n && n <= 21) {
+            return f.write_str(&self.to_plain());
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        if self.digits.len() == 1 {
+            return write!(f, "{}e{}", self.digits, n - 1);
+        }
+        return write!(f, "{}.{}e{}", &self.digits[..1], &self.digits[1..], n - 1);
+    }
+}
+
+/// Splits an exponent into its sign and digits, or returns `None` if it is
+/// not one.
+fn split_exponent(text: &str) -> Option<(bool, &str)> {
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    return Some((negative, digits));
+}
+
+/// The exponent's value, or `None` when it does not fit in an `i64`.
+fn parse_exponent(negative: bool, digits: &str) -> Option<i64> {
+    let mut exponent: i64 = 0;
+    for byte in digits.bytes() {
+        exponent = exponent
+            .checked_mul(10)?
+            .checked_add((byte - b'0') as i64)?;
+    }
+    return Some(if negative { -exponent } else { exponent });
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::tokenizer;
     #[test]
     fn test_number_tokenizer_tokenize() {
+        assert_eq!(NumberTokenizer::new().tokenize("0".as_bytes(), &mut 0), "0");
+
+        assert_eq!(
+            NumberTokenizer::new().tokenize("99999999".as_bytes(), &mut 0),
+            "99999999"
+        );
+
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"0".chars().collect::<Vec<char>>(), &mut 0),
-            "0".to_string()
+            NumberTokenizer::new().tokenize("-3".as_bytes(), &mut 0),
+            "-3"
         );
 
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"99999999".chars().collect::<Vec<char>>(), &mut 0),
-            "99999999".to_string()
+            NumberTokenizer::new().tokenize("0.0001".as_bytes(), &mut 0),
+            "0.0001"
         );
 
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"-3".chars().collect::<Vec<char>>(), &mut 0),
-            "-3".to_string()
+            NumberTokenizer::new().tokenize("-0.0001".as_bytes(), &mut 0),
+            "-0.0001"
         );
 
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"0.0001".chars().collect::<Vec<char>>(), &mut 0),
-            "0.0001".to_string()
+            NumberTokenizer::new().tokenize("-1.3e+5".as_bytes(), &mut 0),
+            "-1.3e+5"
         );
 
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"-0.0001".chars().collect::<Vec<char>>(), &mut 0),
-            "-0.0001".to_string()
+            NumberTokenizer::new().tokenize("-1.3e-5".as_bytes(), &mut 0),
+            "-1.3e-5"
         );
 
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"-1.3e+5".chars().collect::<Vec<char>>(), &mut 0),
-            "-1.3e+5".to_string()
+            NumberTokenizer::new().tokenize("-1.3e5".as_bytes(), &mut 0),
+            "-1.3e5"
         );
+    }
+
+    #[test]
+    fn test_number_tokenizer_stops() {
+        let tokenize = |s: &'static str| {
+            let mut tokenizer = NumberTokenizer::new();
+            let mut count = 0;
+            let buf = tokenizer.tokenize(s.as_bytes(), &mut count);
+            (buf, tokenizer.is_complete())
+        };
+        assert_eq!(tokenize("1+2"), ("1", true));
+        assert_eq!(tokenize("01"), ("0", true));
+        assert_eq!(tokenize("1e-0.5"), ("1e-0", true));
+        assert_eq!(tokenize("1.5e+10,"), ("1.5e+10", true));
+        assert_eq!(tokenize("-"), ("-", false));
+        assert_eq!(tokenize("1."), ("1.", false));
+        assert_eq!(tokenize("2.e3"), ("2.", false));
+        assert_eq!(tokenize("1e"), ("1e", false));
+        assert_eq!(tokenize("1E+"), ("1E+", false));
+        assert_eq!(tokenize("-.5"), ("-", false));
+        assert_eq!(tokenize("0x1F"), ("0", true));
+    }
+
+    #[test]
+    fn test_number_tokenizer_json5() {
+        let tokenize = |s: &'static str| {
+            let mut tokenizer = NumberTokenizer::json5();
+            let mut count = 0;
+            let buf = tokenizer.tokenize(s.as_bytes(), &mut count);
+            (buf, tokenizer.is_complete())
+        };
+        assert_eq!(tokenize("+1"), ("+1", true));
+        assert_eq!(tokenize(".5"), (".5", true));
+        assert_eq!(tokenize("-.5e2"), ("-.5e2", true));
+        assert_eq!(tokenize("5."), ("5.", true));
+        assert_eq!(tokenize("5.e3,"), ("5.e3", true));
+        assert_eq!(tokenize("0x1fG"), ("0x1f", true));
+        assert_eq!(tokenize("-0XAb"), ("-0XAb", true));
+        assert_eq!(tokenize("0x"), ("0x", false));
+        assert_eq!(tokenize("."), (".", false));
+        assert_eq!(tokenize("+"), ("+", false));
+        assert_eq!(tokenize("0x1.5"), ("0x1", true));
+    }
+
+    #[test]
+    fn test_number_parse() {
+        assert_eq!(Number::parse("-1.5e3").unwrap().as_str(), "-1.5e3");
+        assert_eq!(Number::parse("1."), None);
+        assert_eq!(Number::parse("01"), None);
+        assert_eq!(Number::parse("+1"), None);
+        assert_eq!(Number::parse(""), None);
+        assert_eq!(Number::from(-42i64).as_str(), "-42");
+        assert_eq!(Number::from(u64::MAX).as_str(), "18446744073709551615");
+        assert_eq!(Number::from_f64(0.1).unwrap().as_str(), "0.1");
+        assert_eq!(Number::from_f64(1.0).unwrap().as_str(), "1");
+        assert_eq!(Number::from_f64(-0.0).unwrap().as_str(), "-0");
+        assert_eq!(Number::from_f64(1e300).unwrap().as_str(), "1e300");
+        assert_eq!(Number::from_f64(f64::NAN), None);
+    }
 
+    #[test]
+    fn test_number_integers() {
+        let number = |text: &str| Number::from_lexeme(text.to_string());
+        assert_eq!(number("9007199254740993").as_i64(), Some(9007199254740993));
+        assert_eq!(number("-9223372036854775808").as_i64(), Some(i64::MIN));
+        assert_eq!(number("9223372036854775808").as_i64(), None);
+        assert_eq!(number("9223372036854775808").as_u64(), Some(1 << 63));
+        assert_eq!(number("-1").as_u64(), None);
+        assert_eq!(number("-0").as_u64(), Some(0));
+        assert_eq!(number("1e3").as_i64(), Some(1000));
+        assert_eq!(number("1.50e1").as_i64(), Some(15));
+        assert_eq!(number("10.0").as_i64(), Some(10));
+        assert_eq!(number("1.5").as_i64(), None);
+        assert_eq!(number("1e-400").as_i64(), None);
+        assert_eq!(number("0e999999999999999999999").as_i64(), Some(0));
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"-1.3e-5".chars().collect::<Vec<char>>(), &mut 0),
-            "-1.3e-5".to_string()
+            number("170141183460469231731687303715884105727").as_i128(),
+            Some(i128::MAX)
         );
+        assert_eq!(number("1e39").as_i128(), None);
+        assert_eq!(number("0x1F").as_i64(), Some(31));
+        assert_eq!(number("Infinity").as_i64(), None);
+    }
+
+    #[test]
+    fn test_number_floats() {
+        let number = |text: &str| Number::from_lexeme(text.to_string());
+        assert_eq!(number("1.5e3").as_f64(), Some(1500.0));
+        assert_eq!(number("1e400").as_f64(), None);
+        assert_eq!(number("-Infinity").as_f64(), Some(f64::NEG_INFINITY));
+        assert!(number("NaN").as_f64().unwrap().is_nan());
+        assert!(number("0.1").fits_f64());
+        assert!(number("9007199254740992").fits_f64());
+        assert!(!number("9007199254740993").fits_f64());
+        assert!(!number("0.10000000000000000001").fits_f64());
+        assert!(!number("1e400").fits_f64());
+        assert!(number("1e-400").as_f64() == Some(0.0));
+        assert!(!number("1e-400").fits_f64());
+    }
 
+    #[test]
+    fn test_decimal() {
+        let normalized = |text: &str| Number::from_lexeme(text.to_string()).normalized();
+        assert_eq!(normalized("1.0"), "1");
+        assert_eq!(normalized("1e0"), "1");
+        assert_eq!(normalized("10E-1"), "1");
+        assert_eq!(normalized("1.50"), "1.5");
+        assert_eq!(normalized("-0.0"), "-0");
+        assert_eq!(normalized("0e5"), "0");
+        assert_eq!(normalized("1E+2"), "100");
+        assert_eq!(normalized("0.000001"), "0.000001");
+        assert_eq!(normalized("1e-7"), "1e-7");
+        assert_eq!(normalized("123e18"), "123000000000000000000");
+        assert_eq!(normalized("123e19"), "1.23e21");
+        assert_eq!(
+            normalized("12345678901234567890123"),
+            "1.2345678901234567890123e22"
+        );
+        assert_eq!(normalized("0x10"), "16");
+        assert_eq!(normalized("NaN"), "NaN");
+        // 指数が i64 に収まらないものは値を変えない
+        assert_eq!(
+            normalized("1e99999999999999999999"),
+            "1e99999999999999999999"
+        );
+        assert_eq!(
+            normalized("-1.50E-99999999999999999999"),
+            "-1.50E-99999999999999999999"
+        );
+        assert_eq!(
+            normalized("+.5e99999999999999999999"),
+            "0.5e99999999999999999999"
+        );
+
+        let decimal = Number::from_lexeme("-12.3400e2".to_string())
+            .to_decimal()
+            .unwrap();
+        assert!(decimal.is_negative());
+        assert!(decimal.is_integer());
+        assert_eq!(decimal.digits(), "1234");
+        assert_eq!(decimal.exponent(), 0);
+        assert_eq!(
+            Number::from_lexeme("1.5".to_string()).to_decimal(),
+            Number::from_lexeme("15e-1".to_string()).to_decimal()
+        );
+
+        // i64 に収まらない指数は丸めずに None を返す
+        let decimal = |text: &str| Number::from_lexeme(text.to_string()).to_decimal();
+        assert_eq!(decimal("1e99999999999999999999"), None);
+        assert_eq!(decimal("-1e-99999999999999999999"), None);
+        assert_eq!(decimal("0.5e-9223372036854775808"), None);
+        assert_eq!(decimal("12e9223372036854775807"), None);
+        assert_eq!(
+            decimal("1e9223372036854775806").unwrap().exponent(),
+            9223372036854775806
+        );
+        assert_eq!(decimal("1e9223372036854775807"), None);
+        assert_eq!(
+            decimal("10e-9223372036854775807").unwrap().exponent(),
+            -9223372036854775806
+        );
+        assert!(decimal("0e99999999999999999999").unwrap().is_zero());
+    }
+
+    #[test]
+    fn test_number_format() {
+        let format = |text: &str, format: NumberFormat| {
+            return Number::from_lexeme(text.to_string()).format(&format);
+        };
+        let trim = NumberFormat {
+            trim_fraction_zeros: true,
+            ..NumberFormat::default()
+        };
+        assert_eq!(format("1.50", trim), "1.5");
+        assert_eq!(format("-2.000E+3", trim), "-2E+3");
+        assert_eq!(format("100", trim), "100");
+        let exponent = NumberFormat {
+            exponent_case: Some(ExponentCase::Lower),
+            strip_exponent_plus: true,
+            ..NumberFormat::default()
+        };
+        assert_eq!(format("1.50E+07", exponent), "1.50e07");
+        assert_eq!(format("1E-7", exponent), "1e-7");
+        let upper = NumberFormat {
+            exponent_case: Some(ExponentCase::Upper),
+            ..NumberFormat::default()
+        };
+        assert_eq!(format("1e+5", upper), "1E+5");
+        let plain = NumberFormat {
+            plain_width: Some(7),
+            ..NumberFormat::default()
+        };
+        assert_eq!(format("1.5e3", plain), "1500");
+        assert_eq!(format("-15E-4", plain), "-0.0015");
+        assert_eq!(format("1.5e6", plain), "1500000");
+        assert_eq!(format("1.5e7", plain), "1.5e7");
+        assert_eq!(
+            format("1e999999999999999999", plain),
+            "1e999999999999999999"
+        );
+        assert_eq!(
+            format("1e-99999999999999999999", plain),
+            "1e-99999999999999999999"
+        );
+        assert_eq!(format("1.50", plain), "1.50");
+        let normalized = NumberFormat {
+            exponent_case: Some(ExponentCase::Upper),
+            ..NumberFormat::normalized()
+        };
+        assert_eq!(format("10E-1", normalized), "1");
+        assert_eq!(format("1.0e30", normalized), "1E30");
+        // JSON5 の書き方は正規化するときだけ変える
+        assert_eq!(format("0x1F", trim), "0x1F");
+        assert_eq!(format("0x1F", NumberFormat::normalized()), "31");
+        assert_eq!(format("+1.50", trim), "+1.50");
+    }
+
+    #[test]
+    fn test_lossy_numbers() {
+        let token_list =
+            tokenizer::tokenize_bytes(b"[9007199254740993, 0.1, 1e400, 2, 1e-400]").unwrap();
+        let warnings = lossy_numbers(&token_list)
+            .iter()
+            .map(|warning| format!("{}: {}", warning.span.start.column, warning.message))
+            .collect::<Vec<String>>();
         assert_eq!(
-            NumberTokenizer::new().tokenize(&"-1.3e5".chars().collect::<Vec<char>>(), &mut 0),
-            "-1.3e5".to_string()
+            warnings,
+            vec![
+                "2: 9007199254740993 loses precision as a double and reads back as 9007199254740992",
+                "25: 1e400 is out of range of a double",
+                "35: 1e-400 loses precision as a double and reads back as 0",
+            ]
         );
     }
 }
@@ -0,0 +1,219 @@
+use super::color::Theme;
+
+/// Options shared by the tokenizer and the parser.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParseOptions {
+    /// Enforce the RFC 8259 grammar exactly. When off, a few harmless
+    /// deviations are tolerated: a leading byte order mark and Unicode
+    /// whitespace between tokens.
+    pub strict: bool,
+    /// Accept raw control characters inside strings and decode lone
+    /// surrogate escapes to U+FFFD, which changes the data. Ignored in
+    /// strict mode.
+    pub lossy_strings: bool,
+    /// Accept `//` and `/* */` comments between tokens, as JSONC does.
+    pub comments: bool,
+    /// Accept a comma after the last member or element, as in `[1, 2,]`.
+    pub trailing_commas: bool,
+    /// Accept JSON5: unquoted keys, single-quoted strings and their escapes,
+    /// hexadecimal numbers, `+`, `.5`, `5.`, `Infinity` and
+    /// `NaN`. Number tokens keep their JSON5 spelling. The stream formatter
+    /// does not support it.
+    pub json5: bool,
+    /// What to do with a key that appears twice in one object.
+    pub duplicate_keys: DuplicateKeys,
+    /// Bounds on the size of the input, for reading untrusted documents.
+    pub limits: Limits,
+}
+
+/// Bounds the tokenizer and parser enforce, each reported with its own
+/// error when exceeded. `None` means no bound, which is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Limits {
+    /// The most objects and arrays open at once; a scalar at the root is at
+    /// depth 0.
+    pub max_depth: Option<usize>,
+    /// The longest document, in bytes.
+    pub max_bytes: Option<usize>,
+    /// The longest string or key, in bytes after unescaping.
+    pub max_string_length: Option<usize>,
+    /// The longest number, in characters as written.
+    pub max_number_length: Option<usize>,
+    /// The most members in one object or elements in one array.
+    pub max_members: Option<usize>,
+}
+
+/// How the parser treats an object that uses a key more than once, which
+/// RFC 8259 leaves to each reader.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DuplicateKeys {
+    /// Keep every member as written.
+    #[default]
+    Allow,
+    /// Reject the document at the second use of the key.
+    Error,
+    /// Keep every member; `parser::duplicate_keys` reports each repeat.
+    Warn,
+    /// Keep the first member with the key and drop the rest.
+    First,
+    /// Keep the value of the last member, in the place of the first, as
+    /// JavaScript's `JSON.parse` does.
+    Last,
+}
+
+impl ParseOptions {
+    pub fn strict() -> ParseOptions {
+        ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// Lenient parsing with comments allowed.
+    pub fn jsonc() -> ParseOptions {
+        ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// JSON5, which includes comments and trailing commas.
+    pub fn json5() -> ParseOptions {
+        ParseOptions {
+            comments: true,
+            trailing_commas: true,
+            json5: true,
+            ..ParseOptions::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Newline {
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// How object keys are ordered when sorting.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum KeyOrder {
+    /// By Unicode code point.
+    #[default]
+    CodePoint,
+    /// Digit runs by numeric value, e.g. `a2` before `a10`.
+    Natural,
+    /// By UTF-16 code unit, as RFC 8785 and JavaScript's default sort do.
+    Utf16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SortKeys {
+    pub order: KeyOrder,
+    /// Sort nested objects too, not only the top-level one.
+    pub recursive: bool,
+}
+
+/// The letter that starts an exponent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExponentCase {
+    Lower,
+    Upper,
+}
+
+/// How numbers are written. By default they are kept as written; each field
+/// turns on one rewrite. JSON5 spellings such as `0x1F` are only changed by
+/// `normalize`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NumberFormat {
+    /// Write the normal form of `number::Decimal`, so that `1.0`, `1e0` and
+    /// `10E-1` all become `1`. A number whose exponent does not fit in an
+    /// `i64` is written as given. Only `exponent_case` still applies.
+    pub normalize: bool,
+    /// Drop zeros at the end of a fraction, and the point when none are
+    /// left: `1.50` becomes `1.5` and `2.0e3` `2e3`.
+    pub trim_fraction_zeros: bool,
+    /// Write `e` or `E` whatever the input used.
+    pub exponent_case: Option<ExponentCase>,
+    /// Drop the `+` of an exponent: `1e+5` becomes `1e5`.
+    pub strip_exponent_plus: bool,
+    /// Write a number with an exponent in plain decimal notation when that
+    /// takes at most this many characters: `1.5e3` becomes `1500`.
+    pub plain_width: Option<usize>,
+}
+
+impl NumberFormat {
+    /// Numbers as they were written.
+    pub fn preserve() -> NumberFormat {
+        return NumberFormat::default();
+    }
+
+    /// Numbers in the normal form of `number::Decimal`.
+    pub fn normalized() -> NumberFormat {
+        NumberFormat {
+            normalize: true,
+            ..NumberFormat::default()
+        }
+    }
+}
+
+/// Layout of formatted output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Written once per nesting level, e.g. two spaces or a tab.
+    pub indent: String,
+    pub newline: Newline,
+    pub space_before_colon: bool,
+    pub space_after_colon: bool,
+    /// End the output with a newline.
+    pub trailing_newline: bool,
+    /// Keep an array or object on one line when it takes at most this many
+    /// columns written that way.
+    pub inline_width: Option<usize>,
+    /// Keep an array or object on one line when the line it is on stays
+    /// within this many columns.
+    pub max_width: Option<usize>,
+    /// Write without any insignificant whitespace, as `Node::print_node`
+    /// does. Only `trailing_newline` and `newline` still apply.
+    pub compact: bool,
+    /// Color the output with ANSI escape codes.
+    pub color: Option<Theme>,
+    /// Write object members sorted by key instead of in input order.
+    /// Members with equal keys keep their input order.
+    pub sort_keys: Option<SortKeys>,
+    /// Write JSON5 style: keys that are identifiers go unquoted. Numbers are
+    /// written as they were parsed either way; see `json5::to_json`.
+    pub json5: bool,
+    /// Put a comma after the last member or element of a container that is
+    /// written over several lines, for JSONC and JSON5 readers.
+    pub trailing_commas: bool,
+    pub numbers: NumberFormat,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent: "  ".to_string(),
+            newline: Newline::Lf,
+            space_before_colon: false,
+            space_after_colon: true,
+            trailing_newline: true,
+            inline_width: None,
+            max_width: None,
+            compact: false,
+            color: None,
+            sort_keys: None,
+            json5: false,
+            trailing_commas: false,
+            numbers: NumberFormat::preserve(),
+        }
+    }
+}
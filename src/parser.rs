@@ -1,342 +1,752 @@
-use super::tokenizer::Token;
+use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug)]
-pub enum ParseError {
+use super::comment::{self, Comments};
+use super::diagnostic::Diagnostic;
+use super::error::Error;
+use super::escape;
+use super::format::Printer;
+use super::number::Number;
+use super::options::{DuplicateKeys, FormatOptions, ParseOptions};
+use super::tokenizer::{self, Span, Token, TokenKind, TokenizeError};
+use super::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseErrorKind {
     IsNotString,
     IsNotNumber,
     IsNotBool,
     IsNotNull,
     InvalidToken,
     UnexpectedToken,
+    UnexpectedEof,
+    /// The recovering parser's findings; see `recover`.
+    MissingColon,
+    MissingComma,
+    Unclosed,
+    StrayToken,
+    TrailingComma,
+    /// A key used twice in one object, under `DuplicateKeys::Error`.
+    DuplicateKey,
+    /// Input past one of the `Limits`.
+    TooDeep,
+    TooManyMembers,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+    /// What the parser was looking for, e.g. `',' or '}'`.
+    pub expected: String,
+    /// The token it got instead, or `end of input`.
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
 }
 
 pub trait Node {
     fn print_node(&self) -> String;
-    fn format_node(&self, indent: &String, depth: &mut usize) -> String;
+    fn format_node(&self, indent: &str, depth: &mut usize) -> String;
 }
 
-pub struct ObjectListNode {
-    value: Vec<Box<dyn Node>>,
+/// What `print_node` has left to write, last first.
+enum Step<'a> {
+    Value(&'a Value),
+    Key(&'a str),
+    Punct(char),
 }
 
-impl Node for ObjectListNode {
+impl Node for Value {
     fn print_node(&self) -> String {
+        // 入れ子を再帰せずに書くため、残りの仕事をスタックに積む
         let mut buf = String::new();
-        let mut count = 0;
-        while count < self.value.len() {
-            if count == 0 {
-                buf = self.value[count].print_node();
-            } else {
-                buf = format!("{},{}", buf, self.value[count].print_node());
+        let mut stack = vec![Step::Value(self)];
+        while let Some(step) = stack.pop() {
+            let value = match step {
+                Step::Value(value) => value,
+                Step::Key(key) => {
+                    buf.push_str(&escape::quote(key));
+                    buf.push(':');
+                    continue;
+                }
+                Step::Punct(punct) => {
+                    buf.push(punct);
+                    continue;
+                }
+            };
+            match value {
+                Value::Object(members) => {
+                    buf.push('{');
+                    stack.push(Step::Punct('}'));
+                    for (i, (key, value)) in members.iter().enumerate().rev() {
+                        stack.push(Step::Value(value));
+                        stack.push(Step::Key(key));
+                        if i > 0 {
+                            stack.push(Step::Punct(','));
+                        }
+                    }
+                }
+                Value::Array(elements) => {
+                    buf.push('[');
+                    stack.push(Step::Punct(']'));
+                    for (i, value) in elements.iter().enumerate().rev() {
+                        stack.push(Step::Value(value));
+                        if i > 0 {
+                            stack.push(Step::Punct(','));
+                        }
+                    }
+                }
+                Value::Str(string) => buf.push_str(&escape::quote(string)),
+                Value::Num(number) => buf.push_str(number.as_str()),
+                Value::Bool(b) => buf.push_str(if *b { "true" } else { "false" }),
+                Value::Null => buf.push_str("null"),
             }
-            count += 1;
         }
-        return format!("{{{}}}", buf);
+        return buf;
     }
 
-    fn format_node(&self, indent: &String, depth: &mut usize) -> String {
-        let mut buf = String::new();
-        let mut count = 0;
-        while count < self.value.len() {
-            if count == 0 {
-                buf = self.value[count].format_node(indent, depth);
-            } else {
-                buf = format!("{},\n{}", buf, self.value[count].format_node(indent, depth),);
-            }
-            count += 1;
-        }
-        return format!("{{\n{}\n{}}}", buf, indent.repeat(*depth));
+    fn format_node(&self, indent: &str, depth: &mut usize) -> String {
+        let options = FormatOptions {
+            indent: indent.to_string(),
+            trailing_newline: false,
+            ..FormatOptions::default()
+        };
+        let mut printer = Printer::new(&options);
+        printer.value(self, *depth);
+        return printer.buf;
     }
 }
 
-pub struct ObjectNode {
-    key: Box<dyn Node>,
-    value: Box<dyn Node>,
-}
-
-impl Node for ObjectNode {
-    fn print_node(&self) -> String {
-        return format!("{}:{}", self.key.print_node(), self.value.print_node());
-    }
-
-    fn format_node(&self, indent: &String, depth: &mut usize) -> String {
-        *depth += 1;
-        let result = format!(
-            "{}{}: {}",
-            indent.repeat(*depth),
-            self.key.format_node(indent, depth),
-            self.value.format_node(indent, depth)
-        );
-        *depth -= 1;
-        return result;
+fn expect_token(token_type: TokenKind, token_list: &[Token], index: &mut usize) -> bool {
+    match token_list.get(*index) {
+        Some(token) if token.kind == token_type => {
+            *index += 1;
+            true
+        }
+        _ => false,
     }
 }
 
-pub struct ArrayNode {
-    value: Vec<Box<dyn Node>>,
+fn is_token(token_type: &TokenKind, token_list: &[Token], index: usize) -> bool {
+    return token_list
+        .get(index)
+        .is_some_and(|token| token.kind == *token_type);
 }
 
-impl Node for ArrayNode {
-    fn print_node(&self) -> String {
-        let mut buf = String::new();
-        let mut count = 0;
-        while count < self.value.len() {
-            if count == 0 {
-                buf = self.value[count].print_node();
-            } else {
-                buf = format!("{},{}", buf, self.value[count].print_node());
+/// Builds an error describing the token at `index`, or the end of input when
+/// the token list has run out.
+pub(crate) fn error_at(
+    kind: ParseErrorKind,
+    token_list: &[Token],
+    index: usize,
+    expected: &str,
+) -> ParseError {
+    match token_list.get(index) {
+        Some(token) => ParseError {
+            kind,
+            span: token.span,
+            expected: expected.to_string(),
+            found: token.kind.to_string(),
+        },
+        None => {
+            let end = token_list
+                .last()
+                .map(|token| token.span.end)
+                .unwrap_or_default();
+            ParseError {
+                kind: ParseErrorKind::UnexpectedEof,
+                span: Span { start: end, end },
+                expected: expected.to_string(),
+                found: "end of input".to_string(),
             }
-            count += 1;
         }
-        return format!("[{}]", buf);
     }
+}
 
-    fn format_node(&self, indent: &String, depth: &mut usize) -> String {
-        let mut buf = String::new();
-        let mut count = 0;
-        *depth += 1;
-        while count < self.value.len() {
-            if count == 0 {
-                buf = format!(
-                    "{}{}",
-                    indent.repeat(*depth),
-                    self.value[count].format_node(indent, depth)
-                );
-            } else {
-                buf = format!(
-                    "{},\n{}{}",
-                    buf,
-                    indent.repeat(*depth),
-                    self.value[count].format_node(indent, depth)
-                );
-            }
-            count += 1;
-        }
-        *depth -= 1;
-
-        return format!("[\n{}\n{}]", buf, indent.repeat(*depth));
-    }
+/// Tokenizes and parses `source` in one step.
+pub fn parse_str(source: &str) -> Result<Value, Error> {
+    return parse_str_with(source, &ParseOptions::default());
 }
 
-pub struct StringNode {
-    value: String,
+pub fn parse_str_with(source: &str, options: &ParseOptions) -> Result<Value, Error> {
+    return parse_bytes_with(source.as_bytes(), options);
 }
 
-impl Node for StringNode {
-    fn print_node(&self) -> String {
-        return format!("{}", self.value);
-    }
+/// Tokenizes and parses UTF-8 input that has not been validated yet.
+pub fn parse_bytes(bytes: &[u8]) -> Result<Value, Error> {
+    return parse_bytes_with(bytes, &ParseOptions::default());
+}
 
-    fn format_node(&self, _: &String, _: &mut usize) -> String {
-        return format!("{}", self.value);
-    }
+pub fn parse_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<Value, Error> {
+    let token_list = tokenizer::tokenize_bytes_with(bytes, options)?;
+    return Ok(parse_with(token_list, options)?);
 }
 
-pub struct NumberNode {
-    value: String,
+/// A parsed value together with the comments around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub value: Value,
+    pub comments: Comments,
 }
 
-impl Node for NumberNode {
+impl Node for Document {
+    /// Compact output has nowhere to put `//` comments, so it drops them all.
     fn print_node(&self) -> String {
-        return format!("{}", self.value);
+        return self.value.print_node();
     }
 
-    fn format_node(&self, _: &String, _: &mut usize) -> String {
-        return format!("{}", self.value);
+    fn format_node(&self, indent: &str, depth: &mut usize) -> String {
+        let options = FormatOptions {
+            indent: indent.to_string(),
+            trailing_newline: false,
+            ..FormatOptions::default()
+        };
+        let mut printer = Printer::new(&options);
+        printer.document(self, *depth);
+        return printer.buf;
     }
 }
 
-pub struct BoolNode {
-    value: String,
+/// Parses like `parse_bytes_with` but keeps the comments, which are only
+/// found when `options.comments` is set.
+pub fn parse_document_with(bytes: &[u8], options: &ParseOptions) -> Result<Document, Error> {
+    let token_list = tokenizer::tokenize_bytes_with(bytes, options)?;
+    let has_comments = token_list
+        .iter()
+        .any(|token| matches!(token.kind, TokenKind::Comment(_)));
+    if !has_comments {
+        return Ok(Document {
+            value: parse_with(token_list, options)?,
+            comments: Comments::default(),
+        });
+    }
+    // 構文を確かめてからコメントの位置を決める
+    let value = parse_with(token_list.clone(), options)?;
+    return Ok(Document {
+        value,
+        comments: comment::collect(&token_list),
+    });
 }
 
-impl Node for BoolNode {
-    fn print_node(&self) -> String {
-        return format!("{}", self.value);
-    }
+/// Parses each of the root values in an input holding several documents,
+/// separated by whitespace or written one right after another, as in
+/// `{"a":1}{"a":2}`.
+pub fn parse_many(bytes: &[u8]) -> Values<'_> {
+    return parse_many_with(bytes, &ParseOptions::default());
+}
 
-    fn format_node(&self, _: &String, _: &mut usize) -> String {
-        return format!("{}", self.value);
-    }
+pub fn parse_many_with<'a>(bytes: &'a [u8], options: &ParseOptions) -> Values<'a> {
+    let (token_list, errors) = tokenizer::tokenize_bytes_recovering(bytes, options);
+    let token_list = token_list
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Comment(_)))
+        .collect();
+    return Values {
+        token_list,
+        index: 0,
+        options: options.clone(),
+        tokenize_error: errors.into_iter().next(),
+        line: 0,
+        done: false,
+    };
 }
 
-pub struct NullNode {
-    value: String,
+/// The values found by `parse_many`. Reading stops at the first error, as
+/// where the next document begins is then unknown.
+pub struct Values<'a> {
+    token_list: Vec<Token<'a>>,
+    index: usize,
+    options: ParseOptions,
+    /// 字句の誤りは、その位置まで読み進めたときに返す
+    tokenize_error: Option<TokenizeError>,
+    line: usize,
+    done: bool,
 }
 
-impl Node for NullNode {
-    fn print_node(&self) -> String {
-        return format!("{}", self.value);
-    }
-    fn format_node(&self, _: &String, _: &mut usize) -> String {
-        return format!("{}", self.value);
+impl Values<'_> {
+    /// The line the last value read began on.
+    pub fn line(&self) -> usize {
+        return self.line;
     }
 }
 
-fn expect_token(token_type: Token, token_list: &Vec<Token>, index: &mut usize) -> bool {
-    if token_type == token_list[*index] {
-        *index += 1;
-        true
-    } else {
-        false
+impl Iterator for Values<'_> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let error_offset = self
+            .tokenize_error
+            .as_ref()
+            .map_or(usize::MAX, |err| err.span.start.offset);
+        let start = match self.token_list.get(self.index) {
+            Some(token) if token.span.start.offset < error_offset => token.span.start,
+            _ => {
+                self.done = true;
+                return self.tokenize_error.take().map(|err| Err(err.into()));
+            }
+        };
+        self.line = start.line;
+        let result = parse_value(&self.token_list, &mut self.index, &self.options);
+        let end = match &result {
+            Ok(_) => self.token_list[self.index - 1].span.end.offset,
+            Err(err) => err.span.start.offset,
+        };
+        // 値の途中に字句の誤りがあれば、そちらを返す
+        if error_offset < end || result.is_err() {
+            self.done = true;
+            if let Some(err) = self.tokenize_error.take().filter(|_| error_offset < end) {
+                return Some(Err(err.into()));
+            }
+        }
+        return Some(result.map_err(Error::from));
     }
 }
 
-pub fn parse(token_list: Vec<Token>) -> Result<Box<dyn Node>, ParseError> {
-    return parse_value(&token_list, &mut 0);
+pub fn parse(token_list: Vec<Token>) -> Result<Value, ParseError> {
+    return parse_with(token_list, &ParseOptions::default());
+}
+
+pub fn parse_with(token_list: Vec<Token>, options: &ParseOptions) -> Result<Value, ParseError> {
+    // コメントは値に含めない
+    let token_list: Vec<Token> = token_list
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Comment(_)))
+        .collect();
+    let mut index = 0;
+    let value = parse_value(&token_list, &mut index, options)?;
+    // ルートの値の後に続くトークンは受け付けない
+    if index < token_list.len() {
+        value.dispose();
+        return Err(error_at(
+            ParseErrorKind::UnexpectedToken,
+            &token_list,
+            index,
+            "end of input",
+        ));
+    }
+    return Ok(value);
 }
 
 pub fn parse_objects(
-    token_list: &Vec<Token>,
+    token_list: &[Token],
     index: &mut usize,
-) -> Result<Box<dyn Node>, ParseError> {
-    let mut value: Vec<Box<dyn Node>> = Vec::new();
-    if !expect_token(Token::LeftBracket, token_list, index) {
-        return Err(ParseError::UnexpectedToken);
+    options: &ParseOptions,
+) -> Result<Value, ParseError> {
+    if !is_token(&TokenKind::LeftBracket, token_list, *index) {
+        return Err(error_at(
+            ParseErrorKind::UnexpectedToken,
+            token_list,
+            *index,
+            "'{'",
+        ));
     }
-    loop {
-        match parse_object(token_list, index) {
-            Ok(v) => value.push(v),
-            Err(err) => return Err(err),
-        }
+    return parse_value(token_list, index, options);
+}
 
-        if !expect_token(Token::Commma, token_list, index) {
-            break;
+fn duplicate_key_error(key: &str, first: Span, span: Span) -> ParseError {
+    return ParseError {
+        kind: ParseErrorKind::DuplicateKey,
+        span,
+        expected: "a key not already in the object".to_string(),
+        found: format!(
+            "{} again, first at {}:{}",
+            escape::quote(key),
+            first.start.line,
+            first.start.column
+        ),
+    };
+}
+
+/// Warnings for the keys among `token_list` that an object has already
+/// used, each giving where the key was first used. The tokens must parse
+/// with `options`.
+pub fn duplicate_keys(token_list: &[Token], options: &ParseOptions) -> Vec<Diagnostic> {
+    let token_list: Vec<&Token> = token_list
+        .iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Comment(_)))
+        .collect();
+    let mut warnings = Vec::new();
+    // 開いているオブジェクトごとに、使ったキーとその位置。配列は None
+    let mut stack: Vec<Option<HashMap<String, Span>>> = Vec::new();
+    for (i, token) in token_list.iter().enumerate() {
+        match token.kind {
+            TokenKind::LeftBracket => stack.push(Some(HashMap::new())),
+            TokenKind::LeftSquareBracket => stack.push(None),
+            TokenKind::RightBracket | TokenKind::RightSquareBracket => {
+                stack.pop();
+            }
+            _ => {
+                let Some(Some(keys)) = stack.last_mut() else {
+                    continue;
+                };
+                let is_key = token_list
+                    .get(i + 1)
+                    .is_some_and(|next| next.kind == TokenKind::Colorn);
+                if !is_key {
+                    continue;
+                }
+                let Ok(key) = parse_key(&[(*token).clone()], &mut 0, options) else {
+                    continue;
+                };
+                match keys.get(&key) {
+                    Some(first) => warnings.push(Diagnostic::warning(
+                        token.span,
+                        format!(
+                            "duplicate key {}, first at {}:{}",
+                            escape::quote(&key),
+                            first.start.line,
+                            first.start.column
+                        ),
+                    )),
+                    None => {
+                        keys.insert(key, token.span);
+                    }
+                }
+            }
         }
     }
-    if !expect_token(Token::RightBracket, token_list, index) {
-        return Err(ParseError::UnexpectedToken);
-    }
-
-    return Ok(Box::new(ObjectListNode { value: value }));
+    return warnings;
 }
 
 pub fn parse_object(
-    token_list: &Vec<Token>,
+    token_list: &[Token],
     index: &mut usize,
-) -> Result<Box<dyn Node>, ParseError> {
-    let key_resutl;
-    let value_result;
+    options: &ParseOptions,
+) -> Result<(String, Value), ParseError> {
+    let (key, _) = parse_member_key(token_list, index, options)?;
+    let value = parse_value(token_list, index, options)?;
 
-    match parse_string(token_list, index) {
-        Ok(key) => {
-            key_resutl = key;
-        }
-        Err(err) => return Err(err),
-    }
-    if !expect_token(Token::Colorn, token_list, index) {
-        return Err(ParseError::UnexpectedToken);
-    }
+    return Ok((key, value));
+}
 
-    match parse_value(token_list, index) {
-        Ok(value) => {
-            value_result = value;
-        }
-        Err(err) => return Err(err),
+/// Reads a key and the colon after it, and returns the key with its span.
+fn parse_member_key(
+    token_list: &[Token],
+    index: &mut usize,
+    options: &ParseOptions,
+) -> Result<(String, Span), ParseError> {
+    let key_index = *index;
+    let key = parse_key(token_list, index, options)?;
+    if !expect_token(TokenKind::Colorn, token_list, index) {
+        return Err(error_at(
+            ParseErrorKind::UnexpectedToken,
+            token_list,
+            *index,
+            "':'",
+        ));
     }
+    return Ok((key, token_list[key_index].span));
+}
 
-    return Ok(Box::new(ObjectNode {
-        key: key_resutl,
-        value: value_result,
-    }));
+/// Reads an object key: a string, or in JSON5 also an identifier.
+pub(crate) fn parse_key(
+    token_list: &[Token],
+    index: &mut usize,
+    options: &ParseOptions,
+) -> Result<String, ParseError> {
+    let key = match token_list.get(*index).map(|token| &token.kind) {
+        Some(TokenKind::Str(key)) => key.to_string(),
+        Some(TokenKind::Ident(name)) => name.to_string(),
+        // true などの予約語もキーには使える
+        Some(TokenKind::Bool(b)) if options.json5 => b.to_string(),
+        Some(TokenKind::Null) if options.json5 => "null".to_string(),
+        Some(TokenKind::Num(word))
+            if options.json5 && word.bytes().all(|byte| byte.is_ascii_alphabetic()) =>
+        {
+            word.to_string()
+        }
+        _ => {
+            let expected = if options.json5 {
+                "string or identifier"
+            } else {
+                "string"
+            };
+            return Err(error_at(
+                ParseErrorKind::IsNotString,
+                token_list,
+                *index,
+                expected,
+            ));
+        }
+    };
+    *index += 1;
+    return Ok(key);
 }
 
 pub fn parse_array(
-    token_list: &Vec<Token>,
+    token_list: &[Token],
     index: &mut usize,
-) -> Result<Box<dyn Node>, ParseError> {
-    let mut value: Vec<Box<dyn Node>> = Vec::new();
+    options: &ParseOptions,
+) -> Result<Value, ParseError> {
+    if !is_token(&TokenKind::LeftSquareBracket, token_list, *index) {
+        return Err(error_at(
+            ParseErrorKind::UnexpectedToken,
+            token_list,
+            *index,
+            "'['",
+        ));
+    }
+    return parse_value(token_list, index, options);
+}
 
-    if !expect_token(Token::LeftSquareBracket, token_list, index) {
-        return Err(ParseError::UnexpectedToken);
+/// An object or array that `parse_value` has opened and not yet closed.
+enum Frame {
+    Object {
+        members: Vec<(String, Value)>,
+        /// 値を読んでいるメンバーのキーとその位置
+        key: String,
+        key_span: Span,
+        /// キーごとに、最初のメンバーの位置とキーの位置
+        seen: HashMap<String, (usize, Span)>,
+    },
+    Array(Vec<Value>),
+}
+
+impl Frame {
+    fn len(&self) -> usize {
+        match self {
+            Frame::Object { members, .. } => return members.len(),
+            Frame::Array(elements) => return elements.len(),
+        }
     }
 
-    // 空配列の場合はreturnする
-    if expect_token(Token::RightSquareBracket, token_list, index) {
-        return Ok(Box::new(ArrayNode { value: value }));
+    fn close_token(&self) -> TokenKind<'static> {
+        match self {
+            Frame::Object { .. } => return TokenKind::RightBracket,
+            Frame::Array(_) => return TokenKind::RightSquareBracket,
+        }
     }
 
-    loop {
-        match parse_value(token_list, index) {
-            Ok(v) => {
-                value.push(v);
-            }
-            Err(err) => return Err(err),
+    /// Starts the next member or element at `index`: checks the limit on
+    /// members and, in an object, reads the key and its colon.
+    fn begin(
+        &mut self,
+        token_list: &[Token],
+        index: &mut usize,
+        options: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        if let Some(max) = options.limits.max_members.filter(|max| self.len() >= *max) {
+            let expected = match self {
+                Frame::Object { .. } => format!("at most {} members", max),
+                Frame::Array(_) => format!("at most {} elements", max),
+            };
+            return Err(error_at(
+                ParseErrorKind::TooManyMembers,
+                token_list,
+                *index,
+                &expected,
+            ));
         }
-        if !expect_token(Token::Commma, token_list, index) {
-            break;
+        if let Frame::Object { key, key_span, .. } = self {
+            (*key, *key_span) = parse_member_key(token_list, index, options)?;
         }
+        return Ok(());
     }
 
-    if !expect_token(Token::RightSquareBracket, token_list, index) {
-        return Err(ParseError::UnexpectedToken);
+    /// Adds the value of the member or element just read, applying the
+    /// duplicate-key policy in objects.
+    fn push(&mut self, value: Value, options: &ParseOptions) -> Result<(), ParseError> {
+        let (members, key, span, seen) = match self {
+            Frame::Array(elements) => {
+                elements.push(value);
+                return Ok(());
+            }
+            Frame::Object {
+                members,
+                key,
+                key_span,
+                seen,
+            } => (members, std::mem::take(key), *key_span, seen),
+        };
+        match options.duplicate_keys {
+            DuplicateKeys::Allow | DuplicateKeys::Warn => members.push((key, value)),
+            policy => match seen.get(&key) {
+                Some(&(position, first)) => match policy {
+                    DuplicateKeys::Error => {
+                        value.dispose();
+                        return Err(duplicate_key_error(&key, first, span));
+                    }
+                    DuplicateKeys::Last => {
+                        std::mem::replace(&mut members[position].1, value).dispose()
+                    }
+                    _ => value.dispose(),
+                },
+                None => {
+                    seen.insert(key.clone(), (members.len(), span));
+                    members.push((key, value));
+                }
+            },
+        }
+        return Ok(());
     }
 
-    return Ok(Box::new(ArrayNode { value: value }));
+    fn into_value(mut self) -> Value {
+        match &mut self {
+            Frame::Object { members, .. } => return Value::Object(std::mem::take(members)),
+            Frame::Array(elements) => return Value::Array(std::mem::take(elements)),
+        }
+    }
 }
 
+/// Drops what was read of a container whose value is never finished, as
+/// when the input has an error deep inside it, without recursion.
+impl Drop for Frame {
+    fn drop(&mut self) {
+        match self {
+            Frame::Object { members, .. } => {
+                members.drain(..).for_each(|(_, value)| value.dispose());
+            }
+            Frame::Array(elements) => elements.drain(..).for_each(Value::dispose),
+        }
+    }
+}
+
+/// Reads one value. Objects and arrays are kept on a stack of their own
+/// rather than read by recursion, so nesting is bounded by
+/// `options.limits`, not by the size of the thread's stack.
 pub fn parse_value(
-    token_list: &Vec<Token>,
+    token_list: &[Token],
     index: &mut usize,
-) -> Result<Box<dyn Node>, ParseError> {
-    match token_list[*index] {
-        Token::Str(_) => parse_string(token_list, index),
-        Token::Num(_) => parse_number(token_list, index),
-        Token::Bool(_) => parse_bool(token_list, index),
-        Token::Null => parse_null(token_list, index),
-        Token::LeftBracket => parse_objects(token_list, index),
-        Token::LeftSquareBracket => parse_array(token_list, index),
-        _ => Err(ParseError::InvalidToken),
+    options: &ParseOptions,
+) -> Result<Value, ParseError> {
+    let mut stack: Vec<Frame> = Vec::new();
+    loop {
+        // 値を一つ読む。コンテナを開いたら、その最初の要素へ進む
+        let mut value = match token_list.get(*index).map(|token| &token.kind) {
+            Some(TokenKind::Str(_)) => parse_string(token_list, index)?,
+            Some(TokenKind::Num(_)) => parse_number(token_list, index)?,
+            Some(TokenKind::Bool(_)) => parse_bool(token_list, index)?,
+            Some(TokenKind::Null) => parse_null(token_list, index)?,
+            Some(kind @ (TokenKind::LeftBracket | TokenKind::LeftSquareBracket)) => {
+                if let Some(max) = options.limits.max_depth.filter(|max| stack.len() >= *max) {
+                    return Err(error_at(
+                        ParseErrorKind::TooDeep,
+                        token_list,
+                        *index,
+                        &format!("at most {} levels of nesting", max),
+                    ));
+                }
+                let mut frame = if *kind == TokenKind::LeftBracket {
+                    Frame::Object {
+                        members: Vec::new(),
+                        key: String::new(),
+                        key_span: Span::default(),
+                        seen: HashMap::new(),
+                    }
+                } else {
+                    Frame::Array(Vec::new())
+                };
+                *index += 1;
+                // 空のコンテナ
+                if expect_token(frame.close_token(), token_list, index) {
+                    frame.into_value()
+                } else {
+                    frame.begin(token_list, index, options)?;
+                    stack.push(frame);
+                    continue;
+                }
+            }
+            _ => {
+                return Err(error_at(
+                    ParseErrorKind::InvalidToken,
+                    token_list,
+                    *index,
+                    "a value",
+                ))
+            }
+        };
+        // 読んだ値をコンテナに入れ、閉じたコンテナはさらに外側に入れる
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return Ok(value);
+            };
+            frame.push(value, options)?;
+            let close = frame.close_token();
+            if expect_token(TokenKind::Commma, token_list, index)
+                // 閉じ括弧の前のカンマを認める
+                && !(options.trailing_commas && is_token(&close, token_list, *index))
+            {
+                frame.begin(token_list, index, options)?;
+                break;
+            }
+            if !expect_token(close, token_list, index) {
+                let expected = match frame {
+                    Frame::Object { .. } => "',' or '}'",
+                    Frame::Array(_) => "',' or ']'",
+                };
+                return Err(error_at(
+                    ParseErrorKind::UnexpectedToken,
+                    token_list,
+                    *index,
+                    expected,
+                ));
+            }
+            value = stack.pop().unwrap().into_value();
+        }
     }
 }
 
-pub fn parse_string(
-    token_list: &Vec<Token>,
-    index: &mut usize,
-) -> Result<Box<dyn Node>, ParseError> {
-    if let Token::Str(string) = &token_list[*index] {
+pub fn parse_string(token_list: &[Token], index: &mut usize) -> Result<Value, ParseError> {
+    if let Some(TokenKind::Str(string)) = token_list.get(*index).map(|token| &token.kind) {
         *index += 1;
-        Ok(Box::new(StringNode {
-            value: string.clone(),
-        }))
+        Ok(Value::Str(string.to_string()))
     } else {
-        Err(ParseError::IsNotString)
+        Err(error_at(
+            ParseErrorKind::IsNotString,
+            token_list,
+            *index,
+            "string",
+        ))
     }
 }
 
-pub fn parse_number(
-    token_list: &Vec<Token>,
-    index: &mut usize,
-) -> Result<Box<dyn Node>, ParseError> {
-    if let Token::Num(string) = &token_list[*index] {
+pub fn parse_number(token_list: &[Token], index: &mut usize) -> Result<Value, ParseError> {
+    if let Some(TokenKind::Num(string)) = token_list.get(*index).map(|token| &token.kind) {
         *index += 1;
-        Ok(Box::new(NumberNode {
-            value: string.clone(),
-        }))
+        Ok(Value::Num(Number::from_lexeme(string.to_string())))
     } else {
-        Err(ParseError::IsNotNumber)
+        Err(error_at(
+            ParseErrorKind::IsNotNumber,
+            token_list,
+            *index,
+            "number",
+        ))
     }
 }
 
-pub fn parse_bool(token_list: &Vec<Token>, index: &mut usize) -> Result<Box<dyn Node>, ParseError> {
-    if let Token::Bool(string) = &token_list[*index] {
+pub fn parse_bool(token_list: &[Token], index: &mut usize) -> Result<Value, ParseError> {
+    if let Some(TokenKind::Bool(b)) = token_list.get(*index).map(|token| &token.kind) {
         *index += 1;
-        Ok(Box::new(BoolNode {
-            value: string.clone(),
-        }))
+        Ok(Value::Bool(*b))
     } else {
-        Err(ParseError::IsNotBool)
+        Err(error_at(
+            ParseErrorKind::IsNotBool,
+            token_list,
+            *index,
+            "true or false",
+        ))
     }
 }
 
-pub fn parse_null(token_list: &Vec<Token>, index: &mut usize) -> Result<Box<dyn Node>, ParseError> {
-    if let Token::Null = &token_list[*index] {
+pub fn parse_null(token_list: &[Token], index: &mut usize) -> Result<Value, ParseError> {
+    if let Some(TokenKind::Null) = token_list.get(*index).map(|token| &token.kind) {
         *index += 1;
-        Ok(Box::new(NullNode {
-            value: "null".to_string(),
-        }))
+        Ok(Value::Null)
     } else {
-        Err(ParseError::IsNotNull)
+        Err(error_at(
+            ParseErrorKind::IsNotNull,
+            token_list,
+            *index,
+            "null",
+        ))
     }
 }
 
@@ -345,113 +755,214 @@ mod test {
     use std::vec;
 
     use super::*;
+    use crate::options::Limits;
+
+    fn tokens(kinds: Vec<TokenKind>) -> Vec<Token> {
+        return kinds
+            .into_iter()
+            .map(|kind| Token {
+                kind,
+                span: Span::default(),
+            })
+            .collect();
+    }
 
     #[test]
     fn test_parse() {
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("\"value\"".to_string()),
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Str("value".into()),
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":\"value\"}".to_string()
         );
 
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("1".to_string()),
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Num("1".into()),
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":1}".to_string()
         );
 
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("true".to_string()),
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Bool(true),
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":true}".to_string()
         );
 
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("null".to_string()),
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Null,
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":null}".to_string()
         );
 
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("\"value\"".to_string()),
-                Token::RightBracket,
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Str("value".into()),
+                TokenKind::RightBracket,
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":{\"key\":\"value\"}}".to_string()
         );
 
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::LeftSquareBracket,
-                Token::Str("\"a\"".to_string()),
-                Token::Commma,
-                Token::Str("\"b\"".to_string()),
-                Token::RightSquareBracket,
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::LeftSquareBracket,
+                TokenKind::Str("a".into()),
+                TokenKind::Commma,
+                TokenKind::Str("b".into()),
+                TokenKind::RightSquareBracket,
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":[\"a\",\"b\"]}".to_string()
         );
 
         assert_eq!(
-            parse(vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("\"value\"".to_string()),
-                Token::Commma,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("\"value\"".to_string()),
-                Token::RightBracket,
-            ])
+            parse(tokens(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Str("value".into()),
+                TokenKind::Commma,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Str("value".into()),
+                TokenKind::RightBracket,
+            ]))
             .unwrap()
             .print_node(),
             "{\"key\":\"value\",\"key\":\"value\"}".to_string()
         );
     }
 
+    #[test]
+    fn test_parse_empty_object() {
+        assert_eq!(parse_str("{}").unwrap(), Value::Object(vec![]));
+        assert_eq!(
+            parse_str("{\"a\": {}, \"b\": [{}]}").unwrap().print_node(),
+            "{\"a\":{},\"b\":[{}]}".to_string()
+        );
+        assert!(parse_str("{,}").is_err());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let value = parse(tokens(vec![
+            TokenKind::LeftBracket,
+            TokenKind::Str("key".into()),
+            TokenKind::Colorn,
+            TokenKind::LeftSquareBracket,
+            TokenKind::Num("1".into()),
+            TokenKind::Commma,
+            TokenKind::Bool(false),
+            TokenKind::Commma,
+            TokenKind::Null,
+            TokenKind::RightSquareBracket,
+            TokenKind::RightBracket,
+        ]))
+        .unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![(
+                "key".to_string(),
+                Value::Array(vec![
+                    Value::Num(Number::from(1)),
+                    Value::Bool(false),
+                    Value::Null
+                ])
+            )])
+        );
+        assert_eq!(value["key"][1].as_bool(), Some(false));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = parse_str("{\"a\" 1}").unwrap_err();
+        match err {
+            Error::Parse(err) => {
+                assert_eq!(err.kind, ParseErrorKind::UnexpectedToken);
+                assert_eq!(err.expected, "':'");
+                assert_eq!(err.found, "number 1");
+                assert_eq!(err.span.start.column, 6);
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        let err = parse_str("[1, 2").unwrap_err();
+        match err {
+            Error::Parse(err) => {
+                assert_eq!(err.kind, ParseErrorKind::UnexpectedEof);
+                assert_eq!(err.expected, "',' or ']'");
+                assert_eq!(err.found, "end of input");
+                assert_eq!(err.span.start.column, 6);
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        assert!(matches!(parse_str("[1, @]"), Err(Error::Tokenize(_))));
+
+        let err = parse_str("[1] 2").unwrap_err();
+        match err {
+            Error::Parse(err) => {
+                assert_eq!(err.expected, "end of input");
+                assert_eq!(err.found, "number 2");
+            }
+            _ => panic!("expected a parse error"),
+        }
+        assert!(parse_str("{\"a\":1}}").is_err());
+        assert!(parse_str("").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = r#"{"caf\u00e9":"\ud83d\ude00 \"q\" \\ \u0001\n"}"#;
+        let value = parse_str(source).unwrap();
+        assert_eq!(value["café"].as_str(), Some("😀 \"q\" \\ \u{1}\n"));
+        assert_eq!(
+            value.print_node(),
+            r#"{"café":"😀 \"q\" \\ \u0001\n"}"#.to_string()
+        );
+        assert_eq!(parse_str(&value.print_node()).unwrap(), value);
+    }
+
     #[test]
     fn test_format_node() {
         let indent = "  ".to_string();
@@ -459,17 +970,8 @@ mod test {
   "key": "value"
 }"#;
         assert_eq!(
-            ObjectListNode {
-                value: vec![Box::new(ObjectNode {
-                    key: Box::new(StringNode {
-                        value: "\"key\"".to_string(),
-                    }),
-                    value: Box::new(StringNode {
-                        value: "\"value\"".to_string(),
-                    }),
-                })],
-            }
-            .format_node(&indent, &mut 0),
+            Value::Object(vec![("key".to_string(), Value::Str("value".to_string()))])
+                .format_node(&indent, &mut 0),
             expect1.to_string()
         );
 
@@ -481,25 +983,308 @@ mod test {
 }"#;
 
         assert_eq!(
-            ObjectListNode {
-                value: vec![Box::new(ObjectNode {
-                    key: Box::new(StringNode {
-                        value: "\"key\"".to_string(),
-                    }),
-                    value: Box::new(ArrayNode {
-                        value: vec![
-                            Box::new(StringNode {
-                                value: "\"value1\"".to_string()
-                            }),
-                            Box::new(StringNode {
-                                value: "\"value2\"".to_string()
-                            })
-                        ],
-                    }),
-                })],
-            }
+            Value::Object(vec![(
+                "key".to_string(),
+                Value::Array(vec![
+                    Value::Str("value1".to_string()),
+                    Value::Str("value2".to_string())
+                ])
+            )])
             .format_node(&indent, &mut 0),
             expect2.to_string()
         );
+
+        let expect3 = r#"{
+  "object": {
+    "array": []
+  },
+  "empty": {}
+}"#;
+
+        assert_eq!(
+            Value::Object(vec![
+                (
+                    "object".to_string(),
+                    Value::Object(vec![("array".to_string(), Value::Array(vec![]))])
+                ),
+                ("empty".to_string(), Value::Object(vec![]))
+            ])
+            .format_node(&indent, &mut 0),
+            expect3.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_json5() {
+        let options = ParseOptions::json5();
+        let value =
+            parse_str_with("{a: [1, 2,], 'b': {}, null: 0x10, Infinity: 1,}", &options).unwrap();
+        assert_eq!(
+            value.print_node(),
+            r#"{"a":[1,2],"b":{},"null":0x10,"Infinity":1}"#
+        );
+        assert!(parse_str_with("[1,,]", &options).is_err());
+        assert!(parse_str_with("[,]", &options).is_err());
+        assert!(parse_str_with("{a}", &options).is_err());
+        let err = parse_str_with("{1: 2}", &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected string or identifier, found number 1"
+        );
+        assert!(parse_str("[1, 2,]").is_err());
+    }
+
+    #[test]
+    fn test_parse_trailing_commas() {
+        let options = ParseOptions {
+            trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let value = parse_str_with(r#"{"a": [1, 2,], "b": {"c": null,},}"#, &options).unwrap();
+        assert_eq!(value.print_node(), r#"{"a":[1,2],"b":{"c":null}}"#);
+        assert!(parse_str_with("[1,,]", &options).is_err());
+        assert!(parse_str_with("[,]", &options).is_err());
+        assert!(parse_str_with("{,}", &options).is_err());
+        let err = parse_str("[1, 2,]").unwrap_err();
+        assert_eq!(err.to_string(), "expected a value, found ']'");
+    }
+
+    #[test]
+    fn test_parse_duplicate_keys() {
+        let source = r#"{"a": 1, "b": {"a": 2, "c": 3}, "a": 4, "b": [5]}"#;
+        let with = |duplicate_keys| {
+            let options = ParseOptions {
+                duplicate_keys,
+                ..ParseOptions::default()
+            };
+            return parse_str_with(source, &options).map(|value| value.print_node());
+        };
+        assert_eq!(
+            with(DuplicateKeys::Allow).unwrap(),
+            r#"{"a":1,"b":{"a":2,"c":3},"a":4,"b":[5]}"#
+        );
+        assert_eq!(with(DuplicateKeys::Warn), with(DuplicateKeys::Allow));
+        assert_eq!(
+            with(DuplicateKeys::First).unwrap(),
+            r#"{"a":1,"b":{"a":2,"c":3}}"#
+        );
+        assert_eq!(with(DuplicateKeys::Last).unwrap(), r#"{"a":4,"b":[5]}"#);
+
+        let err = with(DuplicateKeys::Error).unwrap_err();
+        let Error::Parse(err) = err else {
+            panic!("expected a parse error, got {:?}", err);
+        };
+        assert_eq!(err.kind, ParseErrorKind::DuplicateKey);
+        assert_eq!((err.span.start.line, err.span.start.column), (1, 33));
+        assert_eq!(
+            err.to_string(),
+            r#"expected a key not already in the object, found "a" again, first at 1:2"#
+        );
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::json5()
+        };
+        assert!(parse_str_with("{a: 1, /* */ 'a': 2}", &options).is_err());
+        assert!(parse_str_with(r#"[{"a": 1}, {"a": 2}]"#, &options).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let options = ParseOptions::json5();
+        let source = "{\n  a: 1,\n  // a: 0\n  b: {a: [{a: 2}], 'a': 3},\n  \"a\": 4,\n}";
+        let token_list = tokenizer::tokenize_bytes_with(source.as_bytes(), &options).unwrap();
+        let warnings = duplicate_keys(&token_list, &options);
+        let found = warnings
+            .iter()
+            .map(|warning| {
+                let start = warning.span.start;
+                return format!("{}:{} {}", start.line, start.column, warning.message);
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(
+            found,
+            vec![
+                r#"4:20 duplicate key "a", first at 4:7"#.to_string(),
+                r#"5:3 duplicate key "a", first at 2:3"#.to_string(),
+            ]
+        );
+        let token_list =
+            tokenizer::tokenize_bytes_with(br#"{"a": 1, "b": 2}"#, &ParseOptions::default())
+                .unwrap();
+        assert!(duplicate_keys(&token_list, &ParseOptions::default()).is_empty());
+    }
+
+    /// The depth of `value` along its last children.
+    fn nesting(mut value: &Value) -> usize {
+        let mut depth = 0;
+        loop {
+            let last = match value {
+                Value::Array(elements) => elements.last(),
+                Value::Object(members) => members.last().map(|(_, value)| value),
+                _ => return depth,
+            };
+            depth += 1;
+            match last {
+                Some(last) => value = last,
+                None => return depth,
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_deep_nesting() {
+        // 再帰しないので、スレッドのスタックより深く入れ子にできる
+        let depth = 100_000;
+        let source = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let value = parse_str(&source).unwrap();
+        assert_eq!(nesting(&value), depth);
+        assert_eq!(value.print_node(), source);
+        value.dispose();
+        let source = format!("{}1{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+        let value = parse_str(&source).unwrap();
+        assert_eq!(nesting(&value), depth * 2);
+        value.dispose();
+        // 途中で捨てる値も再帰せずに捨てる
+        let source = format!("{}1,]", "[".repeat(depth));
+        assert!(parse_str(&source).is_err());
+        let deep = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let source = format!(r#"{{"a": {}, "a": {}}}"#, deep, deep);
+        for duplicate_keys in [
+            DuplicateKeys::First,
+            DuplicateKeys::Last,
+            DuplicateKeys::Error,
+        ] {
+            let options = ParseOptions {
+                duplicate_keys,
+                ..ParseOptions::default()
+            };
+            if let Ok(value) = parse_str_with(&source, &options) {
+                value.dispose();
+            }
+        }
+        assert!(parse_str(&format!("{} 1", deep)).is_err());
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let with = |limits, source: &str| {
+            let options = ParseOptions {
+                limits,
+                ..ParseOptions::default()
+            };
+            return parse_str_with(source, &options).map_err(|err| {
+                let start = err.span().start;
+                return format!("{}:{} {}", start.line, start.column, err);
+            });
+        };
+        let depth = Limits {
+            max_depth: Some(2),
+            ..Limits::default()
+        };
+        assert!(with(depth, r#"[{"a": []}, 1]"#).is_err());
+        assert!(with(depth, r#"[{"a": 1}, [2]]"#).is_ok());
+        assert_eq!(
+            with(depth, r#"{"a": [[1]]}"#),
+            Err("1:8 expected at most 2 levels of nesting, found '['".to_string())
+        );
+        let members = Limits {
+            max_members: Some(2),
+            ..Limits::default()
+        };
+        assert!(with(members, r#"[[1, 2], {"a": 1, "b": 2}]"#).is_ok());
+        assert_eq!(
+            with(members, "[1, 2, 3]"),
+            Err("1:8 expected at most 2 elements, found number 3".to_string())
+        );
+        assert_eq!(
+            with(members, r#"{"a": 1, "b": 2, "c": 3}"#),
+            Err(r#"1:18 expected at most 2 members, found string "c""#.to_string())
+        );
+        let none = Limits {
+            max_members: Some(0),
+            ..Limits::default()
+        };
+        assert!(with(none, "[[], {}]").is_err());
+        assert!(with(none, "1").is_ok());
+
+        let sizes = Limits {
+            max_bytes: Some(16),
+            max_string_length: Some(4),
+            max_number_length: Some(3),
+            ..Limits::default()
+        };
+        assert!(with(sizes, r#"["abcd", 123]"#).is_ok());
+        assert_eq!(
+            with(sizes, r#"["abcde"]"#),
+            Err(
+                r#"1:2 expected a shorter string, found a string of more than 4 bytes"#.to_string()
+            )
+        );
+        assert_eq!(
+            with(sizes, r#"{"a\u00e9bc": 1}"#),
+            Err(
+                r#"1:2 expected a shorter string, found a string of more than 4 bytes"#.to_string()
+            )
+        );
+        assert_eq!(
+            with(sizes, "[-1.5]"),
+            Err(
+                "1:2 expected a shorter number, found a number of more than 3 characters"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            with(sizes, "[1, 2, 3, 4, 5, 6]"),
+            Err("1:17 expected a smaller input, found more than 16 bytes".to_string())
+        );
+        // 上限の次の一字まで読めば足りる
+        assert_eq!(
+            with(sizes, &"[1, 2, 3, 4, 5, 6]"[..17]),
+            with(sizes, "[1, 2, 3, 4, 5, 6]")
+        );
+    }
+
+    #[test]
+    fn test_parse_many() {
+        let values = |source: &str| {
+            return parse_many(source.as_bytes())
+                .map(|result| match result {
+                    Ok(value) => value.print_node(),
+                    Err(err) => format!("error at {}: {}", err.span().start.offset, err),
+                })
+                .collect::<Vec<String>>();
+        };
+        assert_eq!(
+            values("{\"a\":1}{\"a\":2}\n[3] \"x\"4 null"),
+            vec![r#"{"a":1}"#, r#"{"a":2}"#, "[3]", r#""x""#, "4", "null"]
+        );
+        assert!(values("").is_empty());
+        assert!(values(" \n ").is_empty());
+        assert_eq!(
+            values("1 [2 3] 4"),
+            vec!["1", "error at 5: expected ',' or ']', found number 3"]
+        );
+        assert_eq!(
+            values("1 2 @"),
+            vec![
+                "1",
+                "2",
+                "error at 4: expected a JSON value or punctuation, found '@'"
+            ]
+        );
+        assert_eq!(
+            values("1 [@] 2"),
+            vec![
+                "1",
+                "error at 3: expected a JSON value or punctuation, found '@'"
+            ]
+        );
+
+        let mut values = parse_many_with(b"// a\n{}\n/* b */ []", &ParseOptions::jsonc());
+        assert_eq!(values.next(), Some(Ok(Value::Object(vec![]))));
+        assert_eq!(values.line(), 2);
+        assert_eq!(values.next(), Some(Ok(Value::Array(vec![]))));
+        assert_eq!(values.line(), 3);
+        assert_eq!(values.next(), None);
     }
 }
@@ -0,0 +1,658 @@
+//! A parser that carries on after errors, so that every problem in a
+//! document can be reported at once.
+//!
+//! After an error it resynchronises on the next `,`, `]` or `}`. A missing
+//! colon or comma is assumed to be there, a closing bracket that belongs to
+//! an outer container closes the inner ones first, and anything that cannot
+//! be read is left out of the tree.
+
+use super::error::Error;
+use super::number::Number;
+use super::options::ParseOptions;
+use super::parser::{self, ParseError, ParseErrorKind};
+use super::tokenizer::{self, Token, TokenKind};
+use super::value::Value;
+
+/// What `parse_bytes_recovering` could make of its input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovered {
+    /// The document with the parts that could not be read left out, or
+    /// `None` when there was no value at all.
+    pub value: Option<Value>,
+    /// Every problem found, in source order. Empty when the input is valid.
+    pub errors: Vec<Error>,
+}
+
+/// Parses like `parse_bytes_with`, but instead of stopping at the first
+/// error returns a partial tree and all the errors. Input nested past
+/// `options.limits.max_depth` is rejected before it is read.
+pub fn parse_bytes_recovering(bytes: &[u8], options: &ParseOptions) -> Recovered {
+    let (token_list, tokenize_errors) = tokenizer::tokenize_bytes_recovering(bytes, options);
+    let skipped = tokenize_errors
+        .iter()
+        .map(|err| err.span.start.offset)
+        .collect();
+    let (value, parse_errors) = parse_tokens_recovering(&token_list, skipped, options);
+
+    let mut errors: Vec<Error> = tokenize_errors.into_iter().map(Error::Tokenize).collect();
+    errors.extend(parse_errors.into_iter().map(Error::Parse));
+    // 入力の終わりでの誤りは最後の字句の位置を指すので、その後の読み飛ばしより後ろに並べる
+    errors.sort_by_key(|err| (is_at_end(err), err.span().start.offset));
+    return Recovered { value, errors };
+}
+
+/// The parsing half of `parse_bytes_recovering`. `skipped` holds the
+/// offsets where the tokenizer dropped text it could not read.
+pub(crate) fn parse_tokens_recovering(
+    token_list: &[Token],
+    skipped: Vec<usize>,
+    options: &ParseOptions,
+) -> (Option<Value>, Vec<ParseError>) {
+    let token_list: Vec<Token> = token_list
+        .iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Comment(_)))
+        .cloned()
+        .collect();
+    // 深すぎる入力は読まずに断る
+    if let Some(err) = too_deep(&token_list, options) {
+        return (None, vec![err]);
+    }
+    let mut parser = Recovering {
+        token_list: &token_list,
+        index: 0,
+        options,
+        open: Vec::new(),
+        skipped,
+        errors: Vec::new(),
+    };
+    let value = parser.root();
+    return (value, parser.errors);
+}
+
+/// An error at the first bracket that opens past `max_depth`, counting
+/// every bracket whether or not it is matched.
+fn too_deep(token_list: &[Token], options: &ParseOptions) -> Option<ParseError> {
+    let max = options.limits.max_depth?;
+    let mut depth = 0;
+    for (index, token) in token_list.iter().enumerate() {
+        match token.kind {
+            TokenKind::LeftBracket | TokenKind::LeftSquareBracket if depth >= max => {
+                return Some(parser::error_at(
+                    ParseErrorKind::TooDeep,
+                    token_list,
+                    index,
+                    &format!("at most {} levels of nesting", max),
+                ));
+            }
+            TokenKind::LeftBracket | TokenKind::LeftSquareBracket => depth += 1,
+            TokenKind::RightBracket | TokenKind::RightSquareBracket => {
+                depth = usize::saturating_sub(depth, 1);
+            }
+            _ => {}
+        }
+    }
+    return None;
+}
+
+fn is_at_end(err: &Error) -> bool {
+    match err {
+        Error::Tokenize(err) => return err.found == "end of input",
+        Error::Parse(err) => return err.found == "end of input",
+    }
+}
+
+/// An object or array being read by `Recovering::value`.
+struct Frame {
+    items: Items,
+    // 値を読んでいるメンバーのキー
+    key: String,
+    // 開き括弧かカンマの直後か
+    expect_item: bool,
+    // カンマが要素の後ろにあったか
+    after_comma: bool,
+}
+
+enum Items {
+    Object(Vec<(String, Value)>),
+    Array(Vec<Value>),
+}
+
+impl Frame {
+    fn into_value(self) -> Value {
+        match self.items {
+            Items::Object(members) => return Value::Object(members),
+            Items::Array(elements) => return Value::Array(elements),
+        }
+    }
+}
+
+/// What to do after looking at the next token inside a container.
+enum Next {
+    /// 要素の値を読む
+    Value,
+    /// コンテナを閉じる
+    Close,
+    /// 次のトークンを見る
+    Again,
+}
+
+struct Recovering<'t, 'a> {
+    token_list: &'t [Token<'a>],
+    index: usize,
+    options: &'t ParseOptions,
+    // 開いているコンテナの開き括弧の位置
+    open: Vec<usize>,
+    // 字句解析で読み飛ばした位置
+    skipped: Vec<usize>,
+    errors: Vec<ParseError>,
+}
+
+impl Recovering<'_, '_> {
+    fn root(&mut self) -> Option<Value> {
+        loop {
+            if let Some(value) = self.value() {
+                if self.index < self.token_list.len() {
+                    self.error(ParseErrorKind::StrayToken, "end of input");
+                }
+                return Some(value);
+            }
+            // 値にならないトークンは読み飛ばして次を試す
+            self.index += 1;
+            if self.index >= self.token_list.len() {
+                return None;
+            }
+        }
+    }
+
+    /// Reads a value, or reports what is there instead and returns `None`
+    /// without consuming it. Objects and arrays are kept on a stack of their
+    /// own rather than read by recursion, like `parser::parse_value`.
+    fn value(&mut self) -> Option<Value> {
+        let mut stack: Vec<Frame> = Vec::new();
+        loop {
+            // 値を一つ読む。コンテナを開いたら、その最初の要素へ進む
+            let mut read = match self.peek() {
+                Some(kind @ (TokenKind::LeftBracket | TokenKind::LeftSquareBracket)) => {
+                    let items = if *kind == TokenKind::LeftBracket {
+                        Items::Object(Vec::new())
+                    } else {
+                        Items::Array(Vec::new())
+                    };
+                    stack.push(Frame {
+                        items,
+                        key: String::new(),
+                        expect_item: true,
+                        after_comma: false,
+                    });
+                    self.open.push(self.index);
+                    self.index += 1;
+                    None
+                }
+                _ => Some(self.scalar()),
+            };
+            // 読めた値を親に加え、次の値を読むところまで進む
+            loop {
+                if let Some(value) = read.take() {
+                    match stack.last_mut() {
+                        Some(frame) => self.add(frame, value),
+                        None => return value,
+                    }
+                }
+                let frame = stack.last_mut().unwrap();
+                let next = match frame.items {
+                    Items::Object(_) => self.object(frame),
+                    Items::Array(_) => self.array(frame),
+                };
+                match next {
+                    Next::Value => break,
+                    Next::Again => {}
+                    Next::Close => {
+                        let frame = stack.pop().unwrap();
+                        self.open.pop();
+                        read = Some(Some(frame.into_value()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a string, number, boolean or null, or reports what is there
+    /// instead and returns `None` without consuming it.
+    fn scalar(&mut self) -> Option<Value> {
+        let value = match self.peek() {
+            Some(TokenKind::Str(string)) => Value::Str(string.to_string()),
+            Some(TokenKind::Num(lexeme)) => Value::Num(Number::from_lexeme(lexeme.to_string())),
+            Some(TokenKind::Bool(b)) => Value::Bool(*b),
+            Some(TokenKind::Null) => Value::Null,
+            _ => {
+                self.error(ParseErrorKind::InvalidToken, "a value");
+                return None;
+            }
+        };
+        self.index += 1;
+        return Some(value);
+    }
+
+    /// Adds the value read for the current member or element of `frame`,
+    /// or skips past it when it could not be read.
+    fn add(&mut self, frame: &mut Frame, value: Option<Value>) {
+        match (&mut frame.items, value) {
+            (Items::Object(members), Some(value)) => {
+                members.push((std::mem::take(&mut frame.key), value));
+            }
+            (Items::Object(_), None) => self.skip_member(),
+            (Items::Array(elements), Some(value)) => elements.push(value),
+            // scalar() で報告済み
+            (Items::Array(_), None) => self.index += 1,
+        }
+        frame.expect_item = self.after_comma();
+    }
+
+    /// Looks at the next token inside an object.
+    fn object(&mut self, frame: &mut Frame) -> Next {
+        match self.peek() {
+            None => {
+                self.unclosed();
+                return Next::Close;
+            }
+            Some(TokenKind::RightBracket) => {
+                if frame.after_comma && !self.options.trailing_commas {
+                    self.error(ParseErrorKind::TrailingComma, "string");
+                }
+                self.index += 1;
+                return Next::Close;
+            }
+            Some(TokenKind::RightSquareBracket) => {
+                if self.closes_outer(&TokenKind::LeftSquareBracket) {
+                    self.unclosed();
+                    return Next::Close;
+                }
+                self.error(ParseErrorKind::StrayToken, "',' or '}'");
+                self.index += 1;
+            }
+            Some(TokenKind::Commma) => {
+                if frame.expect_item {
+                    self.error(ParseErrorKind::InvalidToken, "string");
+                }
+                frame.after_comma = !frame.expect_item;
+                frame.expect_item = true;
+                self.index += 1;
+            }
+            Some(_) => {
+                if !frame.expect_item {
+                    self.error(ParseErrorKind::MissingComma, "',' or '}'");
+                }
+                frame.after_comma = false;
+                if let Some(key) = self.key() {
+                    frame.key = key;
+                    return Next::Value;
+                }
+                frame.expect_item = self.after_comma();
+            }
+        }
+        return Next::Again;
+    }
+
+    /// Reads the key of a member and its colon. Returns `None` when the
+    /// member has no value to read.
+    fn key(&mut self) -> Option<String> {
+        let key = match parser::parse_key(self.token_list, &mut self.index, self.options) {
+            Ok(key) => key,
+            Err(err) => {
+                self.errors.push(err);
+                self.skip_member();
+                return None;
+            }
+        };
+        match self.peek() {
+            Some(TokenKind::Colorn) => self.index += 1,
+            None
+            | Some(TokenKind::Commma | TokenKind::RightBracket | TokenKind::RightSquareBracket) => {
+                self.error(ParseErrorKind::MissingColon, "':'");
+                return None;
+            }
+            // 値が続いていればコロンを補って読む
+            Some(_) => self.error(ParseErrorKind::MissingColon, "':'"),
+        }
+        return Some(key);
+    }
+
+    /// Looks at the next token inside an array.
+    fn array(&mut self, frame: &mut Frame) -> Next {
+        match self.peek() {
+            None => {
+                self.unclosed();
+                return Next::Close;
+            }
+            Some(TokenKind::RightSquareBracket) => {
+                if frame.after_comma && !self.options.trailing_commas {
+                    self.error(ParseErrorKind::TrailingComma, "a value");
+                }
+                self.index += 1;
+                return Next::Close;
+            }
+            Some(TokenKind::RightBracket) => {
+                if self.closes_outer(&TokenKind::LeftBracket) {
+                    self.unclosed();
+                    return Next::Close;
+                }
+                self.error(ParseErrorKind::StrayToken, "',' or ']'");
+                self.index += 1;
+            }
+            Some(TokenKind::Commma) => {
+                if frame.expect_item {
+                    self.error(ParseErrorKind::InvalidToken, "a value");
+                }
+                frame.after_comma = !frame.expect_item;
+                frame.expect_item = true;
+                self.index += 1;
+            }
+            // "key": が続くなら外側のオブジェクトのメンバー
+            Some(TokenKind::Str(_))
+                if self.is_key() && self.closes_outer(&TokenKind::LeftBracket) =>
+            {
+                self.unclosed();
+                return Next::Close;
+            }
+            Some(_) => {
+                if !frame.expect_item {
+                    self.error(ParseErrorKind::MissingComma, "',' or ']'");
+                }
+                frame.after_comma = false;
+                return Next::Value;
+            }
+        }
+        return Next::Again;
+    }
+
+    /// Skips to the `,` or closing bracket after a member that could not be
+    /// read, stepping over any containers inside it.
+    fn skip_member(&mut self) {
+        let mut depth = 0;
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::LeftBracket | TokenKind::LeftSquareBracket => depth += 1,
+                TokenKind::RightBracket | TokenKind::RightSquareBracket if depth == 0 => return,
+                TokenKind::RightBracket | TokenKind::RightSquareBracket => depth -= 1,
+                TokenKind::Commma if depth == 0 => return,
+                _ => {}
+            }
+            self.index += 1;
+        }
+    }
+
+    /// Whether a container outside the innermost one was opened with
+    /// `opener`, so that its closing bracket ends the innermost one too.
+    fn closes_outer(&self, opener: &TokenKind) -> bool {
+        let outer = &self.open[..self.open.len() - 1];
+        return outer
+            .iter()
+            .any(|index| self.token_list[*index].kind == *opener);
+    }
+
+    fn unclosed(&mut self) {
+        let open = &self.token_list[*self.open.last().unwrap()];
+        let (opener, closer) = if open.kind == TokenKind::LeftBracket {
+            ("'{'", "'}'")
+        } else {
+            ("'['", "']'")
+        };
+        let expected = format!(
+            "{} to close the {} at {}:{}",
+            closer, opener, open.span.start.line, open.span.start.column
+        );
+        let mut err = parser::error_at(
+            ParseErrorKind::Unclosed,
+            self.token_list,
+            self.index,
+            &expected,
+        );
+        err.kind = ParseErrorKind::Unclosed;
+        self.errors.push(err);
+    }
+
+    /// Whether the last token read was a comma, which a container left
+    /// unclosed may have taken from the one around it.
+    fn after_comma(&self) -> bool {
+        return self.token_list[self.index - 1].kind == TokenKind::Commma;
+    }
+
+    /// Whether the current token is followed by a colon.
+    fn is_key(&self) -> bool {
+        return self
+            .token_list
+            .get(self.index + 1)
+            .is_some_and(|token| token.kind == TokenKind::Colorn);
+    }
+
+    fn peek(&self) -> Option<&TokenKind<'_>> {
+        return self.token_list.get(self.index).map(|token| &token.kind);
+    }
+
+    /// Records an error at the current token, unless the tokenizer already
+    /// reported something it skipped just before it: a value that could not
+    /// be read leaves a gap the parser would otherwise complain about too.
+    fn error(&mut self, kind: ParseErrorKind, expected: &str) {
+        let gap_start = match self.index.checked_sub(1) {
+            Some(previous) => self.token_list[previous].span.end.offset,
+            None => 0,
+        };
+        let gap_end = self
+            .token_list
+            .get(self.index)
+            .map_or(usize::MAX, |token| token.span.start.offset);
+        if self
+            .skipped
+            .iter()
+            .any(|offset| (gap_start..gap_end).contains(offset))
+        {
+            return;
+        }
+        let err = parser::error_at(kind, self.token_list, self.index, expected);
+        self.errors.push(err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::Limits;
+    use crate::parser::Node;
+    use crate::tokenizer::TokenizeErrorKind;
+
+    fn recover(source: &str) -> Recovered {
+        return parse_bytes_recovering(source.as_bytes(), &ParseOptions::default());
+    }
+
+    fn messages(recovered: &Recovered) -> Vec<String> {
+        return recovered
+            .errors
+            .iter()
+            .map(|err| {
+                let start = err.span().start;
+                return format!("{}:{}: {}", start.line, start.column, err);
+            })
+            .collect();
+    }
+
+    fn kinds(recovered: &Recovered) -> Vec<ParseErrorKind> {
+        return recovered
+            .errors
+            .iter()
+            .map(|err| match err {
+                Error::Parse(err) => err.kind,
+                Error::Tokenize(_) => panic!("expected a parse error"),
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_recover_valid() {
+        let source = r#"{"a": [1, {"b": null}], "c": "d"}"#;
+        let recovered = recover(source);
+        assert!(recovered.errors.is_empty());
+        assert_eq!(recovered.value, Some(parser::parse_str(source).unwrap()));
+    }
+
+    #[test]
+    fn test_recover_all_errors() {
+        let source = "{\n  \"a\" 1,\n  \"b\": [1 2,],\n  \"c\": true\n  \"d\": }";
+        let recovered = recover(source);
+        assert_eq!(
+            messages(&recovered),
+            vec![
+                "2:7: expected ':', found number 1",
+                "3:11: expected ',' or ']', found number 2",
+                "3:13: expected a value, found ']'",
+                "5:3: expected ',' or '}', found string \"d\"",
+                "5:8: expected a value, found '}'",
+            ]
+        );
+        assert_eq!(
+            kinds(&recovered),
+            vec![
+                ParseErrorKind::MissingColon,
+                ParseErrorKind::MissingComma,
+                ParseErrorKind::TrailingComma,
+                ParseErrorKind::MissingComma,
+                ParseErrorKind::InvalidToken,
+            ]
+        );
+        assert_eq!(
+            recovered.value.unwrap().print_node(),
+            r#"{"a":1,"b":[1,2],"c":true}"#
+        );
+    }
+
+    #[test]
+    fn test_recover_unclosed() {
+        let recovered = recover("{\"a\": [1, 2}");
+        assert_eq!(
+            messages(&recovered),
+            vec!["1:12: expected ']' to close the '[' at 1:7, found '}'"]
+        );
+        assert_eq!(kinds(&recovered), vec![ParseErrorKind::Unclosed]);
+        assert_eq!(recovered.value.unwrap().print_node(), r#"{"a":[1,2]}"#);
+
+        let recovered = recover("[{\"a\": 1");
+        assert_eq!(
+            messages(&recovered),
+            vec![
+                "1:9: expected '}' to close the '{' at 1:2, found end of input",
+                "1:9: expected ']' to close the '[' at 1:1, found end of input",
+            ]
+        );
+        assert_eq!(recovered.value.unwrap().print_node(), r#"[{"a":1}]"#);
+
+        // 配列の中に "key": が来たら配列を閉じ忘れたとみなす
+        let recovered = recover("{\"a\": [1, 2,\n\"b\": 3}");
+        assert_eq!(
+            messages(&recovered),
+            vec!["2:1: expected ']' to close the '[' at 1:7, found string \"b\""]
+        );
+        assert_eq!(
+            recovered.value.unwrap().print_node(),
+            r#"{"a":[1,2],"b":3}"#
+        );
+    }
+
+    #[test]
+    fn test_recover_stray_tokens() {
+        let recovered = recover("[1, ]] 2");
+        assert_eq!(
+            messages(&recovered),
+            vec![
+                "1:5: expected a value, found ']'",
+                "1:6: expected end of input, found ']'",
+            ]
+        );
+        assert_eq!(
+            kinds(&recovered),
+            vec![ParseErrorKind::TrailingComma, ParseErrorKind::StrayToken]
+        );
+
+        let recovered = recover("{\"a\": 1]}");
+        assert_eq!(kinds(&recovered), vec![ParseErrorKind::StrayToken]);
+        assert_eq!(recovered.value.unwrap().print_node(), r#"{"a":1}"#);
+
+        let recovered = recover("{1: [2], \"b\": :3, \"c\": 4}");
+        assert_eq!(
+            messages(&recovered),
+            vec![
+                "1:2: expected string, found number 1",
+                "1:15: expected a value, found ':'",
+            ]
+        );
+        assert_eq!(recovered.value.unwrap().print_node(), r#"{"c":4}"#);
+
+        let recovered = recover("");
+        assert_eq!(
+            messages(&recovered),
+            vec!["1:1: expected a value, found end of input"]
+        );
+        assert_eq!(recovered.value, None);
+    }
+
+    #[test]
+    fn test_recover_tokenize_errors() {
+        let recovered = recover("[True, \"a\\q\", 1.x, nul, 2");
+        let tokenize_kinds: Vec<TokenizeErrorKind> = recovered
+            .errors
+            .iter()
+            .filter_map(|err| match err {
+                Error::Tokenize(err) => Some(err.kind),
+                Error::Parse(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            tokenize_kinds,
+            vec![
+                TokenizeErrorKind::InvalidCharactar,
+                TokenizeErrorKind::InvalidString,
+                TokenizeErrorKind::InvalidNumber,
+                TokenizeErrorKind::NotNull,
+            ]
+        );
+        assert_eq!(recovered.errors.len(), 5);
+        assert_eq!(recovered.errors[3].to_string(), "expected null, found 'n'");
+        assert!(matches!(
+            recovered.errors.last(),
+            Some(Error::Parse(err)) if err.kind == ParseErrorKind::Unclosed
+        ));
+        assert_eq!(recovered.value.unwrap().print_node(), "[2]");
+    }
+
+    #[test]
+    fn test_recover_deep_nesting() {
+        // 再帰しないので、スレッドのスタックより深く入れ子にできる
+        let depth = 100_000;
+        let source = format!("{}1 2{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+        let recovered = recover(&source);
+        assert_eq!(kinds(&recovered), vec![ParseErrorKind::MissingComma]);
+        let value = recovered.value.unwrap();
+        assert_eq!(value.print_node(), source.replace("1 2", "1,2"));
+        value.dispose();
+        let recovered = recover(&"[".repeat(depth));
+        assert_eq!(recovered.errors.len(), depth);
+        let value = recovered.value.unwrap();
+        assert_eq!(value.print_node().len(), depth * 2);
+        value.dispose();
+    }
+
+    #[test]
+    fn test_recover_too_deep() {
+        let options = ParseOptions {
+            limits: Limits {
+                max_depth: Some(2),
+                ..Limits::default()
+            },
+            ..ParseOptions::default()
+        };
+        let recovered = parse_bytes_recovering(b"[[1], [[2 3]]]", &options);
+        assert_eq!(recovered.value, None);
+        assert_eq!(kinds(&recovered), vec![ParseErrorKind::TooDeep]);
+        assert_eq!(recovered.errors[0].span().start.column, 8);
+        let recovered = parse_bytes_recovering(b"[[1] [2]]", &options);
+        assert_eq!(kinds(&recovered), vec![ParseErrorKind::MissingComma]);
+    }
+}
@@ -0,0 +1,406 @@
+//! Turns almost-JSON, as found in LLM output and logs, into JSON.
+//!
+//! The input is read as JSON5 by the recovering parser, and these fixes are
+//! applied, each reported as a warning:
+//!
+//! - Markdown code fences are dropped, and so is prose before the first
+//!   bracket when the input starts with a word.
+//! - Comments are dropped.
+//! - Single-quoted strings and unquoted keys are written with double quotes.
+//! - Python's `True`, `False` and `None` and JavaScript's `undefined` become
+//!   `true`, `false` and `null`. Any other bare word used as a value becomes
+//!   a string.
+//! - JSON5 numbers are written as JSON, e.g. `0x1F` as `31`. `NaN` and
+//!   `Infinity` become `null`.
+//! - A string cut off by the end of the input is closed.
+//! - A missing comma or colon is inserted and a trailing comma removed.
+//! - Containers still open at the end of the input are closed.
+//! - Anything else that cannot be read is removed, with the member it is in.
+
+use std::borrow::Cow;
+
+use super::diagnostic::Diagnostic;
+use super::error::Error;
+use super::json5;
+use super::options::{Limits, ParseOptions};
+use super::parser::{ParseError, ParseErrorKind};
+use super::recover;
+use super::tokenizer::{self, Position, Span, Token, TokenKind, TokenizeErrorKind};
+use super::value::Value;
+
+/// The result of `repair`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repaired {
+    pub value: Value,
+    /// Every change that was made, as warnings pointing into the input.
+    pub fixes: Vec<Diagnostic>,
+}
+
+/// Repairs `bytes` into a JSON value. Fails only when there is no value to
+/// be found at all.
+pub fn repair(bytes: &[u8]) -> Result<Repaired, Error> {
+    return repair_with(bytes, None);
+}
+
+/// Repairs like `repair`, but fails on input nested deeper than
+/// `max_depth`.
+pub fn repair_with(bytes: &[u8], max_depth: Option<usize>) -> Result<Repaired, Error> {
+    // 末尾のカンマは直したことを報告したいので受け付けない
+    let options = ParseOptions {
+        comments: true,
+        json5: true,
+        limits: Limits {
+            max_depth,
+            ..Limits::default()
+        },
+        ..ParseOptions::default()
+    };
+    let mut fixes = Vec::new();
+    let mut source = bytes.to_vec();
+    blank_code_fences(&mut source, &mut fixes);
+    blank_prose(&mut source, &mut fixes);
+
+    let (mut token_list, mut tokenize_errors) =
+        tokenizer::tokenize_bytes_recovering(&source, &options);
+    if let Some(err) = tokenize_errors
+        .iter()
+        .find(|err| err.kind == TokenizeErrorKind::UnterminatedString)
+    {
+        // 途中で切れた文字列を閉じる
+        let start = token_list.last().map_or(0, |token| token.span.end.offset);
+        let quote = source[start..]
+            .iter()
+            .find(|byte| !byte.is_ascii_whitespace())
+            .copied();
+        if let Some(quote @ (b'"' | b'\'')) = quote {
+            fixes.push(Diagnostic::warning(
+                err.span,
+                "closed a string cut off by the end of the input".to_string(),
+            ));
+            source.push(quote);
+            (token_list, tokenize_errors) = tokenizer::tokenize_bytes_recovering(&source, &options);
+        }
+    }
+    for err in tokenize_errors.iter() {
+        fixes.push(Diagnostic::warning(
+            err.span,
+            format!("removed {}", err.found),
+        ));
+    }
+
+    let token_list = fix_tokens(&source, token_list, &mut fixes);
+    let skipped = tokenize_errors
+        .iter()
+        .map(|err| err.span.start.offset)
+        .collect();
+    let (value, errors) = recover::parse_tokens_recovering(&token_list, skipped, &options);
+    let value = match value {
+        Some(value) => value,
+        None => return Err(Error::Parse(errors.into_iter().next().unwrap())),
+    };
+    // 値の後ろの文章は一つの修正として報告する
+    if let Some(end) = errors
+        .iter()
+        .find(|err| err.kind == ParseErrorKind::StrayToken && err.expected == "end of input")
+    {
+        fixes.retain(|fix| fix.span.start.offset < end.span.start.offset);
+    }
+    for err in errors.iter() {
+        fixes.push(Diagnostic::warning(err.span, describe(err)));
+    }
+    fixes.sort_by_key(|fix| fix.span.start.offset);
+    return Ok(Repaired { value, fixes });
+}
+
+/// Replaces lines starting with ```` ``` ```` by spaces, so that offsets
+/// into the input stay the same.
+fn blank_code_fences(source: &mut [u8], fixes: &mut Vec<Diagnostic>) {
+    let mut start = 0;
+    while start < source.len() {
+        let end = source[start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(source.len(), |i| start + i);
+        let line = &source[start..end];
+        let indent = line.iter().take_while(|byte| **byte == b' ').count();
+        if line[indent..].starts_with(b"```") {
+            fixes.push(Diagnostic::warning(
+                span_of(source, start + indent, end),
+                "removed a Markdown code fence".to_string(),
+            ));
+            blank(&mut source[start..end]);
+        }
+        start = end + 1;
+    }
+}
+
+/// Replaces the text before the first bracket by spaces when the input
+/// starts with a word other than a literal, as in `Here you go: {...}`.
+fn blank_prose(source: &mut [u8], fixes: &mut Vec<Diagnostic>) {
+    let start = match source.iter().position(|byte| !byte.is_ascii_whitespace()) {
+        Some(start) => start,
+        None => return,
+    };
+    let word_end = source[start..]
+        .iter()
+        .position(|byte| !byte.is_ascii_alphanumeric() && *byte != b'_')
+        .map_or(source.len(), |i| start + i);
+    let word = &source[start..word_end];
+    let literals: [&[u8]; 9] = [
+        b"true",
+        b"false",
+        b"null",
+        b"True",
+        b"False",
+        b"None",
+        b"undefined",
+        b"NaN",
+        b"Infinity",
+    ];
+    if !source[start].is_ascii_alphabetic() || literals.contains(&word) {
+        return;
+    }
+    if let Some(end) = source
+        .iter()
+        .position(|byte| *byte == b'{' || *byte == b'[')
+    {
+        fixes.push(Diagnostic::warning(
+            span_of(source, start, end),
+            "removed text before the document".to_string(),
+        ));
+        blank(&mut source[start..end]);
+    }
+}
+
+/// Replaces everything but line breaks by spaces.
+fn blank(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut().filter(|byte| **byte != b'\n') {
+        *byte = b' ';
+    }
+}
+
+/// Drops comments and rewrites the tokens JSON does not have into ones it
+/// does.
+fn fix_tokens<'a>(
+    source: &[u8],
+    token_list: Vec<Token<'a>>,
+    fixes: &mut Vec<Diagnostic>,
+) -> Vec<Token<'a>> {
+    let mut fixed: Vec<Token> = Vec::with_capacity(token_list.len());
+    for (i, token) in token_list.iter().enumerate() {
+        // 次の字句がコロンならキー
+        let is_key = token_list[i + 1..]
+            .iter()
+            .find(|next| !matches!(next.kind, TokenKind::Comment(_)))
+            .is_some_and(|next| next.kind == TokenKind::Colorn);
+        let mut fix = |message: String| fixes.push(Diagnostic::warning(token.span, message));
+        let kind = match &token.kind {
+            TokenKind::Comment(_) => {
+                fix("removed a comment".to_string());
+                continue;
+            }
+            TokenKind::Str(_) if source[token.span.start.offset] == b'\'' => {
+                fix("replaced single quotes with double quotes".to_string());
+                token.kind.clone()
+            }
+            TokenKind::Ident(name) if is_key => {
+                fix(format!("quoted the key {}", name));
+                token.kind.clone()
+            }
+            TokenKind::Bool(_) | TokenKind::Null | TokenKind::Num(_) if is_key => {
+                fix(format!("quoted the key {}", source_text(source, token)));
+                token.kind.clone()
+            }
+            TokenKind::Ident(name) => match name.as_ref() {
+                "True" | "False" => {
+                    let b = name == "True";
+                    fix(format!("replaced {} with {}", name, b));
+                    TokenKind::Bool(b)
+                }
+                "None" | "undefined" => {
+                    fix(format!("replaced {} with null", name));
+                    TokenKind::Null
+                }
+                _ => {
+                    fix(format!("quoted {}", name));
+                    TokenKind::Str(name.clone())
+                }
+            },
+            TokenKind::Num(lexeme) => match json5::number_to_json(lexeme) {
+                Some(number) if number == *lexeme => token.kind.clone(),
+                Some(number) => {
+                    fix(format!("rewrote {} as {}", lexeme, number));
+                    TokenKind::Num(Cow::Owned(number))
+                }
+                None => {
+                    fix(format!("replaced {} with null", lexeme));
+                    TokenKind::Null
+                }
+            },
+            kind => kind.clone(),
+        };
+        fixed.push(Token {
+            kind,
+            span: token.span,
+        });
+    }
+    return fixed;
+}
+
+fn source_text<'a>(source: &'a [u8], token: &Token) -> Cow<'a, str> {
+    return String::from_utf8_lossy(&source[token.span.start.offset..token.span.end.offset]);
+}
+
+/// Says how the recovering parser got past `err`.
+fn describe(err: &ParseError) -> String {
+    let at_punctuation = err.found.starts_with('\'') || err.found == "end of input";
+    match err.kind {
+        ParseErrorKind::MissingComma => return "inserted ','".to_string(),
+        ParseErrorKind::MissingColon if at_punctuation => {
+            return "removed a key with no value".to_string();
+        }
+        ParseErrorKind::MissingColon => return "inserted ':'".to_string(),
+        ParseErrorKind::TrailingComma => return "removed a trailing ','".to_string(),
+        ParseErrorKind::Unclosed => return format!("inserted {}", err.expected),
+        ParseErrorKind::UnexpectedEof => return "removed a member with no value".to_string(),
+        ParseErrorKind::IsNotString => {
+            return format!("removed a member whose key is {}", err.found);
+        }
+        ParseErrorKind::StrayToken if err.expected == "end of input" => {
+            return "removed text after the document".to_string();
+        }
+        _ => return format!("removed {}", err.found),
+    }
+}
+
+/// The span of `source[start..end]`, counting lines and columns from the
+/// start of the input.
+fn span_of(source: &[u8], start: usize, end: usize) -> Span {
+    let position = |offset: usize| {
+        let before = &source[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |i| i + 1);
+        return Position {
+            offset,
+            line: before.iter().filter(|byte| **byte == b'\n').count() + 1,
+            column: String::from_utf8_lossy(&before[line_start..])
+                .chars()
+                .count()
+                + 1,
+        };
+    };
+    return Span {
+        start: position(start),
+        end: position(end),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{self, Node};
+
+    fn repaired(source: &str) -> (String, Vec<String>) {
+        let repaired = repair(source.as_bytes()).unwrap();
+        let fixes = repaired
+            .fixes
+            .iter()
+            .map(|fix| {
+                let start = fix.span.start;
+                return format!("{}:{}: {}", start.line, start.column, fix.message);
+            })
+            .collect();
+        let output = repaired.value.print_node();
+        repaired.value.dispose();
+        return (output, fixes);
+    }
+
+    #[test]
+    fn test_repair_valid() {
+        let source = r#"{"a": [1, 2.5, "x"], "b": {"c": null}}"#;
+        let repaired = repair(source.as_bytes()).unwrap();
+        assert_eq!(repaired.value, parser::parse_str(source).unwrap());
+        assert!(repaired.fixes.is_empty());
+    }
+
+    #[test]
+    fn test_repair_python_and_quotes() {
+        let (value, fixes) = repaired("{'name': 'x', ok: True, missing: None, list: [1 2,]}");
+        assert_eq!(
+            value,
+            r#"{"name":"x","ok":true,"missing":null,"list":[1,2]}"#
+        );
+        assert_eq!(
+            fixes,
+            vec![
+                "1:2: replaced single quotes with double quotes",
+                "1:10: replaced single quotes with double quotes",
+                "1:15: quoted the key ok",
+                "1:19: replaced True with true",
+                "1:25: quoted the key missing",
+                "1:34: replaced None with null",
+                "1:40: quoted the key list",
+                "1:49: inserted ','",
+                "1:51: removed a trailing ','",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repair_truncated() {
+        let (value, fixes) = repaired("{\"a\": [1, 2], \"b\": {\"c\": \"unfinished");
+        assert_eq!(value, r#"{"a":[1,2],"b":{"c":"unfinished"}}"#);
+        assert_eq!(
+            fixes,
+            vec![
+                "1:37: closed a string cut off by the end of the input",
+                "1:38: inserted '}' to close the '{' at 1:20",
+                "1:38: inserted '}' to close the '{' at 1:1",
+            ]
+        );
+
+        let (value, fixes) = repaired("[{\"a\": 1, \"b\":");
+        assert_eq!(value, r#"[{"a":1}]"#);
+        assert_eq!(fixes[0], "1:15: removed a member with no value");
+    }
+
+    #[test]
+    fn test_repair_llm_output() {
+        let source = "Here is the data:\n```json\n{\n  // the answer\n  \"n\": 0x10, \"x\": NaN, \"s\": hello\n}\n```\nHope this helps!";
+        let (value, fixes) = repaired(source);
+        assert_eq!(value, r#"{"n":16,"x":null,"s":"hello"}"#);
+        assert_eq!(
+            fixes,
+            vec![
+                "1:1: removed text before the document",
+                "2:1: removed a Markdown code fence",
+                "4:3: removed a comment",
+                "5:8: rewrote 0x10 as 16",
+                "5:19: replaced NaN with null",
+                "5:29: quoted hello",
+                "7:1: removed a Markdown code fence",
+                "8:1: removed text after the document",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repair_fails_without_value() {
+        assert!(repair(b"").is_err());
+        assert!(repair(b"  ]").is_err());
+        assert_eq!(repaired("None").0, "null");
+    }
+
+    #[test]
+    fn test_repair_deep_nesting() {
+        let depth = 100_000;
+        let (output, fixes) = repaired(&format!("{}True{}", "[".repeat(depth), "]".repeat(depth)));
+        assert_eq!(
+            output,
+            format!("{}true{}", "[".repeat(depth), "]".repeat(depth))
+        );
+        assert_eq!(fixes.len(), 1);
+    }
+}
@@ -0,0 +1,219 @@
+//! JSON text sequences (RFC 7464): each document follows a record separator,
+//! U+001E, and ends with a line feed.
+
+use std::io::{BufRead, Write};
+
+use super::error::Error;
+use super::format;
+use super::options::{FormatOptions, ParseOptions};
+use super::parser::{self, ParseError, ParseErrorKind};
+use super::stream::StreamError;
+use super::tokenizer::{Position, Span};
+use super::value::Value;
+
+/// The record separator that starts each text.
+pub const RS: u8 = 0x1e;
+
+/// Reads the documents of a JSON text sequence one at a time.
+pub struct SequenceReader<R> {
+    reader: R,
+    options: ParseOptions,
+    buf: Vec<u8>,
+    /// 次のテキストの先頭の位置
+    position: Position,
+    /// 直前に読んだテキストが始まる行
+    line: usize,
+}
+
+impl<R: BufRead> SequenceReader<R> {
+    pub fn new(reader: R) -> SequenceReader<R> {
+        return SequenceReader::with_options(reader, ParseOptions::default());
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> SequenceReader<R> {
+        SequenceReader {
+            reader,
+            options,
+            buf: Vec::new(),
+            position: Position::default(),
+            line: 0,
+        }
+    }
+
+    /// The line the text last read began on.
+    pub fn line(&self) -> usize {
+        return self.line;
+    }
+
+    /// Reads the next text, or returns `None` at the end of the input.
+    /// Empty texts are skipped, and text before the first separator is read
+    /// as a document too. A text that does not parse gives a
+    /// `StreamError::Syntax` whose positions count from the start of the
+    /// input; reading can go on with the next text.
+    pub fn next_value(&mut self) -> Result<Option<Value>, StreamError> {
+        loop {
+            self.buf.clear();
+            let n = self.reader.read_until(RS, &mut self.buf)?;
+            if n == 0 {
+                return Ok(None);
+            }
+            let start = self.position;
+            self.position = advance(start, &self.buf);
+            let text = self.buf.strip_suffix(&[RS]).unwrap_or(&self.buf);
+            let blank = text
+                .iter()
+                .position(|byte| !byte.is_ascii_whitespace())
+                .unwrap_or(text.len());
+            if blank == text.len() {
+                continue;
+            }
+            self.line = advance(start, &text[..blank]).line;
+            let value = parser::parse_bytes_with(text, &self.options)
+                .map_err(|err| StreamError::Syntax(err.offset_by(start)))?;
+            // 空白で終わらない数は途中で切れているかもしれない
+            if matches!(value, Value::Num(_)) && !text.ends_with(b"\n") {
+                let end = advance(start, text);
+                return Err(StreamError::Syntax(Error::Parse(ParseError {
+                    kind: ParseErrorKind::UnexpectedEof,
+                    span: Span { start: end, end },
+                    expected: "a line feed after a number".to_string(),
+                    found: "the end of the text".to_string(),
+                })));
+            }
+            return Ok(Some(value));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SequenceReader<R> {
+    type Item = Result<Value, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.next_value().transpose();
+    }
+}
+
+/// The position just past `bytes` when they begin at `start`.
+fn advance(start: Position, bytes: &[u8]) -> Position {
+    let mut position = start;
+    position.offset += bytes.len();
+    for byte in bytes.iter() {
+        if *byte == b'\n' {
+            position.line += 1;
+            position.column = 1;
+        } else if *byte & 0xc0 != 0x80 {
+            // UTF-8 の継続バイトは数えない
+            position.column += 1;
+        }
+    }
+    return position;
+}
+
+/// Formats `value` as one text of a sequence: the record separator, the
+/// value laid out as `options` say, and a line feed.
+pub fn format_text(value: &Value, options: &FormatOptions) -> String {
+    let options = FormatOptions {
+        trailing_newline: true,
+        ..options.clone()
+    };
+    let mut text = String::from(RS as char);
+    text.push_str(&format::format_value(value, &options));
+    return text;
+}
+
+/// Writes `value` as one text of a sequence.
+pub fn write_text<W: Write>(
+    writer: &mut W,
+    value: &Value,
+    options: &FormatOptions,
+) -> std::io::Result<()> {
+    return writer.write_all(format_text(value, options).as_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(source: &str) -> Vec<Result<String, String>> {
+        return SequenceReader::new(source.as_bytes())
+            .map(|result| match result {
+                Ok(value) => Ok(parser::Node::print_node(&value)),
+                Err(StreamError::Syntax(err)) => {
+                    let start = err.span().start;
+                    return Err(format!("{}:{}: {}", start.line, start.column, err));
+                }
+                Err(StreamError::Io(err)) => Err(err.to_string()),
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_sequence_reader() {
+        assert_eq!(
+            read("\u{1e}{\"a\": 1}\n\u{1e}[\n  1,\n  2\n]\n\u{1e}\u{1e}\"x\"\n"),
+            vec![
+                Ok(r#"{"a":1}"#.to_string()),
+                Ok("[1,2]".to_string()),
+                Ok(r#""x""#.to_string()),
+            ]
+        );
+        assert!(read("").is_empty());
+        assert!(read("\u{1e}\n\u{1e}").is_empty());
+        assert_eq!(
+            read("true\n\u{1e}1\n"),
+            vec![Ok("true".to_string()), Ok("1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_sequence_reader_errors() {
+        assert_eq!(
+            read("\u{1e}[1,\n2 3]\n\u{1e}null\n"),
+            vec![
+                Err("2:3: expected ',' or ']', found number 3".to_string()),
+                Ok("null".to_string()),
+            ]
+        );
+        assert_eq!(
+            read("\u{1e}{\"a\" 1}\n"),
+            vec![Err("1:7: expected ':', found number 1".to_string())]
+        );
+        // 改行で終わらない数は切れているとみなす
+        assert_eq!(
+            read("\u{1e}12\u{1e}[1]\n\u{1e}3"),
+            vec![
+                Err(
+                    "1:4: expected a line feed after a number, found the end of the text"
+                        .to_string()
+                ),
+                Ok("[1]".to_string()),
+                Err(
+                    "2:3: expected a line feed after a number, found the end of the text"
+                        .to_string()
+                ),
+            ]
+        );
+
+        let mut texts = SequenceReader::new("\u{1e}1\n\n\u{1e}\n\n  2\n".as_bytes());
+        texts.next_value().unwrap();
+        assert_eq!(texts.line(), 1);
+        texts.next_value().unwrap();
+        assert_eq!(texts.line(), 5);
+    }
+
+    #[test]
+    fn test_format_text() {
+        let value = parser::parse_str(r#"{"a": [1]}"#).unwrap();
+        let options = FormatOptions {
+            trailing_newline: false,
+            ..FormatOptions::default()
+        };
+        let mut out = Vec::new();
+        write_text(&mut out, &value, &options).unwrap();
+        write_text(&mut out, &Value::Null, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\u{1e}{\n  \"a\": [\n    1\n  ]\n}\n\u{1e}null\n"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::cmp::Ordering;
+
+use super::options::KeyOrder;
+
+/// Compares two object keys in the given order.
+pub fn compare_keys(a: &str, b: &str, order: KeyOrder) -> Ordering {
+    match order {
+        KeyOrder::CodePoint => return a.cmp(b),
+        KeyOrder::Natural => return natural_cmp(a, b),
+        KeyOrder::Utf16 => return a.encode_utf16().cmp(b.encode_utf16()),
+    }
+}
+
+/// Compares runs of ASCII digits by their numeric value and everything else
+/// by code point, so `item2` comes before `item10`. Numbers that are equal
+/// but written with different leading zeros fall back to code point order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a, b);
+    while !x.is_empty() && !y.is_empty() {
+        let (x_digits, y_digits) = (digit_run(x), digit_run(y));
+        if x_digits > 0 && y_digits > 0 {
+            let (x_run, y_run) = (&x[..x_digits], &y[..y_digits]);
+            let x_value = x_run.trim_start_matches('0');
+            let y_value = y_run.trim_start_matches('0');
+            // 先頭の 0 を除いた桁数、次に並びで比べる
+            let ordering = x_value.len().cmp(&y_value.len()).then(x_value.cmp(y_value));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            x = &x[x_digits..];
+            y = &y[y_digits..];
+            continue;
+        }
+        let x_char = x.chars().next().unwrap();
+        let y_char = y.chars().next().unwrap();
+        if x_char != y_char {
+            return x_char.cmp(&y_char);
+        }
+        x = &x[x_char.len_utf8()..];
+        y = &y[y_char.len_utf8()..];
+    }
+    return x.len().cmp(&y.len()).then(a.cmp(b));
+}
+
+fn digit_run(s: &str) -> usize {
+    return s.bytes().take_while(|b| b.is_ascii_digit()).count();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare_keys() {
+        let sorted = |order: KeyOrder, keys: &[&str]| -> Vec<String> {
+            let mut keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+            keys.sort_by(|a, b| compare_keys(a, b, order));
+            return keys;
+        };
+        let keys = [
+            "item10", "item2", "Item1", "item", "é", "z", "item02", "a1b10", "a1b9",
+        ];
+        assert_eq!(
+            sorted(KeyOrder::CodePoint, &keys),
+            vec!["Item1", "a1b10", "a1b9", "item", "item02", "item10", "item2", "z", "é"]
+        );
+        assert_eq!(
+            sorted(KeyOrder::Natural, &keys),
+            vec!["Item1", "a1b9", "a1b10", "item", "item02", "item2", "item10", "z", "é"]
+        );
+    }
+
+    #[test]
+    fn test_compare_keys_utf16() {
+        // U+FB33 は UTF-16 ではサロゲートペアの U+1F600 より後ろ
+        assert_eq!(
+            compare_keys("\u{fb33}", "😀", KeyOrder::CodePoint),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_keys("\u{fb33}", "😀", KeyOrder::Utf16),
+            Ordering::Greater
+        );
+        assert_eq!(compare_keys("a", "ab", KeyOrder::Utf16), Ordering::Less);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("a2", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("a10", "a10"), Ordering::Equal);
+        assert_eq!(natural_cmp("a010", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("10", "9x"), Ordering::Greater);
+        assert_eq!(natural_cmp("x", "x1"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,1217 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::color::Style;
+use super::error::Error;
+use super::escape;
+use super::format;
+use super::number::{Number, NumberTokenizer};
+use super::options::{FormatOptions, ParseOptions};
+use super::parser::{ParseError, ParseErrorKind};
+use super::tokenizer::{Position, Span, TokenizeError, TokenizeErrorKind};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// One step of a document, in the order it appears in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    Str(String),
+    Num(String),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Syntax(Error),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(err) => err.fmt(f),
+            StreamError::Syntax(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+impl From<io::Error> for StreamError {
+    fn from(err: io::Error) -> StreamError {
+        StreamError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Root,
+    ValueOrEnd,
+    KeyOrEnd,
+    Key,
+    Colon,
+    Value,
+    CommaOrEnd,
+    Done,
+}
+
+/// Pulls `Event`s out of any `io::Read`.
+///
+/// Input is read in fixed-size chunks, so memory use is bounded by the
+/// nesting depth and the longest single string or number, never by the size
+/// of the document. With `max_bytes` set, no more than one byte past it is
+/// read at all.
+pub struct EventReader<R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+    position: Position,
+    stack: Vec<Container>,
+    /// 開いているコンテナごとの、読み始めたメンバーの数
+    members: Vec<usize>,
+    state: State,
+    options: ParseOptions,
+    /// `reader` から読んだバイト数
+    read: usize,
+    /// `max_bytes` の次の一バイトまで読み、それより先を求められたか
+    capped: bool,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        return EventReader::with_options(reader, ParseOptions::default());
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> EventReader<R> {
+        EventReader {
+            reader,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            pos: 0,
+            position: Position::default(),
+            stack: Vec::new(),
+            members: Vec::new(),
+            state: State::Root,
+            options,
+            read: 0,
+            capped: false,
+        }
+    }
+
+    /// Current nesting depth, i.e. the number of open objects and arrays.
+    pub fn depth(&self) -> usize {
+        return self.stack.len();
+    }
+
+    /// Returns the next event, or `None` once the root value is complete and
+    /// only whitespace is left.
+    pub fn next_event(&mut self) -> Result<Option<Event>, StreamError> {
+        let event = self.read_event();
+        // 上限より先は読んでいないので、そこで見つかった誤りより優先する
+        let limit = self.options.limits.max_bytes;
+        if let Some(max) = limit.filter(|max| self.position.offset > *max || self.capped) {
+            return Err(self.tokenize_error(
+                TokenizeErrorKind::TooLarge,
+                format!("more than {} bytes", max),
+            ));
+        }
+        return event;
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, StreamError> {
+        if self.position.offset == 0 && !self.options.strict {
+            self.skip_bom()?;
+        }
+        loop {
+            self.skip_whitespace()?;
+            let byte = self.peek()?;
+            match self.state {
+                State::Done => {
+                    if byte.is_none() {
+                        return Ok(None);
+                    }
+                    return Err(self.unexpected("end of input")?);
+                }
+                State::Root | State::Value | State::ValueOrEnd => {
+                    if self.state == State::ValueOrEnd && byte == Some(b']') {
+                        return Ok(Some(self.close()?));
+                    }
+                    // オブジェクトのメンバーはキーのところで数える
+                    if self.stack.last() == Some(&Container::Array) {
+                        self.begin_member("elements")?;
+                    }
+                    return Ok(Some(self.read_value()?));
+                }
+                State::KeyOrEnd | State::Key => {
+                    if self.state == State::KeyOrEnd && byte == Some(b'}') {
+                        return Ok(Some(self.close()?));
+                    }
+                    if byte != Some(b'"') {
+                        let expected = if self.state == State::KeyOrEnd {
+                            "string or '}'"
+                        } else {
+                            "string"
+                        };
+                        return Err(self.unexpected(expected)?);
+                    }
+                    self.begin_member("members")?;
+                    let key = self.read_string()?;
+                    self.state = State::Colon;
+                    return Ok(Some(Event::Key(key)));
+                }
+                State::Colon => {
+                    if byte != Some(b':') {
+                        return Err(self.unexpected("':'")?);
+                    }
+                    self.bump();
+                    self.state = State::Value;
+                }
+                State::CommaOrEnd => {
+                    let container = *self.stack.last().unwrap();
+                    match (byte, container) {
+                        (Some(b','), Container::Object) => {
+                            self.bump();
+                            self.state = if self.options.trailing_commas {
+                                State::KeyOrEnd
+                            } else {
+                                State::Key
+                            };
+                        }
+                        (Some(b','), Container::Array) => {
+                            self.bump();
+                            self.state = if self.options.trailing_commas {
+                                State::ValueOrEnd
+                            } else {
+                                State::Value
+                            };
+                        }
+                        (Some(b'}'), Container::Object) | (Some(b']'), Container::Array) => {
+                            return Ok(Some(self.close()?));
+                        }
+                        (_, Container::Object) => return Err(self.unexpected("',' or '}'")?),
+                        (_, Container::Array) => return Err(self.unexpected("',' or ']'")?),
+                    }
+                }
+            }
+        }
+    }
+
+    fn lossy_strings(&self) -> bool {
+        return self.options.lossy_strings && !self.options.strict;
+    }
+
+    /// Counts a member or element of the innermost container and checks it
+    /// against `max_members`.
+    fn begin_member(&mut self, noun: &str) -> Result<(), StreamError> {
+        let count = self.members.last_mut().unwrap();
+        *count += 1;
+        let count = *count;
+        if let Some(max) = self.options.limits.max_members.filter(|max| count > *max) {
+            let expected = format!("at most {} {}", max, noun);
+            return Err(self.limit_error(ParseErrorKind::TooManyMembers, &expected)?);
+        }
+        return Ok(());
+    }
+
+    fn after_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::CommaOrEnd
+        };
+    }
+
+    fn close(&mut self) -> Result<Event, StreamError> {
+        self.bump();
+        let container = self.stack.pop().unwrap();
+        self.members.pop();
+        self.after_value();
+        match container {
+            Container::Object => return Ok(Event::EndObject),
+            Container::Array => return Ok(Event::EndArray),
+        }
+    }
+
+    fn read_value(&mut self) -> Result<Event, StreamError> {
+        let depth = self.stack.len();
+        if let Some(max) = self.options.limits.max_depth.filter(|max| depth >= *max) {
+            if matches!(self.peek()?, Some(b'{' | b'[')) {
+                let expected = format!("at most {} levels of nesting", max);
+                return Err(self.limit_error(ParseErrorKind::TooDeep, &expected)?);
+            }
+        }
+        let event = match self.peek()? {
+            Some(b'{') => {
+                self.bump();
+                self.stack.push(Container::Object);
+                self.members.push(0);
+                self.state = State::KeyOrEnd;
+                return Ok(Event::StartObject);
+            }
+            Some(b'[') => {
+                self.bump();
+                self.stack.push(Container::Array);
+                self.members.push(0);
+                self.state = State::ValueOrEnd;
+                return Ok(Event::StartArray);
+            }
+            Some(b'"') => Event::Str(self.read_string()?),
+            Some(b't') => {
+                self.read_literal("true", TokenizeErrorKind::NotTrue)?;
+                Event::Bool(true)
+            }
+            Some(b'f') => {
+                self.read_literal("false", TokenizeErrorKind::NotFalse)?;
+                Event::Bool(false)
+            }
+            Some(b'n') => {
+                self.read_literal("null", TokenizeErrorKind::NotNull)?;
+                Event::Null
+            }
+            Some(b'-' | b'0'..=b'9') => Event::Num(self.read_number()?),
+            Some(b'}' | b']' | b',' | b':') | None => return Err(self.unexpected("a value")?),
+            Some(_) => {
+                let found = self.describe()?;
+                return Err(self.tokenize_error(TokenizeErrorKind::InvalidCharactar, found));
+            }
+        };
+        self.after_value();
+        return Ok(event);
+    }
+
+    fn read_literal(&mut self, literal: &str, kind: TokenizeErrorKind) -> Result<(), StreamError> {
+        for expected in literal.bytes() {
+            if self.peek()? != Some(expected) {
+                let found = self.describe()?;
+                return Err(self.tokenize_error(kind, found));
+            }
+            self.bump();
+        }
+        return Ok(());
+    }
+
+    fn read_number(&mut self) -> Result<String, StreamError> {
+        let start = self.position;
+        let mut buf = Vec::new();
+        while let Some(byte @ (b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) = self.peek()? {
+            buf.push(byte);
+            self.bump();
+            let limit = self.options.limits.max_number_length;
+            if let Some(max) = limit.filter(|max| buf.len() > *max) {
+                return Err(self.length_error(
+                    TokenizeErrorKind::NumberTooLong,
+                    start,
+                    format!("a number of more than {} characters", max),
+                ));
+            }
+        }
+        // 数値の文法は tokenizer と同じ状態遷移で確かめる
+        let mut num_tokenizer = NumberTokenizer::new();
+        let mut count = 0;
+        let lexeme = num_tokenizer.tokenize(&buf, &mut count).to_string();
+        if count != buf.len() || !num_tokenizer.is_complete() {
+            let found = match buf.get(count) {
+                Some(byte) => format!("{:?}", *byte as char),
+                None => self.describe()?,
+            };
+            return Err(self.tokenize_error(TokenizeErrorKind::InvalidNumber, found));
+        }
+        return Ok(lexeme);
+    }
+
+    fn read_string(&mut self) -> Result<String, StreamError> {
+        let start = self.position;
+        let mut bytes: Vec<u8> = Vec::new();
+        self.bump();
+        loop {
+            let limit = self.options.limits.max_string_length;
+            if let Some(max) = limit.filter(|max| bytes.len() > *max) {
+                return Err(self.length_error(
+                    TokenizeErrorKind::StringTooLong,
+                    start,
+                    format!("a string of more than {} bytes", max),
+                ));
+            }
+            match self.peek()? {
+                None => {
+                    return Err(self.tokenize_error(
+                        TokenizeErrorKind::UnterminatedString,
+                        "end of input".to_string(),
+                    ))
+                }
+                Some(b'"') => {
+                    self.bump();
+                    break;
+                }
+                Some(b'\\') => {
+                    self.bump();
+                    self.read_escape(&mut bytes)?;
+                }
+                Some(byte) if byte < 0x20 && !self.lossy_strings() => {
+                    let found = self.describe()?;
+                    return Err(self.tokenize_error(TokenizeErrorKind::ControlCharacter, found));
+                }
+                Some(byte) => {
+                    bytes.push(byte);
+                    self.bump();
+                }
+            }
+        }
+        match String::from_utf8(bytes) {
+            Ok(string) => return Ok(string),
+            Err(_) => {
+                return Err(self
+                    .tokenize_error(TokenizeErrorKind::InvalidUtf8, "invalid UTF-8".to_string()))
+            }
+        }
+    }
+
+    fn read_escape(&mut self, bytes: &mut Vec<u8>) -> Result<(), StreamError> {
+        let ch = match self.peek()? {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.bump();
+                let high = self.read_hex4()?;
+                let ch = if (0xD800..=0xDBFF).contains(&high) {
+                    // 上位サロゲートの直後には下位サロゲートの \uXXXX が続く必要がある
+                    self.fill(2)?;
+                    if self.buf[self.pos..].starts_with(b"\\u") {
+                        self.fill(6)?;
+                        let low = self
+                            .buf
+                            .get(self.pos + 2..self.pos + 6)
+                            .and_then(|hex| std::str::from_utf8(hex).ok())
+                            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+                        match low {
+                            Some(low) if (0xDC00..=0xDFFF).contains(&low) => {
+                                for _ in 0..6 {
+                                    self.bump();
+                                }
+                                char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                            }
+                            _ => None,
+                        }
+                    } else {
+                        None
+                    }
+                } else {
+                    char::from_u32(high)
+                };
+                match ch {
+                    Some(ch) => push_char(bytes, ch),
+                    None if self.lossy_strings() => push_char(bytes, char::REPLACEMENT_CHARACTER),
+                    None => {
+                        return Err(self.tokenize_error(
+                            TokenizeErrorKind::LoneSurrogate,
+                            format!("\\u{:04x}", high),
+                        ))
+                    }
+                }
+                return Ok(());
+            }
+            Some(_) => {
+                let found = self.describe()?;
+                return Err(self.tokenize_error(TokenizeErrorKind::InvalidString, found));
+            }
+            None => {
+                return Err(self.tokenize_error(
+                    TokenizeErrorKind::UnterminatedString,
+                    "end of input".to_string(),
+                ))
+            }
+        };
+        push_char(bytes, ch);
+        self.bump();
+        return Ok(());
+    }
+
+    fn read_hex4(&mut self) -> Result<u32, StreamError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.peek()? {
+                Some(byte) if byte.is_ascii_hexdigit() => {
+                    code = code * 16 + (byte as char).to_digit(16).unwrap();
+                    self.bump();
+                }
+                _ => {
+                    let found = self.describe()?;
+                    return Err(self.tokenize_error(TokenizeErrorKind::InvalidString, found));
+                }
+            }
+        }
+        return Ok(code);
+    }
+
+    fn skip_bom(&mut self) -> Result<(), StreamError> {
+        self.fill(3)?;
+        if self.buf[self.pos..].starts_with("\u{feff}".as_bytes()) {
+            for _ in 0..3 {
+                self.bump();
+            }
+        }
+        return Ok(());
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), StreamError> {
+        loop {
+            match self.peek()? {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.bump(),
+                Some(byte) if byte >= 0x80 && !self.options.strict => match self.peek_char()? {
+                    Some(ch) if ch.is_whitespace() => {
+                        for _ in 0..ch.len_utf8() {
+                            self.bump();
+                        }
+                    }
+                    _ => return Ok(()),
+                },
+                Some(0x0b | 0x0c) if !self.options.strict => self.bump(),
+                Some(b'/') if self.options.comments => {
+                    if !self.skip_comment()? {
+                        return Ok(());
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips a `//` or `/* */` comment starting at `/`. Returns false,
+    /// consuming nothing, if the `/` does not start a comment.
+    fn skip_comment(&mut self) -> Result<bool, StreamError> {
+        self.fill(2)?;
+        match self.buf.get(self.pos + 1) {
+            Some(b'/') => {
+                while !matches!(self.peek()?, None | Some(b'\n')) {
+                    self.bump();
+                }
+            }
+            Some(b'*') => {
+                self.bump();
+                self.bump();
+                loop {
+                    self.fill(2)?;
+                    if self.buf[self.pos..].starts_with(b"*/") {
+                        self.bump();
+                        self.bump();
+                        break;
+                    }
+                    if self.peek()?.is_none() {
+                        let found = self.describe()?;
+                        return Err(
+                            self.tokenize_error(TokenizeErrorKind::UnterminatedComment, found)
+                        );
+                    }
+                    self.bump();
+                }
+            }
+            _ => return Ok(false),
+        }
+        return Ok(true);
+    }
+
+    /// Makes at least `n` bytes available from `pos` unless the input ends
+    /// first.
+    fn fill(&mut self, n: usize) -> io::Result<()> {
+        while self.buf.len() - self.pos < n {
+            if self.pos > 0 {
+                self.buf.drain(..self.pos);
+                self.pos = 0;
+            }
+            let len = self.buf.len();
+            self.buf.resize(len.max(CHUNK_SIZE), 0);
+            // max_bytes の次の一バイトより先は読まない
+            let end = match self.options.limits.max_bytes {
+                Some(max) => self
+                    .buf
+                    .len()
+                    .min(len + (max + 1).saturating_sub(self.read)),
+                None => self.buf.len(),
+            };
+            if end == len {
+                self.buf.truncate(len);
+                self.capped = true;
+                return Ok(());
+            }
+            let read = loop {
+                match self.reader.read(&mut self.buf[len..end]) {
+                    Ok(read) => break read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        self.buf.truncate(len);
+                        return Err(err);
+                    }
+                }
+            };
+            self.buf.truncate(len + read);
+            self.read += read;
+            if read == 0 {
+                return Ok(());
+            }
+        }
+        return Ok(());
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        if self.pos == self.buf.len() {
+            self.fill(1)?;
+        }
+        return Ok(self.buf.get(self.pos).copied());
+    }
+
+    fn peek_char(&mut self) -> io::Result<Option<char>> {
+        let width = match self.peek()? {
+            None => return Ok(None),
+            Some(byte) if byte < 0x80 => return Ok(Some(byte as char)),
+            Some(byte) if byte >= 0xF0 => 4,
+            Some(byte) if byte >= 0xE0 => 3,
+            Some(_) => 2,
+        };
+        self.fill(width)?;
+        let end = (self.pos + width).min(self.buf.len());
+        return Ok(std::str::from_utf8(&self.buf[self.pos..end])
+            .ok()
+            .and_then(|s| s.chars().next()));
+    }
+
+    fn bump(&mut self) {
+        let byte = self.buf[self.pos];
+        self.pos += 1;
+        self.position.offset += 1;
+        if byte == b'\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // UTF-8 の継続バイトは列として数えない
+            self.position.column += 1;
+        }
+    }
+
+    fn describe(&mut self) -> io::Result<String> {
+        match self.peek_char()? {
+            Some(ch) => return Ok(format!("{:?}", ch)),
+            None if self.peek()?.is_some() => return Ok("invalid UTF-8".to_string()),
+            None => return Ok("end of input".to_string()),
+        }
+    }
+
+    fn span(&mut self) -> Span {
+        let start = self.position;
+        let mut end = start;
+        end.offset += 1;
+        end.column += 1;
+        return Span { start, end };
+    }
+
+    fn tokenize_error(&mut self, kind: TokenizeErrorKind, found: String) -> StreamError {
+        return StreamError::Syntax(Error::Tokenize(TokenizeError {
+            kind,
+            span: self.span(),
+            found,
+        }));
+    }
+
+    /// An error for a string or number that started at `start` and has
+    /// grown past its limit.
+    fn length_error(
+        &mut self,
+        kind: TokenizeErrorKind,
+        start: Position,
+        found: String,
+    ) -> StreamError {
+        return StreamError::Syntax(Error::Tokenize(TokenizeError {
+            kind,
+            span: Span {
+                start,
+                end: self.position,
+            },
+            found,
+        }));
+    }
+
+    fn limit_error(
+        &mut self,
+        kind: ParseErrorKind,
+        expected: &str,
+    ) -> Result<StreamError, StreamError> {
+        let found = self.describe()?;
+        return Ok(StreamError::Syntax(Error::Parse(ParseError {
+            kind,
+            span: self.span(),
+            expected: expected.to_string(),
+            found,
+        })));
+    }
+
+    fn unexpected(&mut self, expected: &str) -> Result<StreamError, StreamError> {
+        let found = self.describe()?;
+        let kind = if self.peek()?.is_none() {
+            ParseErrorKind::UnexpectedEof
+        } else {
+            ParseErrorKind::UnexpectedToken
+        };
+        return Ok(StreamError::Syntax(Error::Parse(ParseError {
+            kind,
+            span: self.span(),
+            expected: expected.to_string(),
+            found,
+        })));
+    }
+}
+
+fn push_char(bytes: &mut Vec<u8>, ch: char) {
+    let mut buf = [0; 4];
+    bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.next_event().transpose();
+    }
+}
+
+/// Writes events to any `io::Write` in the same layout as `format_node`,
+/// without ever holding more than the current nesting path.
+///
+/// `inline_width` and `max_width` are ignored: deciding whether a container
+/// fits on one line would need the whole container in memory. For the same
+/// reason `sort_keys` is ignored. With `compact` the output matches
+/// `print_node` instead.
+pub struct StreamFormatter<W: Write> {
+    writer: W,
+    options: FormatOptions,
+    // 各コンテナに要素を書いたかどうか
+    stack: Vec<bool>,
+    after_key: bool,
+}
+
+impl<W: Write> StreamFormatter<W> {
+    pub fn new(writer: W, indent: &str) -> StreamFormatter<W> {
+        let options = FormatOptions {
+            indent: indent.to_string(),
+            ..FormatOptions::default()
+        };
+        return StreamFormatter::with_options(writer, options);
+    }
+
+    pub fn with_options(writer: W, options: FormatOptions) -> StreamFormatter<W> {
+        StreamFormatter {
+            writer,
+            options,
+            stack: Vec::new(),
+            after_key: false,
+        }
+    }
+
+    pub fn write_event(&mut self, event: &Event) -> io::Result<()> {
+        match event {
+            Event::StartObject => {
+                self.write_prefix()?;
+                self.write_styled(Style::Punct, "{")?;
+                self.stack.push(false);
+            }
+            Event::StartArray => {
+                self.write_prefix()?;
+                self.write_styled(Style::Punct, "[")?;
+                self.stack.push(false);
+            }
+            Event::EndObject | Event::EndArray => {
+                let has_members = self.stack.pop().unwrap_or(false);
+                if has_members && !self.options.compact {
+                    if self.options.trailing_commas {
+                        self.write_styled(Style::Punct, ",")?;
+                    }
+                    self.writer
+                        .write_all(self.options.newline.as_str().as_bytes())?;
+                    self.write_indent()?;
+                }
+                let close = if *event == Event::EndObject { "}" } else { "]" };
+                self.write_styled(Style::Punct, close)?;
+            }
+            Event::Key(key) => {
+                self.write_prefix()?;
+                self.write_styled(Style::Key, &escape::quote(key))?;
+                let compact = self.options.compact;
+                let mut colon = String::new();
+                if self.options.space_before_colon && !compact {
+                    colon.push(' ');
+                }
+                colon.push(':');
+                if self.options.space_after_colon && !compact {
+                    colon.push(' ');
+                }
+                self.write_styled(Style::Punct, &colon)?;
+                self.after_key = true;
+            }
+            Event::Str(string) => {
+                self.write_prefix()?;
+                self.write_styled(Style::Str, &escape::quote(string))?;
+            }
+            Event::Num(lexeme) => {
+                self.write_prefix()?;
+                let number = Number::from_lexeme(lexeme.clone());
+                self.write_styled(Style::Num, &format::number_text(&number, &self.options))?;
+            }
+            Event::Bool(b) => {
+                self.write_prefix()?;
+                self.write_styled(Style::Bool, if *b { "true" } else { "false" })?;
+            }
+            Event::Null => {
+                self.write_prefix()?;
+                self.write_styled(Style::Null, "null")?;
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+
+    /// Writes the separator and indentation that go before a member or
+    /// element.
+    fn write_prefix(&mut self) -> io::Result<()> {
+        if self.after_key {
+            self.after_key = false;
+            return Ok(());
+        }
+        if let Some(has_members) = self.stack.last_mut() {
+            if *has_members {
+                self.write_styled(Style::Punct, ",")?;
+            } else {
+                *has_members = true;
+            }
+            if self.options.compact {
+                return Ok(());
+            }
+            self.writer
+                .write_all(self.options.newline.as_str().as_bytes())?;
+            self.write_indent()?;
+        }
+        return Ok(());
+    }
+
+    fn write_styled(&mut self, style: Style, text: &str) -> io::Result<()> {
+        match &self.options.color {
+            Some(theme) => {
+                let mut buf = String::new();
+                theme.paint(style, text, &mut buf);
+                return self.writer.write_all(buf.as_bytes());
+            }
+            None => return self.writer.write_all(text.as_bytes()),
+        }
+    }
+
+    fn write_indent(&mut self) -> io::Result<()> {
+        for _ in 0..self.stack.len() {
+            self.writer.write_all(self.options.indent.as_bytes())?;
+        }
+        return Ok(());
+    }
+}
+
+/// Reads one document from `reader` and writes it formatted to `writer`.
+pub fn format_stream<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    format: &FormatOptions,
+    options: &ParseOptions,
+) -> Result<(), StreamError> {
+    let mut events = EventReader::with_options(reader, options.clone());
+    let mut formatter = StreamFormatter::with_options(writer, format.clone());
+    while let Some(event) = events.next_event()? {
+        formatter.write_event(&event)?;
+    }
+    if format.trailing_newline {
+        formatter
+            .writer
+            .write_all(format.newline.as_str().as_bytes())?;
+    }
+    formatter.flush()?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Theme;
+    use crate::format::format_value;
+    use crate::options::{Limits, Newline, NumberFormat};
+    use crate::parser::{self, Node};
+
+    /// Hands out at most `size` bytes per read to exercise chunk boundaries.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        size: usize,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.size.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            return Ok(n);
+        }
+    }
+
+    fn format(source: &str, size: usize) -> Result<String, StreamError> {
+        let mut out = Vec::new();
+        let reader = Trickle {
+            data: source.as_bytes(),
+            size,
+        };
+        format_stream(
+            reader,
+            &mut out,
+            &FormatOptions::default(),
+            &ParseOptions::default(),
+        )?;
+        return Ok(String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_events() {
+        let events = EventReader::new(r#"{"a": [1, "x\u00e9", true, null], "b": {}}"#.as_bytes())
+            .collect::<Result<Vec<Event>, StreamError>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartObject,
+                Event::Key("a".to_string()),
+                Event::StartArray,
+                Event::Num("1".to_string()),
+                Event::Str("xé".to_string()),
+                Event::Bool(true),
+                Event::Null,
+                Event::EndArray,
+                Event::Key("b".to_string()),
+                Event::StartObject,
+                Event::EndObject,
+                Event::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_stream_matches_format_node() {
+        let sources = [
+            r#"{"key": "value"}"#,
+            r#"{"a": [1, 2.5e-3, {"b": null, "c": []}], "d": {}, "e": "\ud83d\ude00 \"q\""}"#,
+            r#"[[], [[1]], {"x": {"y": {"z": false}}}]"#,
+            "\"plain\"",
+            "-0.5",
+            "\u{feff} { \"bom\" : true } ",
+        ];
+        for source in sources.iter() {
+            let expected = parser::parse_str(source).unwrap().format_node("  ", &mut 0) + "\n";
+            for size in [1, 3, CHUNK_SIZE] {
+                assert_eq!(format(source, size).unwrap(), expected, "{}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_stream_options() {
+        let format = FormatOptions {
+            indent: "\t".to_string(),
+            newline: Newline::CrLf,
+            space_before_colon: true,
+            space_after_colon: false,
+            trailing_newline: false,
+            inline_width: None,
+            max_width: None,
+            compact: false,
+            color: None,
+            sort_keys: None,
+            json5: false,
+            trailing_commas: false,
+            numbers: NumberFormat::normalized(),
+        };
+        let source = r#"{"a": [1.0, {"b": null}], "c": {}}"#;
+        let mut out = Vec::new();
+        format_stream(
+            source.as_bytes(),
+            &mut out,
+            &format,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format_value(&parser::parse_str(source).unwrap(), &format)
+        );
+    }
+
+    #[test]
+    fn test_format_stream_compact() {
+        let format = FormatOptions {
+            compact: true,
+            ..FormatOptions::default()
+        };
+        let source = "{ \"a\" : [1, {\"b\": null}, []], \"c\" : {} }";
+        let mut out = Vec::new();
+        format_stream(
+            source.as_bytes(),
+            &mut out,
+            &format,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            parser::parse_str(source).unwrap().print_node() + "\n"
+        );
+    }
+
+    #[test]
+    fn test_format_stream_color() {
+        let format = FormatOptions {
+            color: Some(Theme::parse("key=34,string=32,number=36,punct=").unwrap()),
+            ..FormatOptions::default()
+        };
+        let source = r#"{"a": ["x", 1]}"#;
+        let mut out = Vec::new();
+        format_stream(
+            source.as_bytes(),
+            &mut out,
+            &format,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format_value(&parser::parse_str(source).unwrap(), &format)
+        );
+    }
+
+    #[test]
+    fn test_stream_comments() {
+        let source = "/* head */ {\"a\": [1, // one\n 2], /* b */ \"b\": {}} // tail";
+        let expected = parser::parse_str_with(source, &ParseOptions::jsonc())
+            .unwrap()
+            .format_node("  ", &mut 0)
+            + "\n";
+        for size in [1, 3, CHUNK_SIZE] {
+            let mut out = Vec::new();
+            let reader = Trickle {
+                data: source.as_bytes(),
+                size,
+            };
+            format_stream(
+                reader,
+                &mut out,
+                &FormatOptions::default(),
+                &ParseOptions::jsonc(),
+            )
+            .unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+
+        let mut out = Vec::new();
+        let err = format_stream(
+            "[1] /* x".as_bytes(),
+            &mut out,
+            &FormatOptions::default(),
+            &ParseOptions::jsonc(),
+        );
+        match err {
+            Err(StreamError::Syntax(Error::Tokenize(err))) => {
+                assert_eq!(err.kind, TokenizeErrorKind::UnterminatedComment);
+            }
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+        assert!(format_stream(
+            "[1] // x".as_bytes(),
+            &mut out,
+            &FormatOptions::default(),
+            &ParseOptions::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stream_trailing_commas() {
+        let parse = ParseOptions {
+            trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let format = FormatOptions {
+            trailing_commas: true,
+            ..FormatOptions::default()
+        };
+        let mut out = Vec::new();
+        format_stream(
+            r#"{"a": [1, 2,], "b": {},}"#.as_bytes(),
+            &mut out,
+            &format,
+            &parse,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\n  \"a\": [\n    1,\n    2,\n  ],\n  \"b\": {},\n}\n"
+        );
+
+        let mut out = Vec::new();
+        assert!(format_stream("[1,,]".as_bytes(), &mut out, &format, &parse).is_err());
+        assert!(format_stream(
+            "[1,]".as_bytes(),
+            &mut out,
+            &format,
+            &ParseOptions::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let syntax = |source: &str| match format(source, 2) {
+            Err(StreamError::Syntax(err)) => err,
+            other => panic!("{:?} for {}", other.map(|_| ()), source),
+        };
+
+        match syntax("{\n  \"a\" 1\n}") {
+            Error::Parse(err) => {
+                assert_eq!(err.expected, "':'");
+                assert_eq!(err.found, "'1'");
+                assert_eq!(err.span.start.line, 2);
+                assert_eq!(err.span.start.column, 7);
+            }
+            err => panic!("{:?}", err),
+        }
+        match syntax("[1, 2") {
+            Error::Parse(err) => assert_eq!(err.kind, ParseErrorKind::UnexpectedEof),
+            err => panic!("{:?}", err),
+        }
+        match syntax("[1.]") {
+            Error::Tokenize(err) => assert_eq!(err.kind, TokenizeErrorKind::InvalidNumber),
+            err => panic!("{:?}", err),
+        }
+        match syntax("[\"\\ud83d\\ude00\"] x") {
+            Error::Parse(err) => assert_eq!(err.expected, "end of input"),
+            err => panic!("{:?}", err),
+        }
+        match syntax("[\"\\ud800\"]") {
+            Error::Tokenize(err) => assert_eq!(err.kind, TokenizeErrorKind::LoneSurrogate),
+            err => panic!("{:?}", err),
+        }
+        match syntax("[\"a\tb\"]") {
+            Error::Tokenize(err) => assert_eq!(err.kind, TokenizeErrorKind::ControlCharacter),
+            err => panic!("{:?}", err),
+        }
+        match syntax("[tru]") {
+            Error::Tokenize(err) => assert_eq!(err.kind, TokenizeErrorKind::NotTrue),
+            err => panic!("{:?}", err),
+        }
+        assert!(format("[]]", 1).is_err());
+        assert!(format("", 1).is_err());
+    }
+
+    #[test]
+    fn test_stream_limits() {
+        let read = |limits, source: &str| {
+            let options = ParseOptions {
+                limits,
+                ..ParseOptions::default()
+            };
+            let reader = Trickle {
+                data: source.as_bytes(),
+                size: 3,
+            };
+            for event in EventReader::with_options(reader, options) {
+                if let Err(StreamError::Syntax(err)) = event {
+                    let start = err.span().start;
+                    return Err(format!("{}:{} {}", start.line, start.column, err));
+                }
+            }
+            return Ok(());
+        };
+        let depth = Limits {
+            max_depth: Some(2),
+            ..Limits::default()
+        };
+        assert!(read(depth, r#"[{"a": 1}, [2]]"#).is_ok());
+        assert_eq!(
+            read(depth, r#"{"a": [[1]]}"#),
+            Err("1:8 expected at most 2 levels of nesting, found '['".to_string())
+        );
+        let members = Limits {
+            max_members: Some(2),
+            ..Limits::default()
+        };
+        assert!(read(members, r#"[[1, 2], {"a": [1], "b": 2}]"#).is_ok());
+        assert_eq!(
+            read(members, "[1, 2, 3]"),
+            Err("1:8 expected at most 2 elements, found '3'".to_string())
+        );
+        assert_eq!(
+            read(members, r#"{"a": 1, "b": 2, "c": 3}"#),
+            Err(r#"1:18 expected at most 2 members, found '"'"#.to_string())
+        );
+        let sizes = Limits {
+            max_bytes: Some(16),
+            max_string_length: Some(4),
+            max_number_length: Some(3),
+            ..Limits::default()
+        };
+        assert!(read(sizes, r#"["\u00e9ab",123]"#).is_ok());
+        assert_eq!(
+            read(sizes, r#"["abcde"]"#),
+            Err("1:2 expected a shorter string, found a string of more than 4 bytes".to_string())
+        );
+        assert_eq!(
+            read(sizes, "[-1.5]"),
+            Err(
+                "1:2 expected a shorter number, found a number of more than 3 characters"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            read(sizes, "[1, 2, 3, 4, 5, 6]"),
+            Err("1:18 expected a smaller input, found more than 16 bytes".to_string())
+        );
+        assert_eq!(
+            read(sizes, "[1, 2, 3, 4, \"\u{1F600}\"]"),
+            Err("1:16 expected a smaller input, found more than 16 bytes".to_string())
+        );
+
+        // 長い文字列も上限の次の一バイトまでしか読まない
+        let source = format!(r#"["{}"]"#, "a".repeat(100_000));
+        let mut input = io::Cursor::new(source.as_bytes());
+        let options = ParseOptions {
+            limits: Limits {
+                max_bytes: Some(16),
+                ..Limits::default()
+            },
+            ..ParseOptions::default()
+        };
+        let mut events = EventReader::with_options(&mut input, options);
+        let err = events.find_map(Result::err).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "expected a smaller input, found more than 16 bytes"
+        );
+        drop(events);
+        assert_eq!(input.position(), 17);
+    }
+}
@@ -1,200 +1,757 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use super::escape;
 use super::number::NumberTokenizer;
+use super::options::ParseOptions;
+
+/// A location in the source text. `offset` counts bytes from the start of the
+/// input, `line` and `column` start at 1 and `column` counts characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
 
-#[derive(Debug, PartialEq)]
-pub enum Token {
+/// The half-open range `start..end` a token or error covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Strings and numbers borrow from the input when they can be used as is,
+/// and only own their text when escapes had to be decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind<'a> {
     LeftBracket,        // {
     RightBracket,       // }
     LeftSquareBracket,  // [
     RightSquareBracket, // ]
     Commma,             // ,
     Colorn,             // :
-    Str(String),
-    Num(String),
-    Bool(String),
+    Str(Cow<'a, str>),
+    Num(Cow<'a, str>),
+    Bool(bool),
     Null,
+    /// `// ...` or `/* ... */`, delimiters included. Only produced when
+    /// comments are enabled.
+    Comment(Cow<'a, str>),
+    /// An unquoted object key. Only produced in JSON5 mode.
+    Ident(Cow<'a, str>),
 }
 
-#[derive(Debug)]
-pub enum TokenizeError {
+impl TokenKind<'_> {
+    pub fn into_owned(self) -> TokenKind<'static> {
+        match self {
+            TokenKind::LeftBracket => TokenKind::LeftBracket,
+            TokenKind::RightBracket => TokenKind::RightBracket,
+            TokenKind::LeftSquareBracket => TokenKind::LeftSquareBracket,
+            TokenKind::RightSquareBracket => TokenKind::RightSquareBracket,
+            TokenKind::Commma => TokenKind::Commma,
+            TokenKind::Colorn => TokenKind::Colorn,
+            TokenKind::Str(s) => TokenKind::Str(Cow::Owned(s.into_owned())),
+            TokenKind::Num(s) => TokenKind::Num(Cow::Owned(s.into_owned())),
+            TokenKind::Bool(b) => TokenKind::Bool(b),
+            TokenKind::Null => TokenKind::Null,
+            TokenKind::Comment(s) => TokenKind::Comment(Cow::Owned(s.into_owned())),
+            TokenKind::Ident(s) => TokenKind::Ident(Cow::Owned(s.into_owned())),
+        }
+    }
+}
+
+impl fmt::Display for TokenKind<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::LeftBracket => write!(f, "'{{'"),
+            TokenKind::RightBracket => write!(f, "'}}'"),
+            TokenKind::LeftSquareBracket => write!(f, "'['"),
+            TokenKind::RightSquareBracket => write!(f, "']'"),
+            TokenKind::Commma => write!(f, "','"),
+            TokenKind::Colorn => write!(f, "':'"),
+            TokenKind::Str(s) => write!(f, "string {}", escape::quote(s)),
+            TokenKind::Num(s) => write!(f, "number {}", s),
+            TokenKind::Bool(b) => write!(f, "{}", b),
+            TokenKind::Null => write!(f, "null"),
+            TokenKind::Comment(_) => write!(f, "comment"),
+            TokenKind::Ident(s) => write!(f, "identifier {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+    pub span: Span,
+}
+
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            kind: self.kind.into_owned(),
+            span: self.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenizeErrorKind {
     InvalidString,
+    UnterminatedString,
+    ControlCharacter,
+    LoneSurrogate,
+    InvalidNumber,
     NotTrue,
     NotFalse,
     NotNull,
     InvalidCharactar,
+    InvalidUtf8,
+    UnterminatedComment,
+    /// Input past one of the `Limits`.
+    TooLarge,
+    StringTooLong,
+    NumberTooLong,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenizeError {
+    pub kind: TokenizeErrorKind,
+    pub span: Span,
+    /// What the tokenizer saw at `span`, e.g. `'x'` or `end of input`.
+    pub found: String,
+}
+
+impl TokenizeError {
+    pub fn expected(&self) -> &'static str {
+        match self.kind {
+            TokenizeErrorKind::InvalidString => "a valid escape sequence",
+            TokenizeErrorKind::UnterminatedString => "'\"'",
+            TokenizeErrorKind::ControlCharacter => "an escaped control character",
+            TokenizeErrorKind::LoneSurrogate => "a complete surrogate pair",
+            TokenizeErrorKind::InvalidNumber => "a digit",
+            TokenizeErrorKind::NotTrue => "true",
+            TokenizeErrorKind::NotFalse => "false",
+            TokenizeErrorKind::NotNull => "null",
+            TokenizeErrorKind::InvalidCharactar => "a JSON value or punctuation",
+            TokenizeErrorKind::InvalidUtf8 => "valid UTF-8",
+            TokenizeErrorKind::UnterminatedComment => "'*/'",
+            TokenizeErrorKind::TooLarge => "a smaller input",
+            TokenizeErrorKind::StringTooLong => "a shorter string",
+            TokenizeErrorKind::NumberTooLong => "a shorter number",
+        }
+    }
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected(), self.found)
+    }
+}
+
+/// Tracks the position of a byte index while tokenizing. Indexes only ever
+/// move forward, so each byte is visited once.
+struct Cursor {
+    position: Position,
+}
+
+impl Cursor {
+    fn new() -> Cursor {
+        Cursor {
+            position: Position::default(),
+        }
+    }
+
+    fn advance(&mut self, bytes: &[u8], count: usize) -> Position {
+        let count = count.min(bytes.len());
+        while self.position.offset < count {
+            let byte = bytes[self.position.offset];
+            self.position.offset += 1;
+            if byte == b'\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else if byte & 0xC0 != 0x80 {
+                // UTF-8 の継続バイトは列として数えない
+                self.position.column += 1;
+            }
+        }
+        return self.position;
+    }
+}
+
+/// Decodes the character starting at `count`, or `None` if the bytes there
+/// are not valid UTF-8.
+fn decode_char(bytes: &[u8], count: usize) -> Option<char> {
+    let width = match bytes.get(count)? {
+        byte if *byte < 0x80 => 1,
+        byte if *byte >= 0xF0 => 4,
+        byte if *byte >= 0xE0 => 3,
+        _ => 2,
+    };
+    let end = (count + width).min(bytes.len());
+    return std::str::from_utf8(&bytes[count..end]).ok()?.chars().next();
+}
+
+fn describe_char(bytes: &[u8], count: usize) -> String {
+    if count >= bytes.len() {
+        return "end of input".to_string();
+    }
+    match decode_char(bytes, count) {
+        Some(ch) => format!("{:?}", ch),
+        None => "invalid UTF-8".to_string(),
+    }
+}
+
+/// Tokenizes a char vector.
+///
+/// This is the original entry point and is kept for existing callers. It
+/// re-encodes the chars as UTF-8 and copies every lexeme, so prefer
+/// `tokenize_bytes` which borrows from the input instead.
+pub fn tokenize(str_vec: Vec<char>) -> Result<Vec<Token<'static>>, TokenizeError> {
+    return tokenize_with(str_vec, &ParseOptions::default());
+}
+
+pub fn tokenize_with(
+    str_vec: Vec<char>,
+    options: &ParseOptions,
+) -> Result<Vec<Token<'static>>, TokenizeError> {
+    let source = str_vec.into_iter().collect::<String>();
+    let tokens = tokenize_bytes_with(source.as_bytes(), options)?;
+    return Ok(tokens.into_iter().map(Token::into_owned).collect());
+}
+
+/// Tokenizes UTF-8 input without copying it. The bytes are validated as they
+/// are read, so invalid UTF-8 is reported with its position like any other
+/// error.
+pub fn tokenize_bytes(bytes: &[u8]) -> Result<Vec<Token<'_>>, TokenizeError> {
+    return tokenize_bytes_with(bytes, &ParseOptions::default());
+}
+
+pub fn tokenize_bytes_with<'a>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+) -> Result<Vec<Token<'a>>, TokenizeError> {
+    return tokenize_inner(bytes, options, None);
+}
+
+/// Tokenizes as much of the input as it can. After an error the offending
+/// character is skipped, along with the rest of the word it is in, and
+/// tokenizing carries on; every error is returned next to the tokens.
+pub fn tokenize_bytes_recovering<'a>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+) -> (Vec<Token<'a>>, Vec<TokenizeError>) {
+    let mut errors = Vec::new();
+    let tokens = tokenize_inner(bytes, options, Some(&mut errors)).unwrap();
+    return (tokens, errors);
+}
+
+/// Fails with `TooLarge` when `bytes` is longer than
+/// `options.limits.max_bytes`. Only the first byte past the limit needs to
+/// have been read.
+pub fn check_size(bytes: &[u8], options: &ParseOptions) -> Result<(), TokenizeError> {
+    if let Some(max) = options.limits.max_bytes.filter(|max| bytes.len() > *max) {
+        let mut cursor = Cursor::new();
+        let start = cursor.advance(bytes, max);
+        let end = cursor.advance(bytes, max + 1);
+        return Err(TokenizeError {
+            kind: TokenizeErrorKind::TooLarge,
+            span: Span { start, end },
+            found: format!("more than {} bytes", max),
+        });
+    }
+    return Ok(());
 }
 
-pub fn tokenize(str_vec: Vec<char>) -> Result<Vec<Token>, TokenizeError> {
+/// Stops at the first error, or collects them in `errors` when given.
+fn tokenize_inner<'a>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+    mut errors: Option<&mut Vec<TokenizeError>>,
+) -> Result<Vec<Token<'a>>, TokenizeError> {
     let mut vec: Vec<Token> = Vec::new();
     let mut count = 0;
-    while count < str_vec.len() {
-        match str_vec[count] {
-            '{' => {
-                vec.push(Token::LeftBracket);
+    let mut cursor = Cursor::new();
+    // 上限を超えた入力は読まない
+    if let Err(err) = check_size(bytes, options) {
+        match errors {
+            Some(errors) => errors.push(err),
+            None => return Err(err),
+        }
+        return Ok(vec);
+    }
+    if !options.strict && bytes.starts_with("\u{feff}".as_bytes()) {
+        count += 3;
+    }
+    while count < bytes.len() {
+        let start = cursor.advance(bytes, count);
+        let token_start = count;
+        let result = match bytes[count] {
+            b'{' => {
                 count += 1;
+                Ok(TokenKind::LeftBracket)
             }
-            '}' => {
-                vec.push(Token::RightBracket);
+            b'}' => {
                 count += 1;
+                Ok(TokenKind::RightBracket)
             }
-            '[' => {
-                vec.push(Token::LeftSquareBracket);
+            b'[' => {
                 count += 1;
+                Ok(TokenKind::LeftSquareBracket)
             }
-            ']' => {
-                vec.push(Token::RightSquareBracket);
+            b']' => {
                 count += 1;
+                Ok(TokenKind::RightSquareBracket)
             }
-            ',' => {
-                vec.push(Token::Commma);
+            b',' => {
                 count += 1;
+                Ok(TokenKind::Commma)
             }
-            ':' => {
-                vec.push(Token::Colorn);
+            b':' => {
                 count += 1;
+                Ok(TokenKind::Colorn)
             }
-            '"' => {
-                distinguish_string(&str_vec, &mut count, &mut vec)?;
+            b'"' => distinguish_string(bytes, &mut count, options),
+            b'\'' if options.json5 => distinguish_string(bytes, &mut count, options),
+            _ if options.json5 && is_identifier_start(bytes, count) => {
+                distinguish_identifier(bytes, &mut count)
             }
-            'f' => {
-                distinguish_false(&str_vec, &mut count, &mut vec)?;
+            b'f' => distinguish_false(bytes, &mut count),
+            b't' => distinguish_true(bytes, &mut count),
+            b'n' => distinguish_null(bytes, &mut count),
+            b'-' | b'0'..=b'9' => distinguish_number(bytes, &mut count, options),
+            b'+' | b'.' if options.json5 => distinguish_number(bytes, &mut count, options),
+            b'/' if options.comments => distinguish_comment(bytes, &mut count),
+            b'\n' | b'\r' | b' ' | b'\t' => {
+                consume_whitespace(bytes, &mut count, options);
+                continue;
             }
-            't' => {
-                distinguish_true(&str_vec, &mut count, &mut vec)?;
+            _ if !options.strict && is_extra_whitespace(bytes, count) => {
+                consume_whitespace(bytes, &mut count, options);
+                continue;
             }
-            'n' => {
-                distinguish_null(&str_vec, &mut count, &mut vec)?;
+            byte if byte >= 0x80 && decode_char(bytes, count).is_none() => {
+                Err(TokenizeErrorKind::InvalidUtf8)
             }
-            '-' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                distinguish_number(&str_vec, &mut count, &mut vec);
+            _ => Err(TokenizeErrorKind::InvalidCharactar),
+        };
+        match result {
+            Ok(kind) => {
+                let end = cursor.advance(bytes, count);
+                let span = Span { start, end };
+                if let Some(err) = check_length(&kind, span, options) {
+                    match errors.as_mut() {
+                        Some(errors) => errors.push(err),
+                        None => return Err(err),
+                    }
+                    continue;
+                }
+                vec.push(Token { kind, span });
             }
-            '\n' | '\r' | ' ' | '\t' => {
-                consume_whitespace(&str_vec, &mut count);
+            Err(kind) => {
+                // 失敗した文字を指すようにする
+                let start = cursor.advance(bytes, count);
+                let width = decode_char(bytes, count).map_or(1, |ch| ch.len_utf8());
+                let end = cursor.advance(bytes, count + width);
+                let err = TokenizeError {
+                    kind,
+                    span: Span { start, end },
+                    found: describe_char(bytes, count),
+                };
+                match errors.as_mut() {
+                    Some(errors) => errors.push(err),
+                    None => return Err(err),
+                }
+                count = skip_invalid(bytes, token_start, count + width);
             }
-            _ => return Err(TokenizeError::InvalidCharactar),
         }
     }
     return Ok(vec);
 }
 
-fn distinguish_string(
-    str_vec: &Vec<char>,
+/// An error for a string or number token longer than the limits allow.
+fn check_length(kind: &TokenKind, span: Span, options: &ParseOptions) -> Option<TokenizeError> {
+    let limits = &options.limits;
+    let (kind, found) = match kind {
+        TokenKind::Str(string) => {
+            let max = limits.max_string_length.filter(|max| string.len() > *max)?;
+            let found = format!("a string of more than {} bytes", max);
+            (TokenizeErrorKind::StringTooLong, found)
+        }
+        // 数の字句は ASCII なので、バイト数が文字数になる
+        TokenKind::Num(lexeme) => {
+            let max = limits.max_number_length.filter(|max| lexeme.len() > *max)?;
+            let found = format!("a number of more than {} characters", max);
+            (TokenizeErrorKind::NumberTooLong, found)
+        }
+        _ => return None,
+    };
+    return Some(TokenizeError { kind, span, found });
+}
+
+fn distinguish_string<'a>(
+    bytes: &'a [u8],
     count: &mut usize,
-    vec: &mut Vec<Token>,
-) -> Result<(), TokenizeError> {
-    let mut buf = str_vec[*count].to_string();
+    options: &ParseOptions,
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    // JSON5 では ' で囲んでもよい
+    let quote = bytes[*count];
     *count += 1;
+    let start = *count;
+    let mut escaped = false;
 
-    while *count < str_vec.len() {
-        if str_vec[*count] == '"' {
-            buf.push(str_vec[*count]);
-            vec.push(Token::Str(buf.clone()));
-            *count += 1;
-            return Ok(());
-        } else if str_vec[*count] == '\\' {
-            distinguish_escape_string(str_vec, count, &mut buf)?;
-        } else {
-            buf.push(str_vec[*count]);
-            *count += 1;
+    // まず閉じる引用符を探し、エスケープがなければ入力をそのまま借用する
+    loop {
+        match bytes.get(*count) {
+            None => return Err(TokenizeErrorKind::UnterminatedString),
+            Some(byte) if *byte == quote => break,
+            Some(b'\\') => {
+                escaped = true;
+                // JSON5 の行継続の \ CR LF は改行ごと読み飛ばす
+                let continuation =
+                    options.json5 && bytes.get(*count + 1..*count + 3) == Some(b"\r\n");
+                *count += if continuation { 3 } else { 2 };
+            }
+            // RFC 8259 では制御文字はエスケープしなければならない
+            Some(byte) if *byte < 0x20 && (options.strict || !options.lossy_strings) => {
+                return Err(TokenizeErrorKind::ControlCharacter);
+            }
+            Some(_) => *count += 1,
+        }
+    }
+
+    let raw = match std::str::from_utf8(&bytes[start..*count]) {
+        Ok(raw) => raw,
+        Err(err) => {
+            *count = start + err.valid_up_to();
+            return Err(TokenizeErrorKind::InvalidUtf8);
+        }
+    };
+    let end = *count;
+    if !escaped {
+        *count += 1;
+        return Ok(TokenKind::Str(Cow::Borrowed(raw)));
+    }
+    match distinguish_escape_string(raw, options) {
+        Ok(buf) => {
+            *count = end + 1;
+            return Ok(TokenKind::Str(Cow::Owned(buf)));
+        }
+        Err((offset, kind)) => {
+            *count = start + offset;
+            return Err(kind);
         }
     }
-    return Err(TokenizeError::InvalidString);
 }
 
+/// Where to carry on after an error at `count` in the token that starts at
+/// `start`: after the closing quote of a string, or else after the rest of
+/// a word like `True` or `1.x`.
+fn skip_invalid(bytes: &[u8], start: usize, mut count: usize) -> usize {
+    let quote = bytes[start];
+    if (quote == b'"' || quote == b'\'') && count < bytes.len() {
+        count = start + 1;
+        while count < bytes.len() && bytes[count] != quote {
+            count += if bytes[count] == b'\\' { 2 } else { 1 };
+        }
+        return count + 1;
+    }
+    while count < bytes.len() && bytes[count].is_ascii_alphanumeric() {
+        count += 1;
+    }
+    return count;
+}
+
+/// Decodes the escapes in the body of a string. Errors carry the byte offset
+/// into `raw` where decoding failed.
 fn distinguish_escape_string(
-    str_vec: &Vec<char>,
-    count: &mut usize,
-    buf: &mut String,
-) -> Result<(), TokenizeError> {
-    if str_vec[*count] == '\\' {
-        buf.push(str_vec[*count]);
-        *count += 1;
-        match str_vec[*count] {
-            '\\' | '\"' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => {
-                buf.push(str_vec[*count]);
-                *count += 1;
-            }
-            'u' => {
-                *count += 1;
-                let s1 = str_vec[*count];
-                let s2 = str_vec[*count + 1];
-                let s3 = str_vec[*count + 2];
-                let s4 = str_vec[*count + 3];
-                if s1.is_digit(16) && s2.is_digit(16) && s3.is_digit(16) && s4.is_digit(16) {
-                    buf.push(s1);
-                    buf.push(s2);
-                    buf.push(s3);
-                    buf.push(s4);
-                    *count += 4;
-                } else {
-                    return Err(TokenizeError::InvalidString);
+    raw: &str,
+    options: &ParseOptions,
+) -> Result<String, (usize, TokenizeErrorKind)> {
+    let bytes = raw.as_bytes();
+    let mut buf = String::with_capacity(raw.len());
+    let mut count = 0;
+    let mut run = 0;
+    while count < bytes.len() {
+        if bytes[count] != b'\\' {
+            count += 1;
+            continue;
+        }
+        buf.push_str(&raw[run..count]);
+        let start = count;
+        let ch = match bytes.get(count + 1) {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let high = distinguish_unicode_escape(bytes, count)
+                    .ok_or((count + 1, TokenizeErrorKind::InvalidString))?;
+                count += 6;
+                let mut ch = char::from_u32(high);
+                if (0xD800..=0xDBFF).contains(&high) {
+                    // 上位サロゲートの直後には下位サロゲートの \uXXXX が続く必要がある
+                    if let Some(low) = distinguish_unicode_escape(bytes, count) {
+                        if (0xDC00..=0xDFFF).contains(&low) {
+                            ch = char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00));
+                            count += 6;
+                        }
+                    }
+                }
+                match ch {
+                    Some(ch) => buf.push(ch),
+                    // 求められたときだけ、対になっていないサロゲートを U+FFFD に置き換える
+                    None if options.lossy_strings && !options.strict => {
+                        buf.push(char::REPLACEMENT_CHARACTER)
+                    }
+                    None => return Err((start, TokenizeErrorKind::LoneSurrogate)),
                 }
+                run = count;
+                continue;
             }
-            _ => return Err(TokenizeError::InvalidString),
+            Some(_) if options.json5 => {
+                count = distinguish_json5_escape(raw, count, &mut buf)?;
+                run = count;
+                continue;
+            }
+            Some(_) => return Err((count + 1, TokenizeErrorKind::InvalidString)),
+            None => return Err((count + 1, TokenizeErrorKind::UnterminatedString)),
+        };
+        buf.push(ch);
+        count += 2;
+        run = count;
+    }
+    buf.push_str(&raw[run..]);
+    return Ok(buf);
+}
+
+/// Decodes an escape that JSON5 adds to JSON's at `count`, appending it to
+/// `buf`, and returns where the string continues. A backslash before a line
+/// break continues the string on the next line.
+fn distinguish_json5_escape(
+    raw: &str,
+    count: usize,
+    buf: &mut String,
+) -> Result<usize, (usize, TokenizeErrorKind)> {
+    let bytes = raw.as_bytes();
+    let ch = match raw[count + 1..].chars().next() {
+        Some(ch) => ch,
+        None => return Err((count + 1, TokenizeErrorKind::UnterminatedString)),
+    };
+    let mut end = count + 1 + ch.len_utf8();
+    match ch {
+        '\n' | '\u{2028}' | '\u{2029}' => {}
+        '\r' => {
+            if bytes.get(end) == Some(&b'\n') {
+                end += 1;
+            }
+        }
+        'v' => buf.push('\u{b}'),
+        '0' if !bytes.get(end).is_some_and(|byte| byte.is_ascii_digit()) => buf.push('\0'),
+        'x' => {
+            let code = bytes
+                .get(end..end + 2)
+                .filter(|hex| hex.iter().all(|byte| byte.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+                .ok_or((count + 1, TokenizeErrorKind::InvalidString))?;
+            buf.push(code as char);
+            end += 2;
         }
+        // 数字のエスケープは 8 進数と紛らわしいので認めない
+        '0'..='9' => return Err((count + 1, TokenizeErrorKind::InvalidString)),
+        // それ以外の文字はその文字自身を表す
+        ch => buf.push(ch),
     }
-    return Ok(());
+    return Ok(end);
 }
 
-fn distinguish_false(
-    str_vec: &Vec<char>,
+/// Reads `\uXXXX` at `count` and returns the code unit.
+fn distinguish_unicode_escape(bytes: &[u8], count: usize) -> Option<u32> {
+    if bytes.get(count..count + 2) != Some(b"\\u") {
+        return None;
+    }
+    let hex = bytes.get(count + 2..count + 6)?;
+    if !hex.iter().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    return u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok();
+}
+
+fn distinguish_false<'a>(
+    bytes: &[u8],
     count: &mut usize,
-    vec: &mut Vec<Token>,
-) -> Result<(), TokenizeError> {
-    if let Some(bool) = str_vec.get(*count..*count + 5) {
-        let buf = bool.iter().collect::<String>();
-        if buf == "false".to_string() {
-            vec.push(Token::Bool(buf.clone()));
-            *count += 5;
-            return Ok(());
-        }
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    if bytes.get(*count..*count + 5) == Some(b"false") {
+        *count += 5;
+        return Ok(TokenKind::Bool(false));
     }
-    return Err(TokenizeError::NotFalse);
+    return Err(TokenizeErrorKind::NotFalse);
 }
 
-fn distinguish_true(
-    str_vec: &Vec<char>,
+fn distinguish_true<'a>(
+    bytes: &[u8],
     count: &mut usize,
-    vec: &mut Vec<Token>,
-) -> Result<(), TokenizeError> {
-    if let Some(bool) = str_vec.get(*count..*count + 4) {
-        let buf = bool.iter().collect::<String>();
-        if buf == "true".to_string() {
-            vec.push(Token::Bool(buf.clone()));
-            *count += 4;
-            return Ok(());
-        }
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    if bytes.get(*count..*count + 4) == Some(b"true") {
+        *count += 4;
+        return Ok(TokenKind::Bool(true));
     }
-    return Err(TokenizeError::NotTrue);
+    return Err(TokenizeErrorKind::NotTrue);
 }
 
-fn distinguish_null(
-    str_vec: &Vec<char>,
+fn distinguish_null<'a>(
+    bytes: &[u8],
     count: &mut usize,
-    vec: &mut Vec<Token>,
-) -> Result<(), TokenizeError> {
-    if let Some(null) = str_vec.get(*count..*count + 4) {
-        if null.iter().collect::<String>() == "null".to_string() {
-            vec.push(Token::Null);
-            *count += 4;
-            return Ok(());
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    if bytes.get(*count..*count + 4) == Some(b"null") {
+        *count += 4;
+        return Ok(TokenKind::Null);
+    }
+    return Err(TokenizeErrorKind::NotNull);
+}
+
+fn distinguish_number<'a>(
+    bytes: &'a [u8],
+    count: &mut usize,
+    options: &ParseOptions,
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    if options.json5 {
+        // 符号のついた Infinity と NaN
+        let start = *count;
+        let sign = matches!(bytes[start], b'+' | b'-') as usize;
+        for word in [&b"Infinity"[..], b"NaN"] {
+            let end = start + sign + word.len();
+            if bytes.get(start + sign..end) == Some(word) {
+                *count = end;
+                let lexeme = std::str::from_utf8(&bytes[start..end]).unwrap();
+                return Ok(TokenKind::Num(Cow::Borrowed(lexeme)));
+            }
         }
     }
-    return Err(TokenizeError::NotNull);
+    let mut num_tokenizer = if options.json5 {
+        NumberTokenizer::json5()
+    } else {
+        NumberTokenizer::new()
+    };
+    let lexeme = num_tokenizer.tokenize(bytes, count);
+    if !num_tokenizer.is_complete() {
+        return Err(TokenizeErrorKind::InvalidNumber);
+    }
+    return Ok(TokenKind::Num(Cow::Borrowed(lexeme)));
 }
 
-fn distinguish_number(str_vec: &Vec<char>, count: &mut usize, vec: &mut Vec<Token>) {
-    let mut num_tokenizer = NumberTokenizer::new();
-    vec.push(Token::Num(num_tokenizer.tokenize(str_vec, count)));
-    return;
+/// Whether an ECMAScript identifier starts at `count`. Escapes in
+/// identifiers are not supported.
+fn is_identifier_start(bytes: &[u8], count: usize) -> bool {
+    match bytes[count] {
+        b'$' | b'_' => return true,
+        byte if byte < 0x80 => return byte.is_ascii_alphabetic(),
+        _ => return decode_char(bytes, count).is_some_and(|ch| ch.is_alphabetic()),
+    }
 }
 
-fn consume_whitespace(str_vec: &Vec<char>, count: &mut usize) {
-    while *count < str_vec.len() {
-        if str_vec[*count].is_whitespace() {
-            *count += 1;
-            continue;
+fn is_identifier_part(bytes: &[u8], count: usize) -> bool {
+    match bytes.get(count) {
+        None => return false,
+        Some(b'$' | b'_') => return true,
+        Some(byte) if *byte < 0x80 => return byte.is_ascii_alphanumeric(),
+        Some(_) => {
+            return decode_char(bytes, count)
+                .is_some_and(|ch| ch.is_alphanumeric() || ch == '\u{200c}' || ch == '\u{200d}');
+        }
+    }
+}
+
+/// Reads a JSON5 identifier. The words JSON gives a meaning to become their
+/// usual tokens; the parser still accepts them as keys.
+fn distinguish_identifier<'a>(
+    bytes: &'a [u8],
+    count: &mut usize,
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    let start = *count;
+    *count += decode_char(bytes, start).unwrap().len_utf8();
+    while is_identifier_part(bytes, *count) {
+        *count += decode_char(bytes, *count).unwrap().len_utf8();
+    }
+    let name = std::str::from_utf8(&bytes[start..*count]).unwrap();
+    match name {
+        "true" => return Ok(TokenKind::Bool(true)),
+        "false" => return Ok(TokenKind::Bool(false)),
+        "null" => return Ok(TokenKind::Null),
+        "Infinity" | "NaN" => return Ok(TokenKind::Num(Cow::Borrowed(name))),
+        _ => return Ok(TokenKind::Ident(Cow::Borrowed(name))),
+    }
+}
+
+/// Reads a `//` comment up to the end of the line, or a `/* */` comment.
+/// The line break after a `//` comment is not part of it.
+fn distinguish_comment<'a>(
+    bytes: &'a [u8],
+    count: &mut usize,
+) -> Result<TokenKind<'a>, TokenizeErrorKind> {
+    let start = *count;
+    let end = match bytes.get(start + 1) {
+        Some(b'/') => {
+            let rest = &bytes[start..];
+            let len = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+            // CRLF の CR もコメントに含めない
+            let len = if len > 0 && rest[len - 1] == b'\r' {
+                len - 1
+            } else {
+                len
+            };
+            start + len
+        }
+        Some(b'*') => match bytes[start + 2..].windows(2).position(|w| w == b"*/") {
+            Some(position) => start + 2 + position + 2,
+            None => {
+                *count = bytes.len();
+                return Err(TokenizeErrorKind::UnterminatedComment);
+            }
+        },
+        _ => return Err(TokenizeErrorKind::InvalidCharactar),
+    };
+    let text = std::str::from_utf8(&bytes[start..end]).map_err(|err| {
+        *count = start + err.valid_up_to();
+        return TokenizeErrorKind::InvalidUtf8;
+    })?;
+    *count = end;
+    return Ok(TokenKind::Comment(Cow::Borrowed(text)));
+}
+
+/// Whitespace outside of RFC 8259's four characters, accepted when not
+/// strict.
+fn is_extra_whitespace(bytes: &[u8], count: usize) -> bool {
+    match bytes[count] {
+        b' ' | b'\t' | b'\n' | b'\r' => return false,
+        byte if byte < 0x80 => return (byte as char).is_whitespace(),
+        _ => return decode_char(bytes, count).is_some_and(|ch| ch.is_whitespace()),
+    }
+}
+
+fn consume_whitespace(bytes: &[u8], count: &mut usize, options: &ParseOptions) {
+    while *count < bytes.len() {
+        match bytes[*count] {
+            b' ' | b'\t' | b'\n' | b'\r' => *count += 1,
+            _ if !options.strict && is_extra_whitespace(bytes, *count) => {
+                *count += decode_char(bytes, *count).unwrap().len_utf8();
+            }
+            _ => return,
         }
-        return;
     }
     return;
 }
@@ -202,117 +759,401 @@ fn consume_whitespace(str_vec: &Vec<char>, count: &mut usize) {
 #[cfg(test)]
 mod test {
     use super::*;
+
+    fn kinds(tokens: Vec<Token>) -> Vec<TokenKind> {
+        return tokens.into_iter().map(|token| token.kind).collect();
+    }
+
     #[test]
     fn test_tokenize() {
         assert_eq!(
-            tokenize("{".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::LeftBracket]
+            kinds(tokenize("{".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::LeftBracket]
         );
         assert_eq!(
-            tokenize("}".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::RightBracket]
+            kinds(tokenize("}".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::RightBracket]
         );
         assert_eq!(
-            tokenize("[".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::LeftSquareBracket]
+            kinds(tokenize("[".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::LeftSquareBracket]
         );
         assert_eq!(
-            tokenize("]".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::RightSquareBracket]
+            kinds(tokenize("]".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::RightSquareBracket]
         );
         assert_eq!(
-            tokenize(",".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Commma]
+            kinds(tokenize(",".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Commma]
         );
         assert_eq!(
-            tokenize(":".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Colorn]
+            kinds(tokenize(":".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Colorn]
         );
         assert_eq!(
-            tokenize("\"hoge\"".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Str("\"hoge\"".to_string())]
+            kinds(tokenize("\"hoge\"".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Str("hoge".into())]
         );
         assert_eq!(
-            tokenize("false".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Bool("false".to_string())]
+            kinds(tokenize("false".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Bool(false)]
         );
         assert_eq!(
-            tokenize("true".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Bool("true".to_string())]
+            kinds(tokenize("true".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Bool(true)]
         );
         assert_eq!(
-            tokenize("null".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Null]
+            kinds(tokenize("null".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Null]
         );
         assert_eq!(
-            tokenize("100".chars().collect::<Vec<char>>()).unwrap(),
-            vec![Token::Num("100".to_string())]
+            kinds(tokenize("100".chars().collect::<Vec<char>>()).unwrap()),
+            vec![TokenKind::Num("100".into())]
         );
 
         assert_eq!(
-            tokenize(
-                "{  \"key\"  \t :  \"value\" \r\n}"
-                    .chars()
-                    .collect::<Vec<char>>()
-            )
-            .unwrap(),
+            kinds(
+                tokenize(
+                    "{  \"key\"  \t :  \"value\" \r\n}"
+                        .chars()
+                        .collect::<Vec<char>>()
+                )
+                .unwrap()
+            ),
             vec![
-                Token::LeftBracket,
-                Token::Str("\"key\"".to_string()),
-                Token::Colorn,
-                Token::Str("\"value\"".to_string()),
-                Token::RightBracket
+                TokenKind::LeftBracket,
+                TokenKind::Str("key".into()),
+                TokenKind::Colorn,
+                TokenKind::Str("value".into()),
+                TokenKind::RightBracket
             ]
         );
     }
 
     #[test]
     fn test_distinguish_string() {
-        let str_vec = "\"hoge\"".chars().collect::<Vec<char>>();
+        let bytes = "\"hoge\"".as_bytes();
         let mut count = 0;
-        let mut vec: Vec<Token> = Vec::new();
-        distinguish_string(&str_vec, &mut count, &mut vec).unwrap();
-        assert_eq!(vec, vec![Token::Str("\"hoge\"".to_string())]);
+        assert_eq!(
+            distinguish_string(bytes, &mut count, &ParseOptions::default()).unwrap(),
+            TokenKind::Str("hoge".into())
+        );
         assert_eq!(count, 6);
     }
 
+    #[test]
+    fn test_distinguish_escape_string() {
+        let strict = ParseOptions::strict();
+        let decode = |s: &'static str| distinguish_string(s.as_bytes(), &mut 0, &strict);
+        assert_eq!(
+            decode(r#""\"\\\/\b\f\n\r\t""#).unwrap(),
+            TokenKind::Str("\"\\/\u{8}\u{c}\n\r\t".into())
+        );
+        assert_eq!(
+            decode(r#""caf\u00e9""#).unwrap(),
+            TokenKind::Str("café".into())
+        );
+        assert_eq!(
+            decode(r#""\ud83d\ude00""#).unwrap(),
+            TokenKind::Str("😀".into())
+        );
+        assert_eq!(
+            decode(r#""\uD83D\uDE00!""#).unwrap(),
+            TokenKind::Str("😀!".into())
+        );
+        assert_eq!(decode(r#""\ud83d""#), Err(TokenizeErrorKind::LoneSurrogate));
+        assert_eq!(
+            decode(r#""\ud83dx""#),
+            Err(TokenizeErrorKind::LoneSurrogate)
+        );
+        assert_eq!(
+            decode(r#""\ud83d\u0041""#),
+            Err(TokenizeErrorKind::LoneSurrogate)
+        );
+        assert_eq!(decode(r#""\ude00""#), Err(TokenizeErrorKind::LoneSurrogate));
+        assert_eq!(decode(r#""\x41""#), Err(TokenizeErrorKind::InvalidString));
+        assert_eq!(decode("\"a\nb\""), Err(TokenizeErrorKind::ControlCharacter));
+        assert_eq!(decode("\"a\tb\""), Err(TokenizeErrorKind::ControlCharacter));
+
+        let default = ParseOptions::default();
+        let decode = |s: &'static str| distinguish_string(s.as_bytes(), &mut 0, &default);
+        assert_eq!(decode(r#""\ud83d""#), Err(TokenizeErrorKind::LoneSurrogate));
+        assert_eq!(decode("\"a\tb\""), Err(TokenizeErrorKind::ControlCharacter));
+
+        let lenient = ParseOptions {
+            lossy_strings: true,
+            ..ParseOptions::default()
+        };
+        let decode = |s: &'static str| distinguish_string(s.as_bytes(), &mut 0, &lenient);
+        assert_eq!(
+            decode(r#""\ud83d""#).unwrap(),
+            TokenKind::Str("\u{fffd}".into())
+        );
+        assert_eq!(
+            decode(r#""\ud83d\u0041""#).unwrap(),
+            TokenKind::Str("\u{fffd}A".into())
+        );
+        assert_eq!(
+            decode(r#""\ude00x""#).unwrap(),
+            TokenKind::Str("\u{fffd}x".into())
+        );
+        assert_eq!(decode("\"a\tb\"").unwrap(), TokenKind::Str("a\tb".into()));
+    }
+
     #[test]
     fn test_distinguish_false() {
-        let str_vec = "false".chars().collect::<Vec<char>>();
+        let bytes = "false".as_bytes();
         let mut count = 0;
-        let mut vec: Vec<Token> = Vec::new();
-        distinguish_false(&str_vec, &mut count, &mut vec).unwrap();
-        assert_eq!(vec, vec![Token::Bool("false".to_string())]);
+        assert_eq!(
+            distinguish_false(bytes, &mut count).unwrap(),
+            TokenKind::Bool(false)
+        );
         assert_eq!(count, 5);
     }
 
     #[test]
     fn test_distinguish_true() {
-        let str_vec = "true".chars().collect::<Vec<char>>();
+        let bytes = "true".as_bytes();
         let mut count = 0;
-        let mut vec: Vec<Token> = Vec::new();
-        distinguish_true(&str_vec, &mut count, &mut vec).unwrap();
-        assert_eq!(vec, vec![Token::Bool("true".to_string())]);
+        assert_eq!(
+            distinguish_true(bytes, &mut count).unwrap(),
+            TokenKind::Bool(true)
+        );
         assert_eq!(count, 4);
     }
 
     #[test]
     fn test_distinguish_null() {
-        let str_vec = "null".chars().collect::<Vec<char>>();
+        let bytes = "null".as_bytes();
         let mut count = 0;
-        let mut vec: Vec<Token> = Vec::new();
-        distinguish_null(&str_vec, &mut count, &mut vec).unwrap();
-        assert_eq!(vec, vec![Token::Null]);
+        assert_eq!(
+            distinguish_null(bytes, &mut count).unwrap(),
+            TokenKind::Null
+        );
         assert_eq!(count, 4);
     }
 
     #[test]
     fn test_distinguish_number() {
-        let str_vec = "12".chars().collect::<Vec<char>>();
+        let bytes = "12".as_bytes();
         let mut count = 0;
-        let mut vec: Vec<Token> = Vec::new();
-        distinguish_number(&str_vec, &mut count, &mut vec);
-        assert_eq!(vec, vec![Token::Num("12".to_string())]);
+        assert_eq!(
+            distinguish_number(bytes, &mut count, &ParseOptions::default()).unwrap(),
+            TokenKind::Num("12".into())
+        );
         assert_eq!(count, 2);
     }
+
+    #[test]
+    fn test_span() {
+        let tokens = tokenize("{\n  \"é\": 10\n}".chars().collect::<Vec<char>>()).unwrap();
+        assert_eq!(
+            tokens[1].span,
+            Span {
+                start: Position {
+                    offset: 4,
+                    line: 2,
+                    column: 3
+                },
+                end: Position {
+                    offset: 8,
+                    line: 2,
+                    column: 6
+                }
+            }
+        );
+        assert_eq!(
+            tokens[3].span.start,
+            Position {
+                offset: 10,
+                line: 2,
+                column: 8
+            }
+        );
+        assert_eq!(tokens[4].span.start.line, 3);
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let err = tokenize("[1, x]".chars().collect::<Vec<char>>()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidCharactar);
+        assert_eq!(err.span.start.column, 5);
+        assert_eq!(err.found, "'x'");
+
+        let err = tokenize("\"ab\\q\"".chars().collect::<Vec<char>>()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidString);
+        assert_eq!(err.span.start.column, 5);
+
+        let err = tokenize("\"abc".chars().collect::<Vec<char>>()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::UnterminatedString);
+        assert_eq!(err.found, "end of input");
+
+        let err = tokenize("\"\\u12\"".chars().collect::<Vec<char>>()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidString);
+
+        let err = tokenize("nul".chars().collect::<Vec<char>>()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::NotNull);
+
+        let err = tokenize("[1.]".chars().collect::<Vec<char>>()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidNumber);
+        assert_eq!(err.found, "']'");
+    }
+
+    #[test]
+    fn test_tokenize_strict() {
+        let strict = ParseOptions::strict();
+        let source = "\u{feff}[1,\u{a0}2]";
+        assert_eq!(
+            kinds(tokenize(source.chars().collect::<Vec<char>>()).unwrap()),
+            vec![
+                TokenKind::LeftSquareBracket,
+                TokenKind::Num("1".into()),
+                TokenKind::Commma,
+                TokenKind::Num("2".into()),
+                TokenKind::RightSquareBracket
+            ]
+        );
+        let err = tokenize_with(source.chars().collect::<Vec<char>>(), &strict).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidCharactar);
+        assert_eq!(err.span.start.offset, 0);
+
+        let err = tokenize_with("[1,\u{a0}2]".chars().collect::<Vec<char>>(), &strict).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidCharactar);
+        assert_eq!(err.span.start.column, 4);
+    }
+
+    #[test]
+    fn test_tokenize_bytes_borrows() {
+        let source = br#"{"plain": "text", "escaped": "a\nb", "n": -1.5e3}"#;
+        let tokens = tokenize_bytes(source).unwrap();
+        assert!(matches!(
+            tokens[1].kind,
+            TokenKind::Str(Cow::Borrowed("plain"))
+        ));
+        assert!(matches!(
+            tokens[3].kind,
+            TokenKind::Str(Cow::Borrowed("text"))
+        ));
+        assert!(matches!(&tokens[7].kind, TokenKind::Str(Cow::Owned(s)) if s == "a\nb"));
+        assert!(matches!(
+            tokens[11].kind,
+            TokenKind::Num(Cow::Borrowed("-1.5e3"))
+        ));
+        assert_eq!(tokens[11].span.start.offset, 42);
+    }
+
+    #[test]
+    fn test_tokenize_bytes_invalid_utf8() {
+        let err = tokenize_bytes(b"[\"ab\xff\"]").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidUtf8);
+        assert_eq!(err.span.start.offset, 4);
+        assert_eq!(err.found, "invalid UTF-8");
+
+        let err = tokenize_bytes(b"[1, \xe5]").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidUtf8);
+        assert_eq!(err.span.start.column, 5);
+
+        // 途中で切れたマルチバイト文字
+        let err = tokenize_bytes(b"\"\xe3\x81\"").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidUtf8);
+        assert_eq!(err.span.start.offset, 1);
+
+        let err = tokenize_bytes("[1, é]".as_bytes()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidCharactar);
+        assert_eq!(err.found, "'é'");
+        assert_eq!(err.span.end.offset, 6);
+    }
+
+    #[test]
+    fn test_tokenize_comments() {
+        let source = "// head\r\n[1, /* one\n two */ 2] // tail";
+        let tokens = tokenize_bytes_with(source.as_bytes(), &ParseOptions::jsonc()).unwrap();
+        assert_eq!(tokens[0].span.end.offset, 7);
+        assert_eq!(
+            kinds(tokens),
+            vec![
+                TokenKind::Comment(Cow::Borrowed("// head")),
+                TokenKind::LeftSquareBracket,
+                TokenKind::Num(Cow::Borrowed("1")),
+                TokenKind::Commma,
+                TokenKind::Comment(Cow::Borrowed("/* one\n two */")),
+                TokenKind::Num(Cow::Borrowed("2")),
+                TokenKind::RightSquareBracket,
+                TokenKind::Comment(Cow::Borrowed("// tail")),
+            ]
+        );
+
+        // 無効のときは今まで通りエラー
+        let err = tokenize_bytes(b"[1] // x").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidCharactar);
+
+        let err = tokenize_bytes_with(b"[1] /* x", &ParseOptions::jsonc()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::UnterminatedComment);
+        assert_eq!(err.found, "end of input");
+        assert_eq!(err.span.start.offset, 8);
+
+        let err = tokenize_bytes_with(b"[1] / x", &ParseOptions::jsonc()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidCharactar);
+        assert_eq!(err.found, "'/'");
+    }
+
+    #[test]
+    fn test_tokenize_json5() {
+        let source = "{key: 'it\\'s \"q\"', $b_1: [+1, .5, 5., 0x1F, -Infinity, NaN], null: true}";
+        let tokens = tokenize_bytes_with(source.as_bytes(), &ParseOptions::json5()).unwrap();
+        assert_eq!(
+            kinds(tokens),
+            vec![
+                TokenKind::LeftBracket,
+                TokenKind::Ident(Cow::Borrowed("key")),
+                TokenKind::Colorn,
+                TokenKind::Str(Cow::Borrowed("it's \"q\"")),
+                TokenKind::Commma,
+                TokenKind::Ident(Cow::Borrowed("$b_1")),
+                TokenKind::Colorn,
+                TokenKind::LeftSquareBracket,
+                TokenKind::Num(Cow::Borrowed("+1")),
+                TokenKind::Commma,
+                TokenKind::Num(Cow::Borrowed(".5")),
+                TokenKind::Commma,
+                TokenKind::Num(Cow::Borrowed("5.")),
+                TokenKind::Commma,
+                TokenKind::Num(Cow::Borrowed("0x1F")),
+                TokenKind::Commma,
+                TokenKind::Num(Cow::Borrowed("-Infinity")),
+                TokenKind::Commma,
+                TokenKind::Num(Cow::Borrowed("NaN")),
+                TokenKind::RightSquareBracket,
+                TokenKind::Commma,
+                TokenKind::Null,
+                TokenKind::Colorn,
+                TokenKind::Bool(true),
+                TokenKind::RightBracket,
+            ]
+        );
+
+        let string = |source: &str| {
+            let tokens = tokenize_bytes_with(source.as_bytes(), &ParseOptions::json5());
+            match tokens.map(kinds).as_deref() {
+                Ok([TokenKind::Str(s)]) => return Ok(s.to_string()),
+                Ok(_) => panic!("expected one string"),
+                Err(err) => return Err(err.kind),
+            }
+        };
+        assert_eq!(string("'a\\\nb\\\r\nc'"), Ok("abc".to_string()));
+        assert_eq!(
+            string("'\\v\\0\\x41\\q\\u00e9'"),
+            Ok("\u{b}\0Aqé".to_string())
+        );
+        assert_eq!(string("'\\1'"), Err(TokenizeErrorKind::InvalidString));
+        assert_eq!(string("'\\x4'"), Err(TokenizeErrorKind::InvalidString));
+        assert_eq!(string("'open"), Err(TokenizeErrorKind::UnterminatedString));
+
+        // JSON5 でなければ今まで通り
+        assert!(tokenize_bytes(b"{a: 1}").is_err());
+        assert!(tokenize_bytes(b"'a'").is_err());
+        assert!(tokenize_bytes(b"+1").is_err());
+        let err = tokenize_bytes_with(b"[0x]", &ParseOptions::json5()).unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidNumber);
+    }
 }
@@ -0,0 +1,403 @@
+use std::mem;
+use std::ops::Index;
+
+use super::number::Number;
+
+/// A parsed JSON value.
+///
+/// Object members keep their input order, so a value prints back the way it
+/// was written.
+///
+/// `clone` and `==` work through nested values without recursion, but
+/// dropping a value and `Debug` recurse once per level of nesting. Use
+/// `dispose` to drop a value that may be deeply nested.
+#[derive(Debug)]
+pub enum Value {
+    Object(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    Str(String),
+    Num(Number),
+    Bool(bool),
+    Null,
+}
+
+static NULL: Value = Value::Null;
+
+impl Value {
+    pub fn is_object(&self) -> bool {
+        return matches!(self, Value::Object(_));
+    }
+
+    pub fn is_array(&self) -> bool {
+        return matches!(self, Value::Array(_));
+    }
+
+    pub fn is_string(&self) -> bool {
+        return matches!(self, Value::Str(_));
+    }
+
+    pub fn is_number(&self) -> bool {
+        return matches!(self, Value::Num(_));
+    }
+
+    pub fn is_bool(&self) -> bool {
+        return matches!(self, Value::Bool(_));
+    }
+
+    pub fn is_null(&self) -> bool {
+        return matches!(self, Value::Null);
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        if let Value::Object(members) = self {
+            return Some(members);
+        }
+        return None;
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        if let Value::Array(elements) = self {
+            return Some(elements);
+        }
+        return None;
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if let Value::Str(string) = self {
+            return Some(string);
+        }
+        return None;
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        if let Value::Num(number) = self {
+            return Some(number);
+        }
+        return None;
+    }
+
+    /// The number as an `i64`, when it is a whole number in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        return self.as_number()?.as_i64();
+    }
+
+    /// The number as a `u64`, when it is a whole number in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        return self.as_number()?.as_u64();
+    }
+
+    /// The number as the nearest double; see `Number::as_f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        return self.as_number()?.as_f64();
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Value::Bool(b) = self {
+            return Some(*b);
+        }
+        return None;
+    }
+
+    /// Looks up an object member by key. When a key appears more than once
+    /// the first member wins.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        return self
+            .as_object()?
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v);
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        if let Value::Object(members) = self {
+            return members.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v);
+        }
+        return None;
+    }
+
+    /// Looks up an array element by position.
+    pub fn get_index(&self, index: usize) -> Option<&Value> {
+        return self.as_array()?.get(index);
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut Value> {
+        if let Value::Array(elements) = self {
+            return elements.get_mut(index);
+        }
+        return None;
+    }
+
+    /// Iterates over the keys of an object. Yields nothing for other values.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        return self
+            .as_object()
+            .unwrap_or(&[])
+            .iter()
+            .map(|(k, _)| k.as_str());
+    }
+
+    /// Iterates over the key/value pairs of an object. Yields nothing for
+    /// other values.
+    pub fn members(&self) -> impl Iterator<Item = (&str, &Value)> {
+        return self
+            .as_object()
+            .unwrap_or(&[])
+            .iter()
+            .map(|(k, v)| (k.as_str(), v));
+    }
+
+    /// Iterates over the elements of an array. Yields nothing for other
+    /// values.
+    pub fn iter(&self) -> std::slice::Iter<'_, Value> {
+        return self.as_array().unwrap_or(&[]).iter();
+    }
+
+    /// Number of members of an object or elements of an array, 0 otherwise.
+    pub fn len(&self) -> usize {
+        match self {
+            Value::Object(members) => members.len(),
+            Value::Array(elements) => elements.len(),
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Drops the value one level of nesting at a time. Dropping it the
+    /// usual way recurses once per level, which can overflow the stack on
+    /// deeply nested input.
+    pub fn dispose(mut self) {
+        let mut stack = Vec::new();
+        loop {
+            // 子を持つ値だけを積み、残りはその場で捨てる
+            match &mut self {
+                Value::Object(members) => stack.extend(
+                    mem::take(members)
+                        .into_iter()
+                        .map(|(_, value)| value)
+                        .filter(|value| !value.is_empty()),
+                ),
+                Value::Array(elements) => stack.extend(
+                    mem::take(elements)
+                        .into_iter()
+                        .filter(|value| !value.is_empty()),
+                ),
+                _ => {}
+            }
+            match stack.pop() {
+                Some(next) => self = next,
+                None => return,
+            }
+        }
+    }
+}
+
+/// Copies nested values with a stack rather than by recursion.
+impl Clone for Value {
+    fn clone(&self) -> Value {
+        let mut stack: Vec<Copying> = Vec::new();
+        let mut next = self;
+        loop {
+            // 値を一つ写す。コンテナなら、その最初の要素へ進む
+            let mut copy = match next {
+                Value::Object(members) => {
+                    let copied = Vec::with_capacity(members.len());
+                    stack.push(Copying::Object(members.iter(), copied));
+                    None
+                }
+                Value::Array(elements) => {
+                    let copied = Vec::with_capacity(elements.len());
+                    stack.push(Copying::Array(elements.iter(), copied));
+                    None
+                }
+                Value::Str(string) => Some(Value::Str(string.clone())),
+                Value::Num(number) => Some(Value::Num(number.clone())),
+                Value::Bool(b) => Some(Value::Bool(*b)),
+                Value::Null => Some(Value::Null),
+            };
+            // 写し終えた値を親に加え、次に写す値へ進む
+            loop {
+                let frame = match stack.last_mut() {
+                    Some(frame) => frame,
+                    None => return copy.unwrap(),
+                };
+                let child = match frame {
+                    Copying::Object(rest, copied) => {
+                        if let Some(value) = copy.take() {
+                            copied.last_mut().unwrap().1 = value;
+                        }
+                        rest.next().map(|(key, value)| {
+                            copied.push((key.clone(), Value::Null));
+                            return value;
+                        })
+                    }
+                    Copying::Array(rest, copied) => {
+                        copied.extend(copy.take());
+                        rest.next()
+                    }
+                };
+                match child {
+                    Some(child) => {
+                        next = child;
+                        break;
+                    }
+                    None => match stack.pop().unwrap() {
+                        Copying::Object(_, copied) => copy = Some(Value::Object(copied)),
+                        Copying::Array(_, copied) => copy = Some(Value::Array(copied)),
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// A container being copied by `clone`: the members left to copy and
+/// those copied so far.
+enum Copying<'a> {
+    Object(std::slice::Iter<'a, (String, Value)>, Vec<(String, Value)>),
+    Array(std::slice::Iter<'a, Value>, Vec<Value>),
+}
+
+/// Compares nested values with a stack rather than by recursion.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some(pair) = stack.pop() {
+            match pair {
+                (Value::Object(a), Value::Object(b)) => {
+                    if a.len() != b.len() {
+                        return false;
+                    }
+                    for ((key_a, a), (key_b, b)) in a.iter().zip(b.iter()) {
+                        if key_a != key_b {
+                            return false;
+                        }
+                        stack.push((a, b));
+                    }
+                }
+                (Value::Array(a), Value::Array(b)) => {
+                    if a.len() != b.len() {
+                        return false;
+                    }
+                    stack.extend(a.iter().zip(b.iter()));
+                }
+                (Value::Str(a), Value::Str(b)) if a == b => {}
+                (Value::Num(a), Value::Num(b)) if a == b => {}
+                (Value::Bool(a), Value::Bool(b)) if a == b => {}
+                (Value::Null, Value::Null) => {}
+                _ => return false,
+            }
+        }
+        return true;
+    }
+}
+
+/// `value["key"]` returns `Value::Null` when the value is not an object or
+/// has no such key.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        return self.get(key).unwrap_or(&NULL);
+    }
+}
+
+/// `value[3]` returns `Value::Null` when the value is not an array or the
+/// index is out of range.
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        return self.get_index(index).unwrap_or(&NULL);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Value {
+        Value::Object(vec![
+            ("name".to_string(), Value::Str("json".to_string())),
+            (
+                "list".to_string(),
+                Value::Array(vec![
+                    Value::Num(Number::from(1)),
+                    Value::Bool(true),
+                    Value::Null,
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_accessors() {
+        let value = sample();
+        assert!(value.is_object());
+        assert_eq!(value.len(), 2);
+        assert_eq!(value.keys().collect::<Vec<&str>>(), vec!["name", "list"]);
+        assert_eq!(value.get("name").and_then(|v| v.as_str()), Some("json"));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(
+            value["list"].get_index(0).unwrap().as_number(),
+            Some(&Number::from(1))
+        );
+        assert_eq!(value["list"][0].as_i64(), Some(1));
+        assert_eq!(value["list"][0].as_f64(), Some(1.0));
+        assert_eq!(value["name"].as_u64(), None);
+        assert_eq!(value["list"][1].as_bool(), Some(true));
+        assert!(value["list"][2].is_null());
+        assert_eq!(value["list"].iter().count(), 3);
+    }
+
+    #[test]
+    fn test_index_missing() {
+        let value = sample();
+        assert_eq!(value["missing"], Value::Null);
+        assert_eq!(value["list"][10], Value::Null);
+        assert_eq!(value[0], Value::Null);
+        assert_eq!(value["name"]["nested"], Value::Null);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut value = sample();
+        *value.get_mut("name").unwrap() = Value::Bool(false);
+        *value.get_mut("list").unwrap().get_index_mut(0).unwrap() = Value::Null;
+        assert_eq!(value["name"], Value::Bool(false));
+        assert_eq!(value["list"][0], Value::Null);
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        // 再帰しないので、スレッドのスタックより深くてもよい
+        let mut value = Value::Null;
+        for _ in 0..1_000_000 {
+            value = Value::Object(vec![("a".to_string(), Value::Array(vec![value]))]);
+        }
+        let copy = value.clone();
+        assert!(copy == value);
+        let mut other = Value::Bool(true);
+        for _ in 0..1_000_000 {
+            other = Value::Object(vec![("a".to_string(), Value::Array(vec![other]))]);
+        }
+        assert!(other != value);
+        value.dispose();
+        copy.dispose();
+        other.dispose();
+    }
+
+    #[test]
+    fn test_clone_and_eq() {
+        let value = sample();
+        assert_eq!(value.clone(), value);
+        assert_ne!(value, value["list"]);
+        assert_ne!(value["list"], Value::Array(vec![Value::Null]));
+        assert_ne!(
+            Value::Object(vec![("a".to_string(), Value::Null)]),
+            Value::Object(vec![("b".to_string(), Value::Null)])
+        );
+    }
+}
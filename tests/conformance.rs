@@ -0,0 +1,121 @@
+//! Runs the documents under `tests/conformance` through the tokenizer and
+//! parser. File names follow JSONTestSuite:
+//!
+//! - `y_*.json` must be accepted
+//! - `n_*.json` must be rejected in strict mode
+//! - `i_*.json` may go either way but must not panic
+//!
+//! The streaming reader must agree with the parser on every case.
+use std::fs;
+use std::path::PathBuf;
+
+use json_formatter::error::Error;
+use json_formatter::options::{FormatOptions, ParseOptions};
+use json_formatter::parser::{self, Node};
+use json_formatter::stream;
+use json_formatter::value::Value;
+
+fn cases(prefix: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let mut cases = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            name.starts_with(prefix) && name.ends_with(".json")
+        })
+        .map(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            (name, fs::read(&path).unwrap())
+        })
+        .collect::<Vec<(String, Vec<u8>)>>();
+    cases.sort();
+    assert!(!cases.is_empty());
+    return cases;
+}
+
+fn parse(bytes: &[u8], options: &ParseOptions) -> Result<Value, Error> {
+    return parser::parse_bytes_with(bytes, options);
+}
+
+#[test]
+fn test_accepted() {
+    for (name, bytes) in cases("y_") {
+        for options in [ParseOptions::strict(), ParseOptions::default()] {
+            match parse(&bytes, &options) {
+                Ok(value) => {
+                    // 出力した結果も同じ値として読み直せる
+                    let printed = value.print_node();
+                    assert_eq!(
+                        parser::parse_str_with(&printed, &options).unwrap(),
+                        value,
+                        "{} does not round-trip",
+                        name
+                    );
+                }
+                Err(err) => panic!("{} was rejected: {}", name, err),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rejected() {
+    for (name, bytes) in cases("n_") {
+        if let Ok(value) = parse(&bytes, &ParseOptions::strict()) {
+            panic!("{} was accepted as {:?}", name, value);
+        }
+        // strict でなければ受け付けるものもあるが、パニックはしない
+        let _ = parse(&bytes, &ParseOptions::default());
+    }
+}
+
+#[test]
+fn test_implementation_defined() {
+    for (_, bytes) in cases("i_") {
+        let _ = parse(&bytes, &ParseOptions::strict());
+        let _ = parse(&bytes, &ParseOptions::default());
+    }
+}
+
+#[test]
+fn test_stream_agrees_with_parser() {
+    for prefix in ["y_", "n_", "i_"] {
+        for (name, bytes) in cases(prefix) {
+            let options = ParseOptions::strict();
+            let mut out = Vec::new();
+            let streamed = stream::format_stream(
+                bytes.as_slice(),
+                &mut out,
+                &FormatOptions::default(),
+                &options,
+            );
+            let compact = FormatOptions {
+                compact: true,
+                ..FormatOptions::default()
+            };
+            let mut compact_out = Vec::new();
+            let compact_streamed =
+                stream::format_stream(bytes.as_slice(), &mut compact_out, &compact, &options);
+            match parse(&bytes, &options) {
+                Ok(value) => {
+                    assert!(streamed.is_ok(), "{} was rejected by the stream", name);
+                    assert_eq!(
+                        String::from_utf8(out).unwrap(),
+                        value.format_node("  ", &mut 0) + "\n",
+                        "{}",
+                        name
+                    );
+                    assert!(compact_streamed.is_ok(), "{}", name);
+                    assert_eq!(
+                        String::from_utf8(compact_out).unwrap(),
+                        value.print_node() + "\n",
+                        "{}",
+                        name
+                    );
+                }
+                _ => assert!(streamed.is_err(), "{} was accepted by the stream", name),
+            }
+        }
+    }
+}